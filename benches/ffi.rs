@@ -0,0 +1,301 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rust_verkle::*;
+use std::ffi::CString;
+use tempfile::{Builder, TempDir};
+
+// Leaves in the trie before every benchmark
+const PREFILLED: u64 = 10_000;
+const BULK_SIZE: u64 = 1_000;
+const PROOF_SIZES: [u64; 3] = [1, 100, 1000];
+
+#[derive(Clone, Copy)]
+enum Variant {
+    MemoryTest,
+    MemoryLagrange,
+    MemoryReadOnlyTest,
+    MemoryReadOnlyLagrange,
+    RocksdbTest,
+    RocksdbLagrange,
+    RocksdbReadOnlyTest,
+    RocksdbReadOnlyLagrange,
+}
+
+const VARIANTS: [Variant; 8] = [
+    Variant::MemoryTest,
+    Variant::MemoryLagrange,
+    Variant::MemoryReadOnlyTest,
+    Variant::MemoryReadOnlyLagrange,
+    Variant::RocksdbTest,
+    Variant::RocksdbLagrange,
+    Variant::RocksdbReadOnlyTest,
+    Variant::RocksdbReadOnlyLagrange,
+];
+
+impl Variant {
+    fn name(&self) -> &'static str {
+        match self {
+            Variant::MemoryTest => "memory_test",
+            Variant::MemoryLagrange => "memory_lagrange",
+            Variant::MemoryReadOnlyTest => "memory_read_only_test",
+            Variant::MemoryReadOnlyLagrange => "memory_read_only_lagrange",
+            Variant::RocksdbTest => "rocksdb_test",
+            Variant::RocksdbLagrange => "rocksdb_lagrange",
+            Variant::RocksdbReadOnlyTest => "rocksdb_read_only_test",
+            Variant::RocksdbReadOnlyLagrange => "rocksdb_read_only_lagrange",
+        }
+    }
+
+    fn database_scheme(&self) -> DatabaseScheme {
+        match self {
+            Variant::MemoryTest
+            | Variant::MemoryLagrange
+            | Variant::MemoryReadOnlyTest
+            | Variant::MemoryReadOnlyLagrange => DatabaseScheme::MemoryDb,
+            _ => DatabaseScheme::RocksDb,
+        }
+    }
+
+    fn commit_scheme(&self) -> CommitScheme {
+        match self {
+            Variant::MemoryTest
+            | Variant::MemoryReadOnlyTest
+            | Variant::RocksdbTest
+            | Variant::RocksdbReadOnlyTest => CommitScheme::TestCommitment,
+            _ => CommitScheme::PrecomputeLagrange,
+        }
+    }
+
+    fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Variant::MemoryReadOnlyTest
+                | Variant::MemoryReadOnlyLagrange
+                | Variant::RocksdbReadOnlyTest
+                | Variant::RocksdbReadOnlyLagrange
+        )
+    }
+}
+
+// A trie of the variant holding `PREFILLED` leaves. Read-only variants get the leaves in the
+// base database, so that reads go through the overlay
+struct Fixture {
+    _dir: TempDir,
+    db: *mut Database,
+    trie: *mut VerkleTrie,
+    // The database and trie under the overlay of read-only variants
+    base: Option<(*mut Database, *mut VerkleTrie)>,
+    // Offset of the next fresh key, so that inserts always add new leaves
+    next_key: u64,
+}
+
+fn key(i: u64) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    key[..8].copy_from_slice(&i.wrapping_mul(0x9e37_79b9_7f4a_7c15).to_be_bytes());
+    key[31] = (i % 7) as u8;
+    key
+}
+
+fn value(i: u64) -> [u8; 32] {
+    let mut value = [0u8; 32];
+    value[..8].copy_from_slice(&i.to_le_bytes());
+    value
+}
+
+fn keys_values(start: u64, len: u64) -> (Vec<[u8; 32]>, Vec<[u8; 32]>) {
+    (
+        (start..start + len).map(key).collect(),
+        (start..start + len).map(value).collect(),
+    )
+}
+
+impl Fixture {
+    fn new(variant: Variant) -> Fixture {
+        let dir = Builder::new().tempdir().unwrap();
+        let db_path = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let base_db = create_verkle_db(variant.database_scheme(), db_path.as_ptr());
+        let base_trie = create_trie_from_db(variant.commit_scheme(), base_db);
+
+        let (keys, vals) = keys_values(0, PREFILLED);
+        verkle_trie_insert_multiple(base_trie, keys.as_ptr(), vals.as_ptr(), keys.len());
+        verkle_trie_flush(base_trie);
+
+        let (db, trie, base) = if variant.is_read_only() {
+            let ro_db = create_read_only_verkle_db(base_db);
+            let ro_trie = create_trie_from_db(variant.commit_scheme(), ro_db);
+            (ro_db, ro_trie, Some((base_db, base_trie)))
+        } else {
+            (base_db, base_trie, None)
+        };
+        Fixture {
+            _dir: dir,
+            db,
+            trie,
+            base,
+            next_key: PREFILLED,
+        }
+    }
+
+    fn fresh_keys(&mut self, len: u64) -> (Vec<[u8; 32]>, Vec<[u8; 32]>) {
+        let batch = keys_values(self.next_key, len);
+        self.next_key += len;
+        batch
+    }
+}
+
+// The overlay is freed before the database under it
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        verkle_trie_free(self.trie);
+        verkle_db_free(self.db);
+        if let Some((base_db, base_trie)) = self.base {
+            verkle_trie_free(base_trie);
+            verkle_db_free(base_db);
+        }
+    }
+}
+
+fn bench_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    group.sample_size(10);
+    for variant in VARIANTS {
+        let mut fixture = Fixture::new(variant);
+        group.bench_function(BenchmarkId::new("single", variant.name()), |b| {
+            b.iter_batched(
+                || fixture.fresh_keys(1),
+                |(keys, vals)| verkle_trie_insert(fixture.trie, keys[0].as_ptr(), vals[0].as_ptr()),
+                BatchSize::SmallInput,
+            );
+        });
+        group.bench_function(BenchmarkId::new("bulk_1000", variant.name()), |b| {
+            b.iter_batched(
+                || fixture.fresh_keys(BULK_SIZE),
+                |(keys, vals)| {
+                    verkle_trie_insert_multiple(
+                        fixture.trie,
+                        keys.as_ptr(),
+                        vals.as_ptr(),
+                        keys.len(),
+                    )
+                },
+                BatchSize::SmallInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+fn bench_get(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get");
+    for variant in VARIANTS {
+        let fixture = Fixture::new(variant);
+        let keys: Vec<_> = (0..PREFILLED).map(key).collect();
+        let mut i = 0;
+        group.bench_function(BenchmarkId::from_parameter(variant.name()), |b| {
+            b.iter(|| {
+                i = (i + 1) % keys.len();
+                let value = verkle_trie_get(fixture.trie, keys[i].as_ptr());
+                // The value is returned boxed
+                unsafe { Box::from_raw(value as *mut [u8; 32]) }
+            });
+        });
+    }
+    group.finish();
+}
+
+fn bench_proofs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("proof");
+    group.sample_size(10);
+    for variant in VARIANTS {
+        let fixture = Fixture::new(variant);
+        for n in PROOF_SIZES {
+            let (keys, vals) = keys_values(0, n);
+            let parameter = format!("{}/{}", variant.name(), n);
+            group.bench_function(BenchmarkId::new("create", &parameter), |b| {
+                b.iter(|| {
+                    let proof = get_verkle_proof_multiple(fixture.trie, keys.as_ptr(), keys.len());
+                    verkle_proof_free(proof);
+                });
+            });
+
+            let proof_ptr = get_verkle_proof_multiple(fixture.trie, keys.as_ptr(), keys.len());
+            let proof = unsafe { &*proof_ptr };
+            group.bench_function(BenchmarkId::new("verify", &parameter), |b| {
+                b.iter(|| {
+                    let verification = verify_verkle_proof_multiple(
+                        fixture.trie,
+                        proof.ptr,
+                        proof.len,
+                        keys.as_ptr(),
+                        vals.as_ptr(),
+                        keys.len(),
+                    );
+                    assert_eq!(verification, 1);
+                });
+            });
+            verkle_proof_free(proof_ptr);
+        }
+    }
+    group.finish();
+}
+
+fn bench_flush(c: &mut Criterion) {
+    let mut group = c.benchmark_group("flush_1000");
+    group.sample_size(10);
+    for variant in VARIANTS {
+        let mut fixture = Fixture::new(variant);
+        group.bench_function(BenchmarkId::from_parameter(variant.name()), |b| {
+            b.iter_batched(
+                || {
+                    let (keys, vals) = fixture.fresh_keys(BULK_SIZE);
+                    verkle_trie_insert_multiple(
+                        fixture.trie,
+                        keys.as_ptr(),
+                        vals.as_ptr(),
+                        keys.len(),
+                    );
+                },
+                |_| verkle_trie_flush(fixture.trie),
+                BatchSize::PerIteration,
+            );
+        });
+    }
+    group.finish();
+}
+
+// Read-only tries are used to execute blocks speculatively: changes go into the overlay and
+// are thrown away afterwards
+fn bench_read_only_overlay(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_only_overlay");
+    group.sample_size(10);
+    for variant in VARIANTS.iter().filter(|variant| variant.is_read_only()) {
+        let mut fixture = Fixture::new(*variant);
+        group.bench_function(BenchmarkId::from_parameter(variant.name()), |b| {
+            b.iter_batched(
+                || fixture.fresh_keys(BULK_SIZE),
+                |(keys, vals)| {
+                    verkle_trie_insert_multiple(
+                        fixture.trie,
+                        keys.as_ptr(),
+                        vals.as_ptr(),
+                        keys.len(),
+                    );
+                    verkle_trie_flush(fixture.trie);
+                    clear_temp_changes_read_only_db(fixture.db);
+                    verkle_trie_clear(fixture.trie);
+                },
+                BatchSize::SmallInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_insert,
+    bench_get,
+    bench_proofs,
+    bench_flush,
+    bench_read_only_overlay
+);
+criterion_main!(benches);
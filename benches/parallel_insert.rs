@@ -0,0 +1,86 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rust_verkle::*;
+use std::ffi::CString;
+use tempfile::Builder;
+
+const BATCH_SIZES: [usize; 3] = [10_000, 50_000, 100_000];
+
+fn keys_values(n: usize) -> (Vec<[u8; 32]>, Vec<[u8; 32]>) {
+    let mut keys = Vec::with_capacity(n);
+    let mut vals = Vec::with_capacity(n);
+    for i in 0..n as u64 {
+        // Spread the keys like hashed keys, with a few leaves per stem
+        let mut key = [0u8; 32];
+        key[..8].copy_from_slice(&(i / 4).wrapping_mul(0x9e37_79b9_7f4a_7c15).to_be_bytes());
+        key[31] = (i % 4) as u8;
+        let mut value = [0u8; 32];
+        value[..8].copy_from_slice(&i.to_le_bytes());
+        keys.push(key);
+        vals.push(value);
+    }
+    (keys, vals)
+}
+
+fn memory_trie() -> *mut VerkleTrie {
+    let db_path = CString::new("").unwrap();
+    let db = create_verkle_db(DatabaseScheme::MemoryDb, db_path.as_ptr());
+    create_trie_from_db(CommitScheme::PrecomputeLagrange, db)
+}
+
+fn bench_insert_multiple(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_multiple");
+    group.sample_size(10);
+    for n in BATCH_SIZES {
+        let (keys, vals) = keys_values(n);
+        for (name, threshold) in [("sequential", usize::MAX), ("parallel", 0)] {
+            group.bench_with_input(BenchmarkId::new(name, n), &n, |b, _| {
+                b.iter_batched(
+                    memory_trie,
+                    |trie| {
+                        verkle_trie_insert_multiple_with_threshold(
+                            trie,
+                            keys.as_ptr(),
+                            vals.as_ptr(),
+                            n,
+                            threshold,
+                        )
+                    },
+                    BatchSize::PerIteration,
+                );
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_flush(c: &mut Criterion) {
+    let mut group = c.benchmark_group("flush_rocksdb");
+    group.sample_size(10);
+    for n in BATCH_SIZES {
+        let (keys, vals) = keys_values(n);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, _| {
+            b.iter_batched(
+                || {
+                    let dir = Builder::new().tempdir().unwrap();
+                    let db_path = CString::new(dir.path().to_str().unwrap()).unwrap();
+                    let db = create_verkle_db(DatabaseScheme::RocksDb, db_path.as_ptr());
+                    let trie = create_trie_from_db(CommitScheme::PrecomputeLagrange, db);
+                    verkle_trie_insert_multiple_with_threshold(
+                        trie,
+                        keys.as_ptr(),
+                        vals.as_ptr(),
+                        n,
+                        0,
+                    );
+                    (dir, trie)
+                },
+                |(_dir, trie)| verkle_trie_flush(trie),
+                BatchSize::PerIteration,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_insert_multiple, bench_flush);
+criterion_main!(benches);
@@ -0,0 +1,235 @@
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use syn::{Fields, FnArg, Item, Lit, Pat, ReturnType, Type, Visibility};
+
+// Generates the bindings of the exported API from the Rust definitions in src/lib.rs, so
+// consumers never hand-write signatures:
+//   rust_verkle.h     C header, with cbindgen
+//   RustVerkle.g.cs   C# P/Invoke declarations
+// Build scripts only write to OUT_DIR, `make bindings` copies them to bindings/
+
+const HEADER_FILE: &str = "rust_verkle.h";
+const CSHARP_FILE: &str = "RustVerkle.g.cs";
+const CSHARP_NAMESPACE: &str = "RustVerkle";
+const LIBRARY_NAME: &str = "rust_verkle";
+
+// Handle types, see src/handle.rs. cbindgen declares them as incomplete structs, C# passes
+// them as plain pointers
+const OPAQUE_TYPES: [&str; 4] = ["VerkleTrie", "Database", "VerkleBulkLoader", "TrieIterator"];
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-changed=src");
+
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let bindings_dir = Path::new(&out_dir);
+
+    let config = cbindgen::Config::from_file(Path::new(&crate_dir).join("cbindgen.toml"))
+        .expect("could not read cbindgen.toml");
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("could not generate the C header")
+        .write_to_file(bindings_dir.join(HEADER_FILE));
+
+    let lib_rs = fs::read_to_string(Path::new(&crate_dir).join("src/lib.rs")).unwrap();
+    let file = syn::parse_file(&lib_rs).expect("could not parse src/lib.rs");
+    fs::write(bindings_dir.join(CSHARP_FILE), csharp_bindings(&file))
+        .expect("could not write the C# bindings");
+}
+
+fn has_attr(attrs: &[syn::Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident(name))
+}
+
+fn is_repr_c(attrs: &[syn::Attribute]) -> bool {
+    attrs
+        .iter()
+        .any(|attr| attr.path.is_ident("repr") && attr.tokens.to_string().replace(' ', "") == "(C)")
+}
+
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Path(path) => path.path.segments.last().unwrap().ident.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn csharp_identifier(name: &str) -> String {
+    const KEYWORDS: [&str; 12] = [
+        "base", "checked", "event", "fixed", "in", "lock", "object", "out", "params", "ref",
+        "string", "value",
+    ];
+    if KEYWORDS.contains(&name) {
+        format!("@{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn csharp_type(ty: &Type) -> String {
+    match ty {
+        Type::Ptr(ptr) => match &*ptr.elem {
+            // Keys, values and byte buffers are all passed as a pointer to their first byte
+            Type::Array(_) => "byte*".to_string(),
+            elem => {
+                let name = type_name(elem);
+                if OPAQUE_TYPES.contains(&name.as_str()) || name == "c_void" {
+                    "IntPtr".to_string()
+                } else if name == "c_char" {
+                    "byte*".to_string()
+                } else {
+                    format!("{}*", csharp_type(elem))
+                }
+            }
+        },
+        Type::Array(_) => panic!("arrays cannot be passed by value over FFI"),
+        // Callbacks, passed with Marshal.GetFunctionPointerForDelegate
+        Type::BareFn(_) => "IntPtr".to_string(),
+        _ => match type_name(ty).as_str() {
+            "Option" | "ExternalSink" => "IntPtr".to_string(),
+            "u8" => "byte".to_string(),
+            "u16" => "ushort".to_string(),
+            "u32" => "uint".to_string(),
+            "u64" => "ulong".to_string(),
+            "i32" => "int".to_string(),
+            "i64" => "long".to_string(),
+            "usize" => "nuint".to_string(),
+            name => name.to_string(),
+        },
+    }
+}
+
+fn csharp_bindings(file: &syn::File) -> String {
+    let mut out = String::new();
+    writeln!(out, "// <auto-generated>").unwrap();
+    writeln!(out, "// Generated by build.rs from src/lib.rs, do not edit").unwrap();
+    writeln!(out, "// </auto-generated>").unwrap();
+    writeln!(out, "using System;").unwrap();
+    writeln!(out, "using System.Runtime.InteropServices;").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "namespace {}", CSHARP_NAMESPACE).unwrap();
+    writeln!(out, "{{").unwrap();
+
+    for item in file.items.iter() {
+        match item {
+            Item::Enum(item) if is_repr_c(&item.attrs) => {
+                if item
+                    .variants
+                    .iter()
+                    .any(|variant| !matches!(variant.fields, Fields::Unit))
+                {
+                    continue;
+                }
+                writeln!(out, "    public enum {}", item.ident).unwrap();
+                writeln!(out, "    {{").unwrap();
+                for variant in item.variants.iter() {
+                    writeln!(out, "        {},", variant.ident).unwrap();
+                }
+                writeln!(out, "    }}").unwrap();
+                writeln!(out).unwrap();
+            }
+            Item::Struct(item) if is_repr_c(&item.attrs) => {
+                writeln!(out, "    [StructLayout(LayoutKind.Sequential)]").unwrap();
+                writeln!(out, "    public unsafe struct {}", item.ident).unwrap();
+                writeln!(out, "    {{").unwrap();
+                for field in item.fields.iter() {
+                    let name = field.ident.as_ref().unwrap().to_string();
+                    writeln!(
+                        out,
+                        "        public {} {};",
+                        csharp_type(&field.ty),
+                        csharp_identifier(&name)
+                    )
+                    .unwrap();
+                }
+                writeln!(out, "    }}").unwrap();
+                writeln!(out).unwrap();
+            }
+            _ => {}
+        }
+    }
+
+    writeln!(out, "    public static unsafe class NativeMethods").unwrap();
+    writeln!(out, "    {{").unwrap();
+    writeln!(
+        out,
+        "        public const string LibraryName = \"{}\";",
+        LIBRARY_NAME
+    )
+    .unwrap();
+    for item in file.items.iter() {
+        if let Item::Const(item) = item {
+            if !matches!(item.vis, Visibility::Public(_)) {
+                continue;
+            }
+            if let syn::Expr::Lit(syn::ExprLit {
+                lit: Lit::Int(value),
+                ..
+            }) = &*item.expr
+            {
+                writeln!(
+                    out,
+                    "        public const {} {} = {};",
+                    csharp_type(&item.ty),
+                    item.ident,
+                    value.base10_digits()
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    for item in file.items.iter() {
+        let function = match item {
+            Item::Fn(function)
+                if has_attr(&function.attrs, "no_mangle")
+                    && matches!(function.vis, Visibility::Public(_)) =>
+            {
+                function
+            }
+            _ => continue,
+        };
+        let returns = match &function.sig.output {
+            ReturnType::Default => "void".to_string(),
+            ReturnType::Type(_, ty) => csharp_type(ty),
+        };
+        let params: Vec<String> = function
+            .sig
+            .inputs
+            .iter()
+            .map(|input| match input {
+                FnArg::Typed(arg) => {
+                    let name = match &*arg.pat {
+                        Pat::Ident(ident) => ident.ident.to_string(),
+                        _ => panic!("unsupported argument in {}", function.sig.ident),
+                    };
+                    format!("{} {}", csharp_type(&arg.ty), csharp_identifier(&name))
+                }
+                FnArg::Receiver(_) => unreachable!(),
+            })
+            .collect();
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "        [DllImport(LibraryName, CallingConvention = CallingConvention.Cdecl)]"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "        public static extern {} {}({});",
+            returns,
+            function.sig.ident,
+            params.join(", ")
+        )
+        .unwrap();
+    }
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    out
+}
@@ -0,0 +1,28 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use rust_verkle::proof::range::RangeProof;
+use rust_verkle::proof::{json, read_proof, ssz};
+use rust_verkle::*;
+
+type Convert = extern "C" fn(*const u8, usize) -> *mut Proof;
+
+// Every decoder of proofs received from peers rejects garbage instead of panicking
+fuzz_target!(|data: &[u8]| {
+    let _ = read_proof(data);
+    let _ = RangeProof::read(data);
+    let _ = ssz::decode_verkle_proof(data);
+    let _ = ssz::decode_execution_witness(data);
+    if let Ok(proof_json) = std::str::from_utf8(data) {
+        let _ = json::decode_verkle_proof(proof_json);
+    }
+
+    let conversions: [Convert; 4] = [
+        verkle_proof_to_ssz,
+        verkle_proof_from_ssz,
+        verkle_proof_to_json,
+        verkle_proof_from_json,
+    ];
+    for convert in conversions.iter() {
+        let _ = convert(data.as_ptr(), data.len());
+    }
+});
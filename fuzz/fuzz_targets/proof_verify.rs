@@ -0,0 +1,176 @@
+#![no_main]
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use once_cell::sync::Lazy;
+use rust_verkle::*;
+use std::ffi::CString;
+use std::slice;
+
+// Verification of proofs built by peers. Inputs are either raw bytes or a valid proof of
+// some of the keys of a fixed trie with a few bytes flipped, which gets much deeper into
+// the verifier than random bytes do
+
+const LEAVES: u8 = 64;
+
+fn key(i: u8) -> [u8; 32] {
+    // Four leaves per stem
+    let mut key = [(i / 4).wrapping_mul(37); 32];
+    key[31] = i % 4;
+    key
+}
+
+fn value(i: u8) -> [u8; 32] {
+    [i; 32]
+}
+
+// The trie is built once per process, inputs never modify it
+static TRIE: Lazy<usize> = Lazy::new(|| {
+    let path = CString::new("").unwrap();
+    let db = create_verkle_db(DatabaseScheme::MemoryDb, path.as_ptr());
+    let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+    let keys: Vec<_> = (0..LEAVES).map(key).collect();
+    let vals: Vec<_> = (0..LEAVES).map(value).collect();
+    verkle_trie_insert_multiple(trie, keys.as_ptr(), vals.as_ptr(), keys.len());
+    trie as usize
+});
+
+#[derive(Arbitrary, Debug, Clone, Copy)]
+enum Format {
+    Bytes,
+    Ssz,
+    Json,
+    Witness,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Input {
+    Raw {
+        format: Format,
+        proof: Vec<u8>,
+        keys: Vec<[u8; 32]>,
+        vals: Vec<[u8; 32]>,
+    },
+    RawRange {
+        root: Vec<u8>,
+        first_key: [u8; 32],
+        leaves: Vec<([u8; 32], [u8; 32])>,
+        proof: Vec<u8>,
+    },
+    Mutated {
+        format: Format,
+        leaves: Vec<u8>,
+        flips: Vec<(u16, u8)>,
+    },
+}
+
+fn create(trie: *mut VerkleTrie, format: Format, keys: &[[u8; 32]]) -> Vec<u8> {
+    let proof = match format {
+        Format::Bytes => get_verkle_proof_multiple(trie, keys.as_ptr(), keys.len()),
+        Format::Ssz => get_verkle_proof_ssz(trie, keys.as_ptr(), keys.len()),
+        Format::Json => get_verkle_proof_json(trie, keys.as_ptr(), keys.len()),
+        Format::Witness => get_execution_witness_ssz(trie, keys.as_ptr(), keys.len()),
+    };
+    let proof = unsafe { &*proof };
+    unsafe { slice::from_raw_parts(proof.ptr, proof.len) }.to_vec()
+}
+
+fn verify(
+    trie: *mut VerkleTrie,
+    format: Format,
+    proof: &[u8],
+    keys: &[[u8; 32]],
+    vals: &[[u8; 32]],
+) -> u8 {
+    let len = std::cmp::min(keys.len(), vals.len());
+    match format {
+        Format::Bytes if len == 1 => verify_verkle_proof(
+            trie,
+            proof.as_ptr(),
+            proof.len(),
+            keys[0].as_ptr(),
+            vals[0].as_ptr(),
+        ),
+        Format::Bytes => verify_verkle_proof_multiple(
+            trie,
+            proof.as_ptr(),
+            proof.len(),
+            keys.as_ptr(),
+            vals.as_ptr(),
+            len,
+        ),
+        Format::Ssz => verify_verkle_proof_ssz(
+            trie,
+            proof.as_ptr(),
+            proof.len(),
+            keys.as_ptr(),
+            vals.as_ptr(),
+            len,
+        ),
+        Format::Json => verify_verkle_proof_json(
+            trie,
+            proof.as_ptr(),
+            proof.len(),
+            keys.as_ptr(),
+            vals.as_ptr(),
+            len,
+        ),
+        Format::Witness => verify_execution_witness_ssz(trie, proof.as_ptr(), proof.len()),
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let trie = *TRIE as *mut VerkleTrie;
+    match input {
+        Input::Raw {
+            format,
+            proof,
+            keys,
+            vals,
+        } => {
+            verify(trie, format, &proof, &keys, &vals);
+        }
+        Input::RawRange {
+            root,
+            first_key,
+            leaves,
+            proof,
+        } => {
+            let keys: Vec<_> = leaves.iter().map(|(key, _)| *key).collect();
+            let vals: Vec<_> = leaves.iter().map(|(_, val)| *val).collect();
+            verify_range_proof(
+                root.as_ptr(),
+                root.len(),
+                first_key.as_ptr(),
+                keys.as_ptr(),
+                vals.as_ptr(),
+                keys.len(),
+                proof.as_ptr(),
+                proof.len(),
+            );
+        }
+        Input::Mutated {
+            format,
+            leaves,
+            flips,
+        } => {
+            let mut leaves: Vec<_> = leaves.into_iter().map(|i| i % LEAVES).collect();
+            leaves.sort_unstable();
+            leaves.dedup();
+            if leaves.is_empty() {
+                return;
+            }
+            let keys: Vec<_> = leaves.iter().copied().map(key).collect();
+            let vals: Vec<_> = leaves.iter().copied().map(value).collect();
+            let mut proof = create(trie, format, &keys);
+            if flips.is_empty() {
+                assert_eq!(verify(trie, format, &proof, &keys, &vals), 1);
+                return;
+            }
+            for (position, mask) in flips {
+                let position = position as usize % proof.len();
+                proof[position] ^= mask;
+            }
+            verify(trie, format, &proof, &keys, &vals);
+        }
+    }
+});
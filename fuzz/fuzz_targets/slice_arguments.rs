@@ -0,0 +1,26 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use rust_verkle::*;
+
+// The helpers that turn FFI arguments into owned values, with any length the buffer allows,
+// including zero
+fuzz_target!(|data: &[u8]| {
+    let bytes = proof_ptr_to_proof_vec(data.as_ptr(), data.len());
+    assert_eq!(bytes, data);
+
+    let arrays: Vec<[u8; 32]> = data
+        .chunks_exact(32)
+        .map(|chunk| {
+            let mut array = [0u8; 32];
+            array.copy_from_slice(chunk);
+            array
+        })
+        .collect();
+    for len in 0..=arrays.len() {
+        let vector = get_vector_from_slice_argument(arrays.as_ptr(), len);
+        assert_eq!(vector, &arrays[..len]);
+    }
+    if let Some(first) = arrays.first() {
+        assert_eq!(get_array_from_slice_argument(first.as_ptr()), *first);
+    }
+});
@@ -0,0 +1,122 @@
+#![no_main]
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use once_cell::sync::Lazy;
+use rust_verkle::*;
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::slice;
+
+// Random sequences of inserts and proofs, checked against a map of the expected leaves
+//
+// A single trie is reused for every input: it is flushed once while empty, and every input
+// ends with `verkle_trie_clear`, which drops its changes and brings the trie back to empty
+
+static TRIE: Lazy<usize> = Lazy::new(|| {
+    let path = CString::new("").unwrap();
+    let db = create_verkle_db(DatabaseScheme::MemoryDb, path.as_ptr());
+    let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+    verkle_trie_flush(trie);
+    trie as usize
+});
+
+// Keys are built from a small stem and suffix so that inputs hit the same stems and slots
+#[derive(Arbitrary, Debug, Clone, Copy)]
+struct Key {
+    stem: u8,
+    // Byte of the stem that `stem` is written to, the rest is zero
+    position: u8,
+    suffix: u8,
+}
+
+impl Key {
+    fn bytes(&self) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        key[self.position as usize % 31] = self.stem;
+        key[31] = self.suffix;
+        key
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Insert(Key, [u8; 32]),
+    InsertMultiple(Vec<(Key, [u8; 32])>),
+    Get(Key),
+    Prove(Vec<Key>),
+}
+
+fn root_hash(trie: *mut VerkleTrie) -> Vec<u8> {
+    unsafe { slice::from_raw_parts(get_root_hash(trie), 32) }.to_vec()
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let trie = *TRIE as *mut VerkleTrie;
+    let empty_root = root_hash(trie);
+    let mut leaves: BTreeMap<[u8; 32], [u8; 32]> = BTreeMap::new();
+
+    for op in ops {
+        match op {
+            Op::Insert(key, value) => {
+                let key = key.bytes();
+                verkle_trie_insert(trie, key.as_ptr(), value.as_ptr());
+                leaves.insert(key, value);
+            }
+            Op::InsertMultiple(batch) => {
+                let keys: Vec<_> = batch.iter().map(|(key, _)| key.bytes()).collect();
+                let vals: Vec<_> = batch.iter().map(|(_, value)| *value).collect();
+                verkle_trie_insert_multiple(trie, keys.as_ptr(), vals.as_ptr(), keys.len());
+                leaves.extend(keys.into_iter().zip(vals.into_iter()));
+            }
+            Op::Get(key) => {
+                let key = key.bytes();
+                let value = verkle_trie_get(trie, key.as_ptr());
+                let value = if value.is_null() {
+                    None
+                } else {
+                    Some(unsafe { *(value as *const [u8; 32]) })
+                };
+                assert_eq!(value, leaves.get(&key).copied());
+            }
+            Op::Prove(keys) => {
+                let mut keys: Vec<_> = keys
+                    .iter()
+                    .map(Key::bytes)
+                    .filter(|key| leaves.contains_key(key))
+                    .collect();
+                keys.sort_unstable();
+                keys.dedup();
+                if keys.is_empty() {
+                    continue;
+                }
+                let vals: Vec<_> = keys.iter().map(|key| leaves[key]).collect();
+                let proof = unsafe { &*get_verkle_proof_multiple(trie, keys.as_ptr(), keys.len()) };
+                let verified = verify_verkle_proof_multiple(
+                    trie,
+                    proof.ptr,
+                    proof.len,
+                    keys.as_ptr(),
+                    vals.as_ptr(),
+                    keys.len(),
+                );
+                assert_eq!(verified, 1);
+
+                // The same proof does not hold for other values
+                let mut wrong_vals = vals.clone();
+                wrong_vals[0][0] ^= 1;
+                let verified = verify_verkle_proof_multiple(
+                    trie,
+                    proof.ptr,
+                    proof.len,
+                    keys.as_ptr(),
+                    wrong_vals.as_ptr(),
+                    keys.len(),
+                );
+                assert_eq!(verified, 0);
+            }
+        }
+    }
+
+    verkle_trie_clear(trie);
+    assert_eq!(root_hash(trie), empty_root);
+});
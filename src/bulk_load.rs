@@ -0,0 +1,316 @@
+use crate::commitment::{compute_branch_meta, compute_stem_meta};
+use crate::database::generics::GenericMemoryDb;
+use crate::database::traits::FallibleBatchDB;
+use bandersnatch::{EdwardsProjective, Fr};
+use verkle_db::{BareMetalKVDb, BatchWriter};
+use verkle_trie::committer::Committer;
+use verkle_trie::database::generic::{GenericBatchDB, GenericBatchWriter};
+use verkle_trie::database::memory_db::MemoryDb;
+use verkle_trie::database::{BranchMeta, ReadOnlyHigherDb, StemMeta, WriteOnlyHigherDb};
+
+// Number of nodes written to the batch before it is flushed to storage
+const NODES_PER_BATCH: usize = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BulkLoadError {
+    // Keys must be strictly increasing, within a chunk and across chunks
+    UnsortedKeys { previous: [u8; 32], key: [u8; 32] },
+    // The root computed from the loaded leaves is not the expected one
+    RootMismatch,
+    // The storage already holds a trie
+    NonEmptyTarget,
+    // A stem was added without any leaf
+    EmptyStem([u8; 31]),
+    // The storage could not write a batch, the loader cannot be used any more
+    WriteFailed(String),
+}
+
+// Storage that a bulk load writes into, nodes are collected in a batch written in one go
+pub trait BulkLoadTarget {
+    type Batch: WriteOnlyHigherDb;
+
+    fn new_batch() -> Self::Batch;
+
+    fn write_batch(&mut self, batch: Self::Batch) -> Result<(), BulkLoadError>;
+
+    // Whether the storage already holds a trie
+    fn has_root(&self) -> bool;
+}
+
+impl<S: FallibleBatchDB + BareMetalKVDb> BulkLoadTarget for GenericBatchDB<S> {
+    type Batch = GenericBatchWriter<S::BatchWrite>;
+
+    fn new_batch() -> Self::Batch {
+        GenericBatchWriter {
+            inner: S::BatchWrite::new(),
+        }
+    }
+
+    fn write_batch(&mut self, batch: Self::Batch) -> Result<(), BulkLoadError> {
+        self.inner
+            .try_write_batch(&batch.inner)
+            .map_err(BulkLoadError::WriteFailed)
+    }
+
+    fn has_root(&self) -> bool {
+        self.get_branch_meta(&[]).is_some()
+    }
+}
+
+impl BulkLoadTarget for GenericMemoryDb<MemoryDb> {
+    type Batch = MemoryDb;
+
+    fn new_batch() -> Self::Batch {
+        MemoryDb::new()
+    }
+
+    fn write_batch(&mut self, batch: Self::Batch) -> Result<(), BulkLoadError> {
+        self.inner.leaf_table.extend(batch.leaf_table);
+        self.inner.stem_table.extend(batch.stem_table);
+        self.inner.branch_table.extend(batch.branch_table);
+        Ok(())
+    }
+
+    fn has_root(&self) -> bool {
+        self.get_branch_meta(&[]).is_some()
+    }
+}
+
+// A branch whose children are still being collected
+struct OpenBranch {
+    children: Vec<(u8, Fr)>,
+}
+
+// Builds a trie bottom-up from leaves that arrive sorted by key and writes the nodes
+// straight into the storage, computing every commitment exactly once
+//
+// A stem can only be placed once the next stem is known: its depth is one more than the
+// longest prefix it shares with either of its neighbours. `branches[i]` is the open branch
+// at depth i along the path of the last placed stem, branches deeper than the prefix shared
+// with the next stem are complete and get committed as soon as that stem is placed
+//
+// Nodes are written every `NODES_PER_BATCH` nodes to bound memory, but the root branch and
+// the last batch are only written once the root is known to match. A load that fails after
+// writing some batches leaves nodes that no root points to, the storage should be discarded
+// Once a write fails, every later call returns that error
+pub struct BulkLoader<S: BulkLoadTarget + 'static, C: Committer + 'static> {
+    storage: &'static mut S,
+    // Borrowed, so that loaders share a committer whose tables are expensive to build
+    committer: &'static C,
+    writer: S::Batch,
+    nodes_in_batch: usize,
+    // Error of an intermediate write, which happens in the middle of adding leaves
+    write_error: Option<BulkLoadError>,
+
+    branches: Vec<OpenBranch>,
+    // Leaves of the stem that is being collected
+    current_stem: Option<([u8; 31], Vec<(u8, [u8; 32])>)>,
+    // Stem that is complete but not placed yet, with the prefix it shares with the previous stem
+    pending_stem: Option<([u8; 31], StemMeta, usize)>,
+    last_stem: Option<[u8; 31]>,
+    last_key: Option<[u8; 32]>,
+}
+
+impl<S: BulkLoadTarget + 'static, C: Committer + 'static> BulkLoader<S, C> {
+    // The storage must not hold a trie yet, the load would overwrite its root
+    pub fn new(storage: &'static mut S, committer: &'static C) -> Result<Self, BulkLoadError> {
+        if storage.has_root() {
+            return Err(BulkLoadError::NonEmptyTarget);
+        }
+        Ok(BulkLoader {
+            storage,
+            committer,
+            writer: S::new_batch(),
+            nodes_in_batch: 0,
+            write_error: None,
+            branches: vec![OpenBranch {
+                children: Vec::new(),
+            }],
+            current_stem: None,
+            pending_stem: None,
+            last_stem: None,
+            last_key: None,
+        })
+    }
+
+    // The whole chunk is checked before any of it is added, a rejected chunk leaves the
+    // loader as it was
+    pub fn add_leaves(&mut self, leaves: &[([u8; 32], [u8; 32])]) -> Result<(), BulkLoadError> {
+        self.check_writes()?;
+        let mut previous = self.last_key;
+        for (key, _) in leaves {
+            if let Some(previous) = previous {
+                if *key <= previous {
+                    return Err(BulkLoadError::UnsortedKeys {
+                        previous,
+                        key: *key,
+                    });
+                }
+            }
+            previous = Some(*key);
+        }
+
+        for (key, value) in leaves {
+            self.last_key = Some(*key);
+
+            let mut stem = [0u8; 31];
+            stem.copy_from_slice(&key[..31]);
+            match &mut self.current_stem {
+                Some((current, suffixes)) if *current == stem => suffixes.push((key[31], *value)),
+                _ => {
+                    self.finish_stem();
+                    self.current_stem = Some((stem, vec![(key[31], *value)]));
+                }
+            }
+        }
+        self.check_writes()
+    }
+
+    // Add every leaf of `stem` at once, suffixes in increasing order, and return the
+    // commitments of the stem
+    pub fn add_stem(
+        &mut self,
+        stem: [u8; 31],
+        leaves: &[(u8, [u8; 32])],
+    ) -> Result<StemMeta, BulkLoadError> {
+        if leaves.is_empty() {
+            return Err(BulkLoadError::EmptyStem(stem));
+        }
+        let leaves: Vec<_> = leaves
+            .iter()
+            .map(|(suffix, value)| {
+                let mut key = [0u8; 32];
+                key[..31].copy_from_slice(&stem);
+                key[31] = *suffix;
+                (key, *value)
+            })
+            .collect();
+        self.add_leaves(&leaves)?;
+        let meta = self.finish_stem().ok_or(BulkLoadError::EmptyStem(stem))?;
+        self.check_writes()?;
+        Ok(meta)
+    }
+
+    // Place the remaining stems, commit every open branch up to the root and flush
+    // Returns the root commitment if it matches `expected_root`, nothing more is written
+    // otherwise
+    pub fn finish(
+        mut self,
+        expected_root: Option<EdwardsProjective>,
+    ) -> Result<EdwardsProjective, BulkLoadError> {
+        self.finish_stem();
+        if let Some((stem, meta, shared_prev)) = self.pending_stem.take() {
+            self.place_stem(stem, meta, shared_prev, 0);
+        }
+        self.close_branches(&[], 0);
+        self.check_writes()?;
+
+        let root = self.branches.pop().unwrap();
+        let meta = self.branch_meta(&root.children);
+        if let Some(expected) = expected_root {
+            if expected != meta.commitment {
+                return Err(BulkLoadError::RootMismatch);
+            }
+        }
+        self.writer.insert_branch(vec![], meta, 0);
+
+        let writer = std::mem::replace(&mut self.writer, S::new_batch());
+        self.storage.write_batch(writer)?;
+        Ok(meta.commitment)
+    }
+
+    fn check_writes(&self) -> Result<(), BulkLoadError> {
+        match &self.write_error {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    // The leaves of the current stem are complete, write them and compute the stem commitment
+    fn finish_stem(&mut self) -> Option<StemMeta> {
+        let (stem, leaves) = self.current_stem.take()?;
+
+        let meta = self.stem_meta(stem, &leaves);
+        for (suffix, value) in leaves {
+            let mut key = [0u8; 32];
+            key[..31].copy_from_slice(&stem);
+            key[31] = suffix;
+            self.writer.insert_leaf(key, value, 0);
+        }
+        self.count_nodes(1);
+
+        let shared_prev = self
+            .last_stem
+            .map(|last| shared_prefix(&last, &stem))
+            .unwrap_or(0);
+        if let Some((pending, pending_meta, pending_shared_prev)) = self.pending_stem.take() {
+            self.place_stem(pending, pending_meta, pending_shared_prev, shared_prev);
+        }
+        self.pending_stem = Some((stem, meta, shared_prev));
+        self.last_stem = Some(stem);
+        Some(meta)
+    }
+
+    fn place_stem(
+        &mut self,
+        stem: [u8; 31],
+        meta: StemMeta,
+        shared_prev: usize,
+        shared_next: usize,
+    ) {
+        let depth = std::cmp::max(shared_prev, shared_next) + 1;
+        while self.branches.len() < depth {
+            self.branches.push(OpenBranch {
+                children: Vec::new(),
+            });
+        }
+
+        self.writer.insert_stem(stem, meta, depth as u8);
+        self.writer
+            .add_stem_as_branch_child(stem[..depth].to_vec(), stem, depth as u8);
+        self.branches[depth - 1]
+            .children
+            .push((stem[depth - 1], meta.hash_stem_commitment));
+        self.count_nodes(2);
+
+        // The next stem diverges after `shared_next` bytes, nothing else goes below that
+        self.close_branches(&stem, shared_next);
+    }
+
+    fn close_branches(&mut self, path: &[u8], keep: usize) {
+        while self.branches.len() > keep + 1 {
+            let depth = self.branches.len() - 1;
+            let branch = self.branches.pop().unwrap();
+            let meta = self.branch_meta(&branch.children);
+            self.writer
+                .insert_branch(path[..depth].to_vec(), meta, depth as u8);
+            self.branches[depth - 1]
+                .children
+                .push((path[depth - 1], meta.hash_commitment));
+            self.count_nodes(1);
+        }
+    }
+
+    fn count_nodes(&mut self, nodes: usize) {
+        self.nodes_in_batch += nodes;
+        if self.nodes_in_batch >= NODES_PER_BATCH {
+            let writer = std::mem::replace(&mut self.writer, S::new_batch());
+            if let Err(error) = self.storage.write_batch(writer) {
+                self.write_error.get_or_insert(error);
+            }
+            self.nodes_in_batch = 0;
+        }
+    }
+
+    fn stem_meta(&self, stem: [u8; 31], leaves: &[(u8, [u8; 32])]) -> StemMeta {
+        compute_stem_meta(self.committer, stem, leaves)
+    }
+
+    fn branch_meta(&self, children: &[(u8, Fr)]) -> BranchMeta {
+        compute_branch_meta(self.committer, children)
+    }
+}
+
+fn shared_prefix(a: &[u8; 31], b: &[u8; 31]) -> usize {
+    a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count()
+}
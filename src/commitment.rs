@@ -0,0 +1,71 @@
+use ark_ff::{One, PrimeField, Zero};
+use bandersnatch::Fr;
+use verkle_trie::committer::Committer;
+use verkle_trie::database::{BranchMeta, StemMeta};
+use verkle_trie::group_to_field;
+
+// Node commitments computed from scratch, without going through the trie
+// Used where nodes are written or checked directly in storage
+
+// Same commitment scheme as the trie uses on insert:
+// C1/C2 commit to the lower/upper 128 suffixes, each value split in two 16 byte halves
+// with the lower half marked by adding 2^128, and the stem commitment is
+// commit([1, stem, hash(C1), hash(C2)])
+pub(crate) fn compute_stem_meta<C: Committer>(
+    committer: &C,
+    stem: [u8; 31],
+    leaves: &[(u8, [u8; 32])],
+) -> StemMeta {
+    let two_pow_128 = Fr::from(u128::MAX) + Fr::one();
+    let mut c_1_evals = vec![Fr::zero(); 256];
+    let mut c_2_evals = vec![Fr::zero(); 256];
+    for (suffix, value) in leaves {
+        let low = Fr::from_le_bytes_mod_order(&value[..16]) + two_pow_128;
+        let high = Fr::from_le_bytes_mod_order(&value[16..]);
+        let index = 2 * (*suffix as usize % 128);
+        let evals = if *suffix < 128 {
+            &mut c_1_evals
+        } else {
+            &mut c_2_evals
+        };
+        evals[index] = low;
+        evals[index + 1] = high;
+    }
+
+    let c_1 = committer.commit_lagrange(&c_1_evals);
+    let c_2 = committer.commit_lagrange(&c_2_evals);
+    let hash_c1 = group_to_field(&c_1);
+    let hash_c2 = group_to_field(&c_2);
+
+    let mut stem_evals = vec![Fr::zero(); 256];
+    stem_evals[0] = Fr::one();
+    stem_evals[1] = Fr::from_le_bytes_mod_order(&stem);
+    stem_evals[2] = hash_c1;
+    stem_evals[3] = hash_c2;
+    let stem_commitment = committer.commit_lagrange(&stem_evals);
+
+    StemMeta {
+        c_1,
+        hash_c1,
+        c_2,
+        hash_c2,
+        stem_commitment,
+        hash_stem_commitment: group_to_field(&stem_commitment),
+    }
+}
+
+// A branch commits to the hashes of the commitments of its children
+pub(crate) fn compute_branch_meta<C: Committer>(
+    committer: &C,
+    children: &[(u8, Fr)],
+) -> BranchMeta {
+    let mut evals = vec![Fr::zero(); 256];
+    for (index, hash) in children {
+        evals[*index as usize] = *hash;
+    }
+    let commitment = committer.commit_lagrange(&evals);
+    BranchMeta {
+        commitment,
+        hash_commitment: group_to_field(&commitment),
+    }
+}
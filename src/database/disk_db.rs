@@ -1,11 +1,32 @@
+use crate::database::key_value_ro::MemoryBatchDB;
+#[cfg(feature = "rocks_db")]
 use crate::database::key_value_ro::ReadOnlyKVDB;
-use crate::database::traits::{ReadOnlyDB, DB};
+#[cfg(feature = "rocks_db")]
+use crate::database::rocks_kv_db::RocksKVDb;
+#[cfg(feature = "rocks_db")]
+use crate::database::schema::OpenError;
+use crate::database::traits::FallibleBatchDB;
+#[cfg(feature = "rocks_db")]
+use crate::database::traits::{CommitError, ReadOnlyDB, DB};
 use crate::database::verkle_db::VerkleTreeDb;
-use verkle_db::{BareMetalDiskDb, BatchDB, BatchWriter, RocksDb};
+#[cfg(feature = "failpoints")]
+use crate::failpoint::{flush_fault, FlushFault};
+use crate::proof::point_to_bytes;
+#[cfg(feature = "rocks_db")]
+use crate::RocksDbOptions;
+use rayon::prelude::*;
+use std::convert::TryInto;
+#[cfg(feature = "rocks_db")]
+use verkle_db::BareMetalDiskDb;
+use verkle_db::{BareMetalKVDb, BatchWriter};
 use verkle_trie::database::generic::{GenericBatchDB, GenericBatchWriter};
-use verkle_trie::database::{BranchChild, Flush, WriteOnlyHigherDb};
+use verkle_trie::database::memory_db::MemoryDb;
+use verkle_trie::database::{BranchChild, Flush, ReadOnlyHigherDb, WriteOnlyHigherDb};
 
-pub type VerkleRocksDB = GenericBatchDB<RocksDb>;
+// The RocksDB backend, only built with the `rocks_db` feature
+#[cfg(feature = "rocks_db")]
+pub type VerkleRocksDB = GenericBatchDB<RocksKVDb>;
+#[cfg(feature = "rocks_db")]
 impl DB for VerkleRocksDB {
     fn create_db(path: &str) -> Self {
         let _db = GenericBatchDB::from_path(path);
@@ -13,7 +34,22 @@ impl DB for VerkleRocksDB {
     }
 }
 
-pub type VerkleReadOnlyRocksDB = GenericBatchDB<ReadOnlyKVDB<GenericBatchDB<RocksDb>>>;
+#[cfg(feature = "rocks_db")]
+impl VerkleRocksDB {
+    pub fn create_db_with_options(path: &str, options: &RocksDbOptions) -> Result<Self, OpenError> {
+        let _db = RocksKVDb::open(path, options)?;
+        Ok(GenericBatchDB { inner: _db })
+    }
+
+    pub fn create_namespace(&self, name: &[u8]) -> Option<Self> {
+        let _db = self.inner.namespace(name)?;
+        Some(GenericBatchDB { inner: _db })
+    }
+}
+
+#[cfg(feature = "rocks_db")]
+pub type VerkleReadOnlyRocksDB = GenericBatchDB<ReadOnlyKVDB<GenericBatchDB<RocksKVDb>>>;
+#[cfg(feature = "rocks_db")]
 impl ReadOnlyDB for VerkleReadOnlyRocksDB {
     type DbObject = VerkleRocksDB;
 
@@ -22,49 +58,186 @@ impl ReadOnlyDB for VerkleReadOnlyRocksDB {
         GenericBatchDB { inner: _db }
     }
 
+    fn create_from_overlay(parent: &'static mut Self) -> Self {
+        let _db = ReadOnlyKVDB::from_overlay(&mut parent.inner);
+        GenericBatchDB { inner: _db }
+    }
+
     fn clear_temp_changes(&mut self) {
         self.inner.temp.clear();
     }
+
+    fn merge_into_parent(&mut self) -> bool {
+        self.inner.merge_into_parent()
+    }
+
+    fn commit_temp_changes(&mut self) -> Result<(), CommitError> {
+        self.inner.commit_into_db()
+    }
+
+    fn changed_leaves(&self) -> Vec<([u8; 32], Option<[u8; 32]>, [u8; 32])> {
+        self.inner.changed_leaves()
+    }
 }
 
-impl<S: BatchDB> Flush for VerkleTreeDb<GenericBatchDB<S>> {
-    // flush the batch to the storage
-    fn flush(&mut self) {
-        let writer = S::BatchWrite::new();
-        let mut w = GenericBatchWriter { inner: writer };
+// Key of the record written in the same batch as the nodes of a block
+// Since the batch is atomic, the record always describes the state found in storage
+pub const FLUSH_RECORD_KEY: &[u8] = b"\xffflush_record";
 
-        let now = std::time::Instant::now();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlushRecord {
+    pub block: u64,
+    // Serialized root commitment after the block
+    pub root: [u8; 32],
+}
 
-        for (key, value) in self.batch.leaf_table.iter() {
-            w.insert_leaf(*key, *value, 0);
+impl FlushRecord {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.block.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&self.root);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<FlushRecord> {
+        if bytes.len() != 40 {
+            return None;
         }
+        Some(FlushRecord {
+            block: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            root: bytes[8..].try_into().unwrap(),
+        })
+    }
+}
 
-        for (key, meta) in self.batch.stem_table.iter() {
+// The record of the last block flushed with `try_flush`
+pub fn last_flush_record<S: BareMetalKVDb>(db: &GenericBatchDB<S>) -> Option<FlushRecord> {
+    db.inner
+        .fetch(FLUSH_RECORD_KEY)
+        .and_then(|bytes| FlushRecord::from_bytes(&bytes))
+}
+
+type SerializedBatch = GenericBatchWriter<MemoryBatchDB>;
+
+fn new_serialized_batch() -> SerializedBatch {
+    GenericBatchWriter {
+        inner: MemoryBatchDB::new(),
+    }
+}
+
+// Move the entries of the smaller batch into the larger one
+fn merge_batches(a: MemoryBatchDB, b: MemoryBatchDB) -> MemoryBatchDB {
+    let (mut larger, smaller) = if a.inner.len() >= b.inner.len() {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    larger.inner.extend(smaller.inner);
+    larger
+}
+
+// Encode the nodes of the batch into a storage batch, spread over the thread pool
+// Most of the time goes into compressing the commitments, which is independent per node
+// Every thread encodes into a batch of its own, the batches are then merged without copying
+// the encoded keys and values
+fn serialize_batch(batch: &MemoryDb) -> MemoryBatchDB {
+    let leaves = batch
+        .leaf_table
+        .par_iter()
+        .fold(new_serialized_batch, |mut w, (key, value)| {
+            w.insert_leaf(*key, *value, 0);
+            w
+        });
+
+    let stems = batch
+        .stem_table
+        .par_iter()
+        .fold(new_serialized_batch, |mut w, (key, meta)| {
             w.insert_stem(*key, *meta, 0);
+            w
+        });
+
+    let branches =
+        batch
+            .branch_table
+            .par_iter()
+            .fold(new_serialized_batch, |mut w, (branch_id, b_child)| {
+                let branch_id = branch_id.clone();
+                match b_child {
+                    BranchChild::Stem(stem_id) => {
+                        w.add_stem_as_branch_child(branch_id, *stem_id, 0);
+                    }
+                    BranchChild::Branch(b_meta) => {
+                        w.insert_branch(branch_id, *b_meta, 0);
+                    }
+                };
+                w
+            });
+
+    leaves
+        .chain(stems)
+        .chain(branches)
+        .map(|w| w.inner)
+        .reduce(MemoryBatchDB::new, merge_batches)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlushError {
+    Storage(String),
+}
+
+impl<S: FallibleBatchDB<BatchWrite = MemoryBatchDB>> VerkleTreeDb<GenericBatchDB<S>>
+where
+    GenericBatchDB<S>: ReadOnlyHigherDb,
+{
+    // Write the batch to storage in a single atomic write, together with the flush record of
+    // `block` if there is one. The batch and the cache are only cleared once the write
+    // succeeded, so a failed flush loses nothing and can be retried
+    pub fn try_flush(&mut self, block: Option<u64>) -> Result<(), FlushError> {
+        let mut batch = serialize_batch(&self.batch);
+
+        if let Some(block) = block {
+            if let Some(root) = self.get_branch_meta(&[]) {
+                let record = FlushRecord {
+                    block,
+                    root: point_to_bytes(&root.commitment),
+                };
+                batch.batch_put(FLUSH_RECORD_KEY, &record.to_bytes());
+            }
         }
 
-        for (branch_id, b_child) in self.batch.branch_table.iter() {
-            let branch_id = branch_id.clone();
-            match b_child {
-                BranchChild::Stem(stem_id) => {
-                    w.add_stem_as_branch_child(branch_id, *stem_id, 0);
-                }
-                BranchChild::Branch(b_meta) => {
-                    w.insert_branch(branch_id, *b_meta, 0);
-                }
-            };
+        #[cfg(feature = "failpoints")]
+        match flush_fault() {
+            FlushFault::CrashBeforeWrite => std::process::abort(),
+            FlushFault::FailWrite => {
+                return Err(FlushError::Storage("injected write failure".to_string()))
+            }
+            _ => {}
         }
 
-        let num_items = self.batch.num_items();
-        println!(
-            "write to batch time: {}, item count : {}",
-            now.elapsed().as_millis(),
-            num_items
-        );
+        self.storage
+            .inner
+            .try_write_batch(&batch)
+            .map_err(FlushError::Storage)?;
 
-        self.storage.flush(w.inner);
+        #[cfg(feature = "failpoints")]
+        if flush_fault() == FlushFault::CrashAfterWrite {
+            std::process::abort();
+        }
 
         self.batch.clear();
         self.cache.clear();
+        Ok(())
+    }
+}
+
+impl<S: FallibleBatchDB<BatchWrite = MemoryBatchDB>> Flush for VerkleTreeDb<GenericBatchDB<S>>
+where
+    GenericBatchDB<S>: ReadOnlyHigherDb,
+{
+    // flush the batch to the storage
+    // The batch is kept on failure and the next flush writes it again, callers that need to
+    // know whether the write happened use `try_flush`
+    fn flush(&mut self) {
+        let _ = self.try_flush(None);
     }
 }
@@ -0,0 +1,177 @@
+use crate::database::key_value_ro::MemoryBatchDB;
+use crate::database::traits::FallibleBatchDB;
+use crate::{proof_ptr_to_proof_vec, ExternalSink, ExternalStore, Proof};
+use std::os::raw::c_void;
+use verkle_db::{BareMetalKVDb, BatchDB};
+use verkle_trie::database::generic::GenericBatchDB;
+
+// Key value store of the host, reached through the callbacks of an `ExternalStore`
+// The nodes are written with the same keys and values as in RocksDB, so everything built on
+// `GenericBatchDB` works unchanged on top of it
+//
+// The callbacks are only called from the thread of the FFI call that needs them
+
+type GetFn = extern "C" fn(*mut c_void, *const u8, usize, *mut c_void, ExternalSink);
+type PutBatchFn = extern "C" fn(*mut c_void, *const Proof, *const Proof, usize) -> u8;
+type IteratePrefixFn = extern "C" fn(*mut c_void, *const u8, usize, *mut c_void, ExternalSink);
+type FlushFn = extern "C" fn(*mut c_void) -> u8;
+type ReleaseFn = extern "C" fn(*mut c_void);
+
+pub struct ExternalKVDb {
+    context: *mut c_void,
+    get: GetFn,
+    put_batch: PutBatchFn,
+    iterate_prefix: IteratePrefixFn,
+    flush: FlushFn,
+    release: Option<ReleaseFn>,
+}
+
+pub type VerkleExternalDB = GenericBatchDB<ExternalKVDb>;
+
+// Sinks handed to the callbacks, they copy what the host passes before it returns
+extern "C" fn collect_value(
+    sink: *mut c_void,
+    _key: *const u8,
+    _key_len: usize,
+    value: *const u8,
+    value_len: usize,
+) {
+    let out = unsafe { &mut *(sink as *mut Option<Vec<u8>>) };
+    *out = Some(proof_ptr_to_proof_vec(value, value_len));
+}
+
+extern "C" fn collect_pair(
+    sink: *mut c_void,
+    key: *const u8,
+    key_len: usize,
+    value: *const u8,
+    value_len: usize,
+) {
+    let out = unsafe { &mut *(sink as *mut Vec<(Vec<u8>, Vec<u8>)>) };
+    out.push((
+        proof_ptr_to_proof_vec(key, key_len),
+        proof_ptr_to_proof_vec(value, value_len),
+    ));
+}
+
+impl ExternalKVDb {
+    // None if one of the required callbacks is missing
+    pub fn from_store(store: &ExternalStore) -> Option<Self> {
+        Some(ExternalKVDb {
+            context: store.context,
+            get: store.get?,
+            put_batch: store.put_batch?,
+            iterate_prefix: store.iterate_prefix?,
+            flush: store.flush?,
+            release: store.release,
+        })
+    }
+
+    // Every pair whose key starts with `prefix`, in the order the host passes them
+    pub fn iterate_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut pairs: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        (self.iterate_prefix)(
+            self.context,
+            prefix.as_ptr(),
+            prefix.len(),
+            &mut pairs as *mut _ as *mut c_void,
+            collect_pair,
+        );
+        pairs
+    }
+}
+
+impl Drop for ExternalKVDb {
+    fn drop(&mut self) {
+        if let Some(release) = self.release {
+            release(self.context);
+        }
+    }
+}
+
+impl BareMetalKVDb for ExternalKVDb {
+    fn fetch(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let mut value: Option<Vec<u8>> = None;
+        (self.get)(
+            self.context,
+            key.as_ptr(),
+            key.len(),
+            &mut value as *mut _ as *mut c_void,
+            collect_value,
+        );
+        value
+    }
+
+    // An external database is created from the callbacks of the host, see `from_store`
+    // Without a host, the store holds nothing and rejects every write
+    fn new() -> Self {
+        ExternalKVDb {
+            context: std::ptr::null_mut(),
+            get: detached_get,
+            put_batch: detached_put_batch,
+            iterate_prefix: detached_get,
+            flush: detached_flush,
+            release: None,
+        }
+    }
+}
+
+extern "C" fn detached_get(
+    _context: *mut c_void,
+    _key: *const u8,
+    _key_len: usize,
+    _sink: *mut c_void,
+    _emit: ExternalSink,
+) {
+}
+
+extern "C" fn detached_put_batch(
+    _context: *mut c_void,
+    _keys: *const Proof,
+    _values: *const Proof,
+    _len: usize,
+) -> u8 {
+    0
+}
+
+extern "C" fn detached_flush(_context: *mut c_void) -> u8 {
+    0
+}
+
+impl BatchDB for ExternalKVDb {
+    type BatchWrite = MemoryBatchDB;
+
+    // The trait cannot report the failure, callers that need it use `try_write_batch`
+    fn flush(&mut self, batch: Self::BatchWrite) {
+        let _ = self.try_write_batch(&batch);
+    }
+}
+
+impl FallibleBatchDB for ExternalKVDb {
+    // The host stores the whole batch or nothing, then makes it durable
+    fn try_write_batch(&mut self, batch: &Self::BatchWrite) -> Result<(), String> {
+        let (keys, values): (Vec<Proof>, Vec<Proof>) = batch
+            .inner
+            .iter()
+            .map(|(key, value)| {
+                (
+                    Proof {
+                        ptr: key.as_ptr(),
+                        len: key.len(),
+                    },
+                    Proof {
+                        ptr: value.as_ptr(),
+                        len: value.len(),
+                    },
+                )
+            })
+            .unzip();
+        if (self.put_batch)(self.context, keys.as_ptr(), values.as_ptr(), keys.len()) == 0 {
+            return Err("the external store rejected the batch".to_string());
+        }
+        if (self.flush)(self.context) == 0 {
+            return Err("the external store could not flush".to_string());
+        }
+        Ok(())
+    }
+}
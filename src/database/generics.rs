@@ -1,7 +1,6 @@
 use std::collections::HashMap;
 use std::convert::TryInto;
 use std::mem::transmute;
-use verkle_db::RocksDb;
 use verkle_db::{BareMetalDiskDb, BareMetalKVDb, BatchDB, BatchWriter};
 use verkle_trie::database::generic::GenericBatchDB;
 use verkle_trie::database::generic::GenericBatchWriter;
@@ -1,8 +1,8 @@
+use crate::database::traits::{CommitError, FallibleBatchDB};
 use std::collections::HashMap;
-use std::convert::TryFrom;
+use std::convert::{TryFrom, TryInto};
 use std::mem::transmute;
 use std::slice;
-use verkle_db::RocksDb;
 use verkle_db::{BareMetalDiskDb, BareMetalKVDb, BatchDB, BatchWriter};
 use verkle_trie::database::generic::GenericBatchDB;
 use verkle_trie::database::generic::GenericBatchWriter;
@@ -11,10 +11,15 @@ use verkle_trie::database::{
     BranchChild, BranchMeta, Flush, ReadOnlyHigherDb, StemMeta, WriteOnlyHigherDb,
 };
 
+// Table marker of the leaves in `GenericBatchDB`
+const LEAF_TABLE_MARKER: u8 = 0;
+
 pub struct ReadOnlyKVDB<Storage: 'static> {
     // The underlying key value database
     // We will not be updating this
     pub db: &'static mut Storage,
+    // The overlay this one is stacked on, if any. Reads go through it down to `db`
+    pub parent: Option<&'static mut ReadOnlyKVDB<Storage>>,
     // This stores the key-value pairs that we need to insert into the storage
     pub temp: HashMap<Vec<u8>, Vec<u8>>,
 }
@@ -23,17 +28,92 @@ impl<S: BareMetalDiskDb> ReadOnlyKVDB<S> {
     pub fn from_db(db: &'static mut S) -> Self {
         ReadOnlyKVDB {
             db,
+            parent: None,
             temp: HashMap::new(),
         }
     }
 }
 
+impl<S> ReadOnlyKVDB<S> {
+    pub fn from_overlay(parent: &'static mut ReadOnlyKVDB<S>) -> Self {
+        // Every overlay of the stack refers to the same database, none of them writes to it
+        let db: &'static mut S = unsafe { &mut *(&mut *parent.db as *mut S) };
+        ReadOnlyKVDB {
+            db,
+            parent: Some(parent),
+            temp: HashMap::new(),
+        }
+    }
+
+    // Move the changes of this overlay into its parent, false if it has none
+    pub fn merge_into_parent(&mut self) -> bool {
+        match &mut self.parent {
+            Some(parent) => {
+                parent.temp.extend(self.temp.drain());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T: FallibleBatchDB<BatchWrite = MemoryBatchDB>> ReadOnlyKVDB<GenericBatchDB<T>> {
+    // Write the changes of this overlay into the database in a single batch, then drop them
+    // An overlay stacked on another one was built on the changes below it, which the database
+    // does not have, so it has to be merged first
+    pub fn commit_into_db(&mut self) -> Result<(), CommitError> {
+        if self.parent.is_some() {
+            return Err(CommitError::Stacked);
+        }
+        let batch = MemoryBatchDB {
+            inner: std::mem::take(&mut self.temp),
+        };
+        if let Err(err) = self.db.inner.try_write_batch(&batch) {
+            // Nothing was written, the changes stay in the overlay
+            self.temp = batch.inner;
+            return Err(CommitError::Storage(err));
+        }
+        Ok(())
+    }
+}
+
+impl<S: BareMetalKVDb + BareMetalDiskDb> ReadOnlyKVDB<S> {
+    // Leaves written into this overlay with a value other than the one below it, in key order
+    // Leaves are stored by `GenericBatchDB` under their key prefixed with the leaf table marker
+    pub fn changed_leaves(&self) -> Vec<([u8; 32], Option<[u8; 32]>, [u8; 32])> {
+        let mut changes: Vec<_> = self
+            .temp
+            .iter()
+            .filter(|(key, _)| key.len() == 33 && key[0] == LEAF_TABLE_MARKER)
+            .filter_map(|(key, value)| {
+                let old_value = match &self.parent {
+                    Some(parent) => parent.fetch(key),
+                    None => self.db.fetch(key),
+                };
+                if old_value.as_ref() == Some(value) {
+                    return None;
+                }
+                let leaf_key: [u8; 32] = key[1..].try_into().expect("leaf key is 32 bytes");
+                let old_value = old_value.map(|old| to_leaf_value(&old));
+                Some((leaf_key, old_value, to_leaf_value(value)))
+            })
+            .collect();
+        changes.sort_unstable_by_key(|(key, _, _)| *key);
+        changes
+    }
+}
+
+fn to_leaf_value(bytes: &[u8]) -> [u8; 32] {
+    bytes.try_into().expect("leaf value is 32 bytes")
+}
+
 impl<S: BareMetalDiskDb> BareMetalDiskDb for ReadOnlyKVDB<S> {
     fn from_path<P: AsRef<std::path::Path>>(path: P) -> Self {
         let _db = S::from_path(path);
         let db: &mut S = unsafe { transmute(Box::new(_db)) };
         ReadOnlyKVDB {
             db,
+            parent: None,
             temp: HashMap::new(),
         }
     }
@@ -46,7 +126,10 @@ impl<S: BareMetalKVDb + BareMetalDiskDb> BareMetalKVDb for ReadOnlyKVDB<S> {
         if let Some(val) = self.temp.get(key) {
             return Some(val.to_vec());
         }
-        self.db.fetch(key)
+        match &self.parent {
+            Some(parent) => parent.fetch(key),
+            None => self.db.fetch(key),
+        }
     }
     // Create a database given the default path
     fn new() -> Self {
@@ -86,3 +169,15 @@ impl<S: BatchDB> BatchDB for ReadOnlyKVDB<S> {
         self.temp.extend(batch.inner.into_iter());
     }
 }
+
+impl<S: BatchDB> FallibleBatchDB for ReadOnlyKVDB<S> {
+    fn try_write_batch(&mut self, batch: &Self::BatchWrite) -> Result<(), String> {
+        self.temp.extend(
+            batch
+                .inner
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        Ok(())
+    }
+}
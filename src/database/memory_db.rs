@@ -1,6 +1,6 @@
 use crate::database::generics::{GenericMemoryDb, MemDB};
 use crate::database::memory_ro::ReadOnlyMemoryDB;
-use crate::database::traits::{ReadOnlyDB, DB};
+use crate::database::traits::{CommitError, ReadOnlyDB, DB};
 use crate::database::verkle_db::VerkleTreeDb;
 use verkle_trie::database::memory_db::MemoryDb;
 use verkle_trie::database::{BranchChild, Flush, ReadOnlyHigherDb, WriteOnlyHigherDb};
@@ -32,15 +32,30 @@ impl ReadOnlyDB for VerkleReadOnlyMemoryDB {
         GenericMemoryDb { inner: _db }
     }
 
+    fn create_from_overlay(parent: &'static mut Self) -> Self {
+        let _db = ReadOnlyMemoryDB::from_overlay(&mut parent.inner);
+        GenericMemoryDb { inner: _db }
+    }
+
     fn clear_temp_changes(&mut self) {
         self.inner.temp.clear();
     }
+
+    fn merge_into_parent(&mut self) -> bool {
+        self.inner.merge_into_parent()
+    }
+
+    fn commit_temp_changes(&mut self) -> Result<(), CommitError> {
+        self.inner.commit_into_db()
+    }
+
+    fn changed_leaves(&self) -> Vec<([u8; 32], Option<[u8; 32]>, [u8; 32])> {
+        self.inner.changed_leaves()
+    }
 }
 
 impl<T: ReadOnlyHigherDb + WriteOnlyHigherDb> Flush for VerkleTreeDb<GenericMemoryDb<T>> {
     fn flush(&mut self) {
-        let now = std::time::Instant::now();
-
         for (key, value) in self.batch.leaf_table.iter() {
             self.storage.insert_leaf(*key, *value, 0);
         }
@@ -62,13 +77,6 @@ impl<T: ReadOnlyHigherDb + WriteOnlyHigherDb> Flush for VerkleTreeDb<GenericMemo
             };
         }
 
-        let num_items = self.batch.num_items();
-        println!(
-            "write to batch time: {}, item count : {}",
-            now.elapsed().as_millis(),
-            num_items
-        );
-
         self.batch.clear();
         self.cache.clear();
     }
@@ -1,8 +1,8 @@
 use crate::database::generics::MemDB;
+use crate::database::traits::CommitError;
 use std::collections::HashMap;
 use std::convert::TryInto;
 use std::mem::transmute;
-use verkle_db::RocksDb;
 use verkle_db::{BareMetalDiskDb, BareMetalKVDb, BatchDB, BatchWriter};
 use verkle_trie::database::generic::GenericBatchDB;
 use verkle_trie::database::generic::GenericBatchWriter;
@@ -13,6 +13,8 @@ use verkle_trie::database::{
 
 pub struct ReadOnlyMemoryDB<Storage: 'static> {
     pub db: &'static mut Storage,
+    // The overlay this one is stacked on, if any. Reads go through it down to `db`
+    pub parent: Option<&'static mut ReadOnlyMemoryDB<Storage>>,
     pub temp: MemoryDb,
 }
 
@@ -20,9 +22,35 @@ impl<S> ReadOnlyMemoryDB<S> {
     pub fn from_db(db: &'static mut S) -> Self {
         ReadOnlyMemoryDB {
             db,
+            parent: None,
             temp: MemoryDb::new(),
         }
     }
+
+    pub fn from_overlay(parent: &'static mut ReadOnlyMemoryDB<S>) -> Self {
+        // Every overlay of the stack refers to the same database, none of them writes to it
+        let db: &'static mut S = unsafe { &mut *(&mut *parent.db as *mut S) };
+        ReadOnlyMemoryDB {
+            db,
+            parent: Some(parent),
+            temp: MemoryDb::new(),
+        }
+    }
+
+    // Move the changes of this overlay into its parent, false if it has none
+    pub fn merge_into_parent(&mut self) -> bool {
+        let parent = match &mut self.parent {
+            Some(parent) => parent,
+            None => return false,
+        };
+        parent.temp.leaf_table.extend(self.temp.leaf_table.drain());
+        parent.temp.stem_table.extend(self.temp.stem_table.drain());
+        parent
+            .temp
+            .branch_table
+            .extend(self.temp.branch_table.drain());
+        true
+    }
 }
 
 impl<S: MemDB> ReadOnlyMemoryDB<S> {
@@ -30,6 +58,7 @@ impl<S: MemDB> ReadOnlyMemoryDB<S> {
         let db: &mut S = unsafe { transmute(Box::new(S::new())) };
         ReadOnlyMemoryDB {
             db,
+            parent: None,
             temp: MemoryDb::new(),
         }
     }
@@ -39,12 +68,64 @@ impl<S: MemDB> ReadOnlyMemoryDB<S> {
     }
 }
 
+impl<S: WriteOnlyHigherDb> ReadOnlyMemoryDB<S> {
+    // Insert the changes of this overlay into the database, then drop them
+    // An overlay stacked on another one was built on the changes below it, which the database
+    // does not have, so it has to be merged first
+    pub fn commit_into_db(&mut self) -> Result<(), CommitError> {
+        if self.parent.is_some() {
+            return Err(CommitError::Stacked);
+        }
+        for (key, value) in self.temp.leaf_table.drain() {
+            self.db.insert_leaf(key, value, 0);
+        }
+        for (key, meta) in self.temp.stem_table.drain() {
+            self.db.insert_stem(key, meta, 0);
+        }
+        for (branch_id, b_child) in self.temp.branch_table.drain() {
+            match b_child {
+                BranchChild::Stem(stem_id) => {
+                    self.db.add_stem_as_branch_child(branch_id, stem_id, 0);
+                }
+                BranchChild::Branch(b_meta) => {
+                    self.db.insert_branch(branch_id, b_meta, 0);
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+impl<S: ReadOnlyHigherDb> ReadOnlyMemoryDB<S> {
+    // The overlay right below this one, or the database
+    fn base(&self) -> &dyn ReadOnlyHigherDb {
+        match &self.parent {
+            Some(parent) => &**parent,
+            None => &*self.db,
+        }
+    }
+
+    // Leaves written into this overlay with a value other than the one below it, in key order
+    pub fn changed_leaves(&self) -> Vec<([u8; 32], Option<[u8; 32]>, [u8; 32])> {
+        let base = self.base();
+        let mut changes: Vec<_> = self
+            .temp
+            .leaf_table
+            .iter()
+            .map(|(key, value)| (*key, base.get_leaf(*key), *value))
+            .filter(|(_, old_value, value)| *old_value != Some(*value))
+            .collect();
+        changes.sort_unstable_by_key(|(key, _, _)| *key);
+        changes
+    }
+}
+
 impl<S: ReadOnlyHigherDb> ReadOnlyHigherDb for ReadOnlyMemoryDB<S> {
     fn get_stem_meta(&self, stem_key: [u8; 31]) -> Option<StemMeta> {
         if let Some(val) = self.temp.get_stem_meta(stem_key) {
             return Some(val);
         }
-        self.db.get_stem_meta(stem_key)
+        self.base().get_stem_meta(stem_key)
     }
 
     fn get_branch_meta(&self, key: &[u8]) -> Option<BranchMeta> {
@@ -52,7 +133,7 @@ impl<S: ReadOnlyHigherDb> ReadOnlyHigherDb for ReadOnlyMemoryDB<S> {
             return Some(val);
         }
         // Now try the disk
-        self.db.get_branch_meta(key)
+        self.base().get_branch_meta(key)
     }
 
     fn get_leaf(&self, key: [u8; 32]) -> Option<[u8; 32]> {
@@ -60,12 +141,12 @@ impl<S: ReadOnlyHigherDb> ReadOnlyHigherDb for ReadOnlyMemoryDB<S> {
             return Some(val);
         }
         // Now try the disk
-        self.db.get_leaf(key)
+        self.base().get_leaf(key)
     }
 
     fn get_branch_children(&self, branch_id: &[u8]) -> Vec<(u8, BranchChild)> {
         let mut children: HashMap<_, _> = self
-            .db
+            .base()
             .get_branch_children(branch_id)
             .into_iter()
             .map(|(index, val)| (index, val))
@@ -89,7 +170,7 @@ impl<S: ReadOnlyHigherDb> ReadOnlyHigherDb for ReadOnlyMemoryDB<S> {
         // It's possible that they are in disk storage and that batch storage has some recent updates
         // First get the children from storage
         let mut children: HashMap<_, _> = self
-            .db
+            .base()
             .get_stem_children(stem_key)
             .into_iter()
             .map(|(index, val)| (index, val))
@@ -114,7 +195,7 @@ impl<S: ReadOnlyHigherDb> ReadOnlyHigherDb for ReadOnlyMemoryDB<S> {
             return Some(val);
         }
         // Now try the disk
-        self.db.get_branch_child(branch_id, index)
+        self.base().get_branch_child(branch_id, index)
     }
 }
 
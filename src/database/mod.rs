@@ -1,7 +1,14 @@
 pub mod disk_db;
+pub mod external_db;
 pub mod generics;
 mod key_value_ro;
 pub mod memory_db;
 mod memory_ro;
+pub(crate) mod namespace;
+pub mod redb_db;
+#[cfg(feature = "rocks_db")]
+pub mod rocks_kv_db;
+#[cfg(feature = "rocks_db")]
+pub mod schema;
 pub(crate) mod traits;
 pub mod verkle_db;
@@ -0,0 +1,37 @@
+use std::borrow::Cow;
+
+// Namespaces let several independent tries share one RocksDB or redb database. Every key
+// written in a namespace, nodes and flush record alike, is stored under the key it would have
+// on its own, prefixed with `NAMESPACE_MARKER`, the length of the name and the name
+//
+// Keys outside of every namespace start with a table marker of `GenericBatchDB` or 0xff,
+// never with `NAMESPACE_MARKER`, so scanning a table of the database never meets the nodes of
+// a namespace and versions of this library without namespaces read the database as before
+// The length keeps the prefix of a name from being the start of the prefix of another one
+// Namespaces nest, the prefix of a namespace opened in another one follows the prefix of it
+
+pub(crate) const NAMESPACE_MARKER: u8 = 3;
+
+// Prefix of the keys of the namespace `name` opened in the one whose prefix is `parent`
+// None if the name is empty or longer than 255 bytes
+pub(crate) fn namespace_prefix(parent: &[u8], name: &[u8]) -> Option<Vec<u8>> {
+    if name.is_empty() || name.len() > u8::MAX as usize {
+        return None;
+    }
+    let mut prefix = parent.to_vec();
+    prefix.push(NAMESPACE_MARKER);
+    prefix.push(name.len() as u8);
+    prefix.extend_from_slice(name);
+    Some(prefix)
+}
+
+// Key under which `key` is stored in the namespace with prefix `prefix`
+pub(crate) fn namespaced_key<'a>(prefix: &[u8], key: &'a [u8]) -> Cow<'a, [u8]> {
+    if prefix.is_empty() {
+        return Cow::Borrowed(key);
+    }
+    let mut namespaced = Vec::with_capacity(prefix.len() + key.len());
+    namespaced.extend_from_slice(prefix);
+    namespaced.extend_from_slice(key);
+    Cow::Owned(namespaced)
+}
@@ -0,0 +1,168 @@
+use crate::database::key_value_ro::{MemoryBatchDB, ReadOnlyKVDB};
+use crate::database::namespace::{namespace_prefix, namespaced_key};
+use crate::database::traits::{CommitError, FallibleBatchDB, ReadOnlyDB, DB};
+use redb::{ReadableTable, TableDefinition, TableError};
+use std::sync::Arc;
+use verkle_db::{BareMetalDiskDb, BareMetalKVDb, BatchDB};
+use verkle_trie::database::generic::GenericBatchDB;
+
+// Storage in a redb file, a pure Rust alternative to RocksDB that needs no C++ toolchain
+// The nodes are written with the same keys and values as in RocksDB, all in one table
+// A namespace of the database, see `namespace`, is a view sharing the same file
+
+const NODE_TABLE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("verkle_nodes");
+// The database is opened from a directory, as RocksDB is, and kept in this file inside it
+const FILE_NAME: &str = "verkle.redb";
+
+pub struct RedbDb {
+    pub(crate) inner: Arc<redb::Database>,
+    // Prefix of the keys of the namespace this view is in, empty outside of every namespace
+    namespace: Vec<u8>,
+}
+
+impl RedbDb {
+    // Open the database in the directory `path`, creating both if they do not exist
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, String> {
+        std::fs::create_dir_all(&path).map_err(|err| err.to_string())?;
+        let inner =
+            redb::Database::create(path.as_ref().join(FILE_NAME)).map_err(|err| err.to_string())?;
+        Ok(RedbDb {
+            inner: Arc::new(inner),
+            namespace: Vec::new(),
+        })
+    }
+
+    // A view of the same database in the namespace `name`, nested in the one of this view
+    // None if the name is empty or longer than 255 bytes
+    pub fn namespace(&self, name: &[u8]) -> Option<Self> {
+        Some(RedbDb {
+            inner: self.inner.clone(),
+            namespace: namespace_prefix(&self.namespace, name)?,
+        })
+    }
+
+    // Every pair of the namespace whose key starts with `prefix`, in key order
+    pub fn prefix_iterator(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let stored_prefix = namespaced_key(&self.namespace, prefix);
+        let txn = self.inner.begin_read().expect("could not read from redb");
+        let table = match txn.open_table(NODE_TABLE) {
+            Ok(table) => table,
+            // Nothing was flushed yet
+            Err(TableError::TableDoesNotExist(_)) => return Vec::new(),
+            Err(err) => panic!("could not open the node table: {}", err),
+        };
+        table
+            .range(&*stored_prefix..)
+            .expect("could not read from redb")
+            .map(|entry| entry.expect("could not read from redb"))
+            .map(|(key, value)| (key.value().to_vec(), value.value().to_vec()))
+            .take_while(|(key, _)| key.starts_with(&stored_prefix))
+            .map(|(key, value)| (key[self.namespace.len()..].to_vec(), value))
+            .collect()
+    }
+}
+
+impl BareMetalDiskDb for RedbDb {
+    fn from_path<P: AsRef<std::path::Path>>(path: P) -> Self {
+        match RedbDb::open(path) {
+            Ok(db) => db,
+            Err(err) => panic!("could not open redb: {}", err),
+        }
+    }
+
+    const DEFAULT_PATH: &'static str = "./db/verkle_redb";
+}
+
+impl BareMetalKVDb for RedbDb {
+    fn fetch(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let txn = self.inner.begin_read().expect("could not read from redb");
+        let table = match txn.open_table(NODE_TABLE) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return None,
+            Err(err) => panic!("could not open the node table: {}", err),
+        };
+        table
+            .get(&*namespaced_key(&self.namespace, key))
+            .expect("could not read from redb")
+            .map(|value| value.value().to_vec())
+    }
+
+    fn new() -> Self {
+        Self::from_path(Self::DEFAULT_PATH)
+    }
+}
+
+impl BatchDB for RedbDb {
+    type BatchWrite = MemoryBatchDB;
+
+    fn flush(&mut self, batch: Self::BatchWrite) {
+        self.try_write_batch(&batch)
+            .expect("could not write the batch to redb");
+    }
+}
+
+impl FallibleBatchDB for RedbDb {
+    // A redb write transaction is committed atomically, it is aborted when dropped early
+    fn try_write_batch(&mut self, batch: &Self::BatchWrite) -> Result<(), String> {
+        let txn = self.inner.begin_write().map_err(|err| err.to_string())?;
+        {
+            let mut table = txn.open_table(NODE_TABLE).map_err(|err| err.to_string())?;
+            for (key, value) in batch.inner.iter() {
+                table
+                    .insert(&*namespaced_key(&self.namespace, key), value.as_slice())
+                    .map_err(|err| err.to_string())?;
+            }
+        }
+        txn.commit().map_err(|err| err.to_string())
+    }
+}
+
+pub type VerkleRedbDB = GenericBatchDB<RedbDb>;
+impl DB for VerkleRedbDB {
+    fn create_db(path: &str) -> Self {
+        GenericBatchDB::from_path(path)
+    }
+}
+
+impl VerkleRedbDB {
+    pub fn open_db(path: &str) -> Result<Self, String> {
+        let _db = RedbDb::open(path)?;
+        Ok(GenericBatchDB { inner: _db })
+    }
+
+    pub fn create_namespace(&self, name: &[u8]) -> Option<Self> {
+        let _db = self.inner.namespace(name)?;
+        Some(GenericBatchDB { inner: _db })
+    }
+}
+
+pub type VerkleReadOnlyRedbDB = GenericBatchDB<ReadOnlyKVDB<GenericBatchDB<RedbDb>>>;
+impl ReadOnlyDB for VerkleReadOnlyRedbDB {
+    type DbObject = VerkleRedbDB;
+
+    fn create_from_db(db: &'static mut Self::DbObject) -> Self {
+        let _db = ReadOnlyKVDB::from_db(db);
+        GenericBatchDB { inner: _db }
+    }
+
+    fn create_from_overlay(parent: &'static mut Self) -> Self {
+        let _db = ReadOnlyKVDB::from_overlay(&mut parent.inner);
+        GenericBatchDB { inner: _db }
+    }
+
+    fn clear_temp_changes(&mut self) {
+        self.inner.temp.clear();
+    }
+
+    fn merge_into_parent(&mut self) -> bool {
+        self.inner.merge_into_parent()
+    }
+
+    fn commit_temp_changes(&mut self) -> Result<(), CommitError> {
+        self.inner.commit_into_db()
+    }
+
+    fn changed_leaves(&self) -> Vec<([u8; 32], Option<[u8; 32]>, [u8; 32])> {
+        self.inner.changed_leaves()
+    }
+}
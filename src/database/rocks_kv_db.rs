@@ -0,0 +1,198 @@
+use crate::database::key_value_ro::MemoryBatchDB;
+use crate::database::namespace::{namespace_prefix, namespaced_key};
+use crate::database::schema::{self, OpenError};
+use crate::database::traits::FallibleBatchDB;
+use crate::{RocksDbCompression, RocksDbOptions};
+use rocksdb::{
+    BlockBasedOptions, Cache, ColumnFamily, ColumnFamilyDescriptor, DBCompressionType, Options,
+    WriteBatch,
+};
+use std::sync::Arc;
+use verkle_db::{BareMetalDiskDb, BareMetalKVDb, BatchDB, RocksDb};
+
+// RocksDB with the leaves, the stem metadata and the branch nodes each in their own column
+// family, so that every kind of node is compacted and scanned on its own with options suited
+// to it. The keys are the ones `GenericBatchDB` writes, table marker included, anything else
+// such as the flush record or the schema version stays in the default column family
+//
+// A namespace of the database, see `namespace`, is a view sharing the same RocksDB instance
+// whose keys are stored with the prefix of the namespace, in the column family of their kind
+
+// Table markers of `GenericBatchDB`
+const LEAF_TABLE_MARKER: u8 = 0;
+const STEM_TABLE_MARKER: u8 = 1;
+const BRANCH_TABLE_MARKER: u8 = 2;
+
+const LEAF_FAMILY: &str = "leaves";
+const STEM_FAMILY: &str = "stems";
+const BRANCH_FAMILY: &str = "branches";
+
+pub struct RocksKVDb {
+    pub(crate) inner: Arc<RocksDb>,
+    // Prefix of the keys of the namespace this view is in, empty outside of every namespace
+    namespace: Vec<u8>,
+}
+
+fn family_name(marker: u8) -> Option<&'static str> {
+    match marker {
+        LEAF_TABLE_MARKER => Some(LEAF_FAMILY),
+        STEM_TABLE_MARKER => Some(STEM_FAMILY),
+        BRANCH_TABLE_MARKER => Some(BRANCH_FAMILY),
+        _ => None,
+    }
+}
+
+impl RocksKVDb {
+    // Fails if RocksDB cannot open the directory or it cannot be brought to the current
+    // schema, see `schema::upgrade`
+    pub fn open<P: AsRef<std::path::Path>>(
+        path: P,
+        options: &RocksDbOptions,
+    ) -> Result<Self, OpenError> {
+        let (db_options, families) = rocksdb_options(options);
+        let inner = RocksDb::open_cf_descriptors(&db_options, path, families)
+            .map_err(|err| OpenError::Storage(err.to_string()))?;
+        let db = RocksKVDb {
+            inner: Arc::new(inner),
+            namespace: Vec::new(),
+        };
+        schema::upgrade(&db)?;
+        Ok(db)
+    }
+
+    // A view of the same database in the namespace `name`, nested in the one of this view
+    // None if the name is empty or longer than 255 bytes
+    pub fn namespace(&self, name: &[u8]) -> Option<Self> {
+        Some(RocksKVDb {
+            inner: self.inner.clone(),
+            namespace: namespace_prefix(&self.namespace, name)?,
+        })
+    }
+
+    // Column family of the node stored under `key`, None for the default one
+    // `key` is the key of the node before the prefix of the namespace is added
+    pub(crate) fn column_family(&self, key: &[u8]) -> Option<&ColumnFamily> {
+        let name = family_name(*key.first()?)?;
+        Some(
+            self.inner
+                .cf_handle(name)
+                .expect("column family is created at open"),
+        )
+    }
+
+    // Every pair of the namespace whose key starts with `prefix`, in key order
+    pub fn prefix_iterator(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let stored_prefix = namespaced_key(&self.namespace, prefix);
+        let iter = match self.column_family(prefix) {
+            Some(family) => self.inner.prefix_iterator_cf(family, &*stored_prefix),
+            None => self.inner.prefix_iterator(&*stored_prefix),
+        };
+        iter.take_while(|(key, _)| key.starts_with(&stored_prefix))
+            .map(|(key, value)| (key[self.namespace.len()..].to_vec(), value.to_vec()))
+            .collect()
+    }
+}
+
+// Options of the database and of each node column family, with the tuning of `options`
+fn rocksdb_options(options: &RocksDbOptions) -> (Options, Vec<ColumnFamilyDescriptor>) {
+    // One cache for every column family, so that `block_cache_size` bounds the whole database
+    let cache = match options.block_cache_size {
+        0 => None,
+        size => {
+            Some(Cache::new_lru_cache(size as usize).expect("could not create the block cache"))
+        }
+    };
+    let table = |bloom_filter: bool| {
+        let mut table = BlockBasedOptions::default();
+        if let Some(cache) = &cache {
+            table.set_block_cache(cache);
+        }
+        if bloom_filter && options.bloom_filter_bits > 0 {
+            table.set_bloom_filter(options.bloom_filter_bits as i32, false);
+        }
+        table
+    };
+    let family = |table: BlockBasedOptions, compression: DBCompressionType| {
+        let mut opts = Options::default();
+        opts.set_block_based_table_factory(&table);
+        opts.set_compression_type(compression);
+        if options.write_buffer_size > 0 {
+            opts.set_write_buffer_size(options.write_buffer_size as usize);
+        }
+        opts
+    };
+    let compression = match options.compression {
+        RocksDbCompression::Snappy => DBCompressionType::Snappy,
+        RocksDbCompression::None => DBCompressionType::None,
+        RocksDbCompression::Lz4 => DBCompressionType::Lz4,
+        RocksDbCompression::Zstd => DBCompressionType::Zstd,
+    };
+
+    // The default column family only holds a few records, it is never searched for nodes
+    let mut db_options = family(table(false), compression);
+    db_options.create_if_missing(true);
+    db_options.create_missing_column_families(true);
+    if options.max_open_files != 0 {
+        db_options.set_max_open_files(options.max_open_files.max(-1));
+    }
+
+    let families = vec![
+        // Leaves are the bulk of the data, compressed as configured
+        ColumnFamilyDescriptor::new(LEAF_FAMILY, family(table(true), compression)),
+        ColumnFamilyDescriptor::new(STEM_FAMILY, family(table(true), compression)),
+        // Branches are few and read on every path from the root, they stay uncompressed
+        ColumnFamilyDescriptor::new(BRANCH_FAMILY, family(table(true), DBCompressionType::None)),
+    ];
+    (db_options, families)
+}
+
+impl BareMetalDiskDb for RocksKVDb {
+    fn from_path<P: AsRef<std::path::Path>>(path: P) -> Self {
+        match RocksKVDb::open(path, &RocksDbOptions::default()) {
+            Ok(db) => db,
+            Err(err) => panic!("could not open RocksDB: {}", err),
+        }
+    }
+
+    const DEFAULT_PATH: &'static str = "./db/verkle_db";
+}
+
+impl BareMetalKVDb for RocksKVDb {
+    fn fetch(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let stored_key = namespaced_key(&self.namespace, key);
+        let value = match self.column_family(key) {
+            Some(family) => self.inner.get_cf(family, &*stored_key),
+            None => self.inner.get(&*stored_key),
+        };
+        value.expect("could not read from RocksDB")
+    }
+
+    fn new() -> Self {
+        Self::from_path(Self::DEFAULT_PATH)
+    }
+}
+
+impl BatchDB for RocksKVDb {
+    type BatchWrite = MemoryBatchDB;
+
+    fn flush(&mut self, batch: Self::BatchWrite) {
+        self.try_write_batch(&batch)
+            .expect("could not write the batch to RocksDB");
+    }
+}
+
+impl FallibleBatchDB for RocksKVDb {
+    // A RocksDB write batch is applied atomically across column families, either every node
+    // is written or none
+    fn try_write_batch(&mut self, batch: &Self::BatchWrite) -> Result<(), String> {
+        let mut write = WriteBatch::default();
+        for (key, value) in batch.inner.iter() {
+            let stored_key = namespaced_key(&self.namespace, key);
+            match self.column_family(key) {
+                Some(family) => write.put_cf(family, &*stored_key, value),
+                None => write.put(&*stored_key, value),
+            }
+        }
+        self.inner.write(write).map_err(|err| err.to_string())
+    }
+}
@@ -0,0 +1,147 @@
+use crate::database::rocks_kv_db::RocksKVDb;
+use rocksdb::{Direction, IteratorMode, WriteBatch};
+use std::convert::TryInto;
+use std::fmt;
+
+// Version of the on-disk schema of a RocksDB directory: the column families and the encoding
+// of the keys, `StemMeta`, `BranchMeta` and leaves. It is recorded in the default column
+// family when the directory is created and checked every time it is opened
+//
+// Any change of what is written, including one coming from a new `verkle-trie` branch, bumps
+// `SCHEMA_VERSION` and adds the migration that upgrades directories of the previous version
+//
+// 1: every node in the default column family, the version was not recorded
+// 2: leaves, stems and branches in their own column families
+pub const SCHEMA_VERSION: u32 = 2;
+const SCHEMA_VERSION_KEY: &[u8] = b"\xffformat_version";
+
+// Number of nodes moved per write batch by a migration
+const MIGRATION_BATCH: usize = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenError {
+    Storage(String),
+    // Written by a newer version of this library, or with no migration to the current schema
+    UnsupportedSchema(u32),
+    Migration { from: u32, reason: String },
+}
+
+impl fmt::Display for OpenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OpenError::Storage(reason) => write!(f, "storage error: {}", reason),
+            OpenError::UnsupportedSchema(version) => {
+                write!(f, "unsupported schema version {}", version)
+            }
+            OpenError::Migration { from, reason } => {
+                write!(
+                    f,
+                    "migration from schema version {} failed: {}",
+                    from, reason
+                )
+            }
+        }
+    }
+}
+
+// Upgrades a directory from schema version `from` to the next one, in place
+// A migration must be safe to run again after it was interrupted
+struct Migration {
+    from: u32,
+    run: fn(&RocksKVDb) -> Result<(), String>,
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    run: move_nodes_to_column_families,
+}];
+
+fn storage_error(err: rocksdb::Error) -> OpenError {
+    OpenError::Storage(err.to_string())
+}
+
+fn recorded_version(db: &RocksKVDb) -> Result<Option<u32>, OpenError> {
+    let bytes = match db.inner.get(SCHEMA_VERSION_KEY).map_err(storage_error)? {
+        Some(bytes) => bytes,
+        None => return Ok(None),
+    };
+    let bytes: [u8; 4] = bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| OpenError::Storage("malformed schema version".to_string()))?;
+    Ok(Some(u32::from_le_bytes(bytes)))
+}
+
+fn record_version(db: &RocksKVDb, version: u32) -> Result<(), OpenError> {
+    db.inner
+        .put(SCHEMA_VERSION_KEY, version.to_le_bytes())
+        .map_err(storage_error)
+}
+
+// Version of the directory, recording the current one if it was just created
+fn schema_version(db: &RocksKVDb) -> Result<u32, OpenError> {
+    if let Some(version) = recorded_version(db)? {
+        return Ok(version);
+    }
+    // Directories of version 1 hold their nodes in the default column family
+    if db.inner.iterator(IteratorMode::Start).next().is_some() {
+        return Ok(1);
+    }
+    record_version(db, SCHEMA_VERSION)?;
+    Ok(SCHEMA_VERSION)
+}
+
+// Bring the directory to `SCHEMA_VERSION`, one migration at a time. The version is recorded
+// after each of them, so an interrupted upgrade resumes where it stopped
+pub(crate) fn upgrade(db: &RocksKVDb) -> Result<(), OpenError> {
+    let mut version = schema_version(db)?;
+    while version != SCHEMA_VERSION {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|migration| migration.from == version)
+            .ok_or(OpenError::UnsupportedSchema(version))?;
+        (migration.run)(db).map_err(|reason| OpenError::Migration {
+            from: version,
+            reason,
+        })?;
+        version += 1;
+        record_version(db, version)?;
+    }
+    Ok(())
+}
+
+// 1 to 2: move the nodes found in the default column family into the one of their kind
+// Every batch moves its nodes atomically
+fn move_nodes_to_column_families(db: &RocksKVDb) -> Result<(), String> {
+    // Last key moved by the previous batch. Every batch resumes from it, so the deleted keys
+    // before it are not iterated over again
+    let mut last_key: Option<Box<[u8]>> = None;
+    loop {
+        let mut batch = WriteBatch::default();
+        let mut moved = 0;
+        let mode = match &last_key {
+            Some(key) => IteratorMode::From(&key[..], Direction::Forward),
+            // Node keys start with the lowest bytes, so they come first
+            None => IteratorMode::Start,
+        };
+        let mut batch_last_key = None;
+        for (key, value) in db.inner.iterator(mode) {
+            let family = match db.column_family(&key) {
+                Some(family) => family,
+                None => break,
+            };
+            batch.put_cf(family, &*key, &*value);
+            batch.delete(&*key);
+            batch_last_key = Some(key);
+            moved += 1;
+            if moved == MIGRATION_BATCH {
+                break;
+            }
+        }
+        if moved == 0 {
+            return Ok(());
+        }
+        db.inner.write(batch).map_err(|err| err.to_string())?;
+        last_key = batch_last_key;
+    }
+}
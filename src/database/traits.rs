@@ -1,3 +1,5 @@
+use verkle_db::BatchDB;
+
 pub trait DB {
     fn create_db(path: &str) -> Self;
 }
@@ -5,5 +7,27 @@ pub trait DB {
 pub trait ReadOnlyDB {
     type DbObject;
     fn create_from_db(db: &'static mut Self::DbObject) -> Self;
+    // An overlay stacked on `parent`, its changes are kept apart from the ones of `parent`
+    fn create_from_overlay(parent: &'static mut Self) -> Self;
     fn clear_temp_changes(&mut self);
+    // Move the changes into the overlay below, false if this one is not stacked on another
+    fn merge_into_parent(&mut self) -> bool;
+    // Write the changes into the database and drop them, they are kept if this fails
+    fn commit_temp_changes(&mut self) -> Result<(), CommitError>;
+    // The leaves whose value this overlay changed, in key order: the key, the value below the
+    // overlay if there was one and the value in the overlay
+    fn changed_leaves(&self) -> Vec<([u8; 32], Option<[u8; 32]>, [u8; 32])>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitError {
+    // The overlay is stacked on another one, which has to be merged first
+    Stacked,
+    Storage(String),
+}
+
+// A `BatchDB` whose batch writes report failures instead of panicking
+// The batch is borrowed, so that the caller still has it when the write fails
+pub trait FallibleBatchDB: BatchDB {
+    fn try_write_batch(&mut self, batch: &Self::BatchWrite) -> Result<(), String>;
 }
@@ -1,7 +1,6 @@
 use crate::database::generics::MemDB;
 use std::collections::HashMap;
 use std::mem::transmute;
-use verkle_db::RocksDb;
 use verkle_db::{BareMetalDiskDb, BareMetalKVDb, BatchDB, BatchWriter};
 use verkle_trie::database::generic::GenericBatchDB;
 use verkle_trie::database::generic::GenericBatchWriter;
@@ -122,11 +121,10 @@ impl<S: ReadOnlyHigherDb> ReadOnlyHigherDb for VerkleTreeDb<S> {
     }
 
     fn get_branch_children(&self, branch_id: &[u8]) -> Vec<(u8, BranchChild)> {
-        // Check the depth. If the branch is at CACHE_DEPTH or lower, then it will be in the cache
-        // TODO this assumes that the cache is populated on startup from disk
-        if branch_id.len() as u8 <= CACHE_DEPTH {
-            return self.cache.get_branch_children(branch_id);
-        }
+        // The cache cannot answer this on its own: it is emptied on every flush and is not
+        // populated when a trie is created from a database, so it may only hold some of the
+        // children. Everything in the cache is also in the batch
+        //
         // First get the children from storage
         let mut children: HashMap<_, _> = self
             .storage
@@ -150,15 +148,8 @@ impl<S: ReadOnlyHigherDb> ReadOnlyHigherDb for VerkleTreeDb<S> {
     }
 
     fn get_stem_children(&self, stem_key: [u8; 31]) -> Vec<(u8, [u8; 32])> {
-        // Stems don't have a depth, however the children for all stem will always be on the same depth
-        // If we get any children for the stem in the cache storage, then this means we have collected all of them
-        // TODO this assumes that the cache is populated on startup from disk
-        let children = self.cache.get_stem_children(stem_key);
-        if !children.is_empty() {
-            return children;
-        }
-
-        // It's possible that they are in disk storage and that batch storage has some recent updates
+        // As with branches, the cache may only hold the leaves inserted since the last flush
+        // They are in disk storage and batch storage has the recent updates
         // First get the children from storage
         let mut children: HashMap<_, _> = self
             .storage
@@ -0,0 +1,31 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// Faults that tests can inject into the flush of the RocksDB backend
+// The fault is process wide and stays set until it is reset to `FlushFault::None`
+// Only built with the `failpoints` feature, never part of a release build
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushFault {
+    None = 0,
+    // The write of the batch returns an error
+    FailWrite = 1,
+    // The process aborts before the batch is written
+    CrashBeforeWrite = 2,
+    // The process aborts once the batch is written, before the in-memory layers are cleared
+    CrashAfterWrite = 3,
+}
+
+static FLUSH_FAULT: AtomicU8 = AtomicU8::new(FlushFault::None as u8);
+
+pub fn set_flush_fault(fault: FlushFault) {
+    FLUSH_FAULT.store(fault as u8, Ordering::SeqCst);
+}
+
+pub(crate) fn flush_fault() -> FlushFault {
+    match FLUSH_FAULT.load(Ordering::SeqCst) {
+        1 => FlushFault::FailWrite,
+        2 => FlushFault::CrashBeforeWrite,
+        3 => FlushFault::CrashAfterWrite,
+        _ => FlushFault::None,
+    }
+}
@@ -0,0 +1,347 @@
+use crate::commitment::{compute_branch_meta, compute_stem_meta};
+#[cfg(feature = "rocks_db")]
+use crate::database::disk_db::VerkleRocksDB;
+use crate::database::external_db::VerkleExternalDB;
+use crate::database::generics::GenericMemoryDb;
+use crate::database::redb_db::VerkleRedbDB;
+use crate::inspect::stem_meta_to_bytes;
+use bandersnatch::Fr;
+use std::collections::HashSet;
+use std::convert::TryInto;
+use std::fmt;
+use verkle_trie::committer::Committer;
+use verkle_trie::database::memory_db::MemoryDb;
+use verkle_trie::database::{BranchChild, ReadOnlyHigherDb};
+use verkle_trie::group_to_field;
+
+// Integrity check of a persisted trie: every branch and stem reachable from the root is read
+// back from storage, its commitment is recomputed and compared with the stored one, and
+// whatever is stored but not reachable from the root is reported as an orphan
+//
+// The parent commitments are recomputed from the stored hashes of the children, so a single
+// corrupted node is reported once and not again for each of its ancestors
+
+// Table markers of `GenericBatchDB`, every stored key starts with one of them
+const STEM_TABLE_MARKER: u8 = 1;
+const BRANCH_TABLE_MARKER: u8 = 2;
+
+// Lists the nodes written in storage, whether they are reachable or not
+pub trait StoredNodes {
+    fn stored_stems(&self) -> Vec<[u8; 31]>;
+    // Path of every branch table entry, either a branch or the slot of a stem
+    fn stored_branch_paths(&self) -> Vec<Vec<u8>>;
+}
+
+#[cfg(feature = "rocks_db")]
+impl StoredNodes for VerkleRocksDB {
+    fn stored_stems(&self) -> Vec<[u8; 31]> {
+        self.inner
+            .prefix_iterator(&[STEM_TABLE_MARKER])
+            .into_iter()
+            .filter_map(|(key, _)| key[1..].try_into().ok())
+            .collect()
+    }
+
+    fn stored_branch_paths(&self) -> Vec<Vec<u8>> {
+        self.inner
+            .prefix_iterator(&[BRANCH_TABLE_MARKER])
+            .into_iter()
+            .map(|(key, _)| key[1..].to_vec())
+            .collect()
+    }
+}
+
+impl StoredNodes for VerkleExternalDB {
+    fn stored_stems(&self) -> Vec<[u8; 31]> {
+        self.inner
+            .iterate_prefix(&[STEM_TABLE_MARKER])
+            .into_iter()
+            .filter_map(|(key, _)| key[1..].try_into().ok())
+            .collect()
+    }
+
+    fn stored_branch_paths(&self) -> Vec<Vec<u8>> {
+        self.inner
+            .iterate_prefix(&[BRANCH_TABLE_MARKER])
+            .into_iter()
+            .map(|(key, _)| key[1..].to_vec())
+            .collect()
+    }
+}
+
+impl StoredNodes for VerkleRedbDB {
+    fn stored_stems(&self) -> Vec<[u8; 31]> {
+        self.inner
+            .prefix_iterator(&[STEM_TABLE_MARKER])
+            .into_iter()
+            .filter_map(|(key, _)| key[1..].try_into().ok())
+            .collect()
+    }
+
+    fn stored_branch_paths(&self) -> Vec<Vec<u8>> {
+        self.inner
+            .prefix_iterator(&[BRANCH_TABLE_MARKER])
+            .into_iter()
+            .map(|(key, _)| key[1..].to_vec())
+            .collect()
+    }
+}
+
+impl StoredNodes for MemoryDb {
+    fn stored_stems(&self) -> Vec<[u8; 31]> {
+        self.stem_table.keys().copied().collect()
+    }
+
+    fn stored_branch_paths(&self) -> Vec<Vec<u8>> {
+        self.branch_table.keys().cloned().collect()
+    }
+}
+
+impl<T: StoredNodes> StoredNodes for GenericMemoryDb<T> {
+    fn stored_stems(&self) -> Vec<[u8; 31]> {
+        self.inner.stored_stems()
+    }
+
+    fn stored_branch_paths(&self) -> Vec<Vec<u8>> {
+        self.inner.stored_branch_paths()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    // The stored commitment differs from the one recomputed from the children
+    BranchCommitmentMismatch { path: Vec<u8> },
+    StemCommitmentMismatch { stem: [u8; 31] },
+    // A stem is referenced from a slot that is not on its path
+    MisplacedStem { path: Vec<u8>, stem: [u8; 31] },
+    // A branch child points to a stem that is not stored
+    MissingStem { path: Vec<u8>, stem: [u8; 31] },
+    // Nodes that should have been removed or never written
+    EmptyBranch { path: Vec<u8> },
+    EmptyStem { stem: [u8; 31] },
+    // Stored nodes that cannot be reached from the root
+    OrphanBranch { path: Vec<u8> },
+    OrphanStem { stem: [u8; 31] },
+}
+
+impl Issue {
+    pub fn is_mismatch(&self) -> bool {
+        matches!(
+            self,
+            Issue::BranchCommitmentMismatch { .. }
+                | Issue::StemCommitmentMismatch { .. }
+                | Issue::MisplacedStem { .. }
+        )
+    }
+
+    pub fn is_dangling(&self) -> bool {
+        matches!(
+            self,
+            Issue::MissingStem { .. } | Issue::EmptyBranch { .. } | Issue::EmptyStem { .. }
+        )
+    }
+
+    pub fn is_orphan(&self) -> bool {
+        matches!(self, Issue::OrphanBranch { .. } | Issue::OrphanStem { .. })
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::BranchCommitmentMismatch { path } => {
+                write!(f, "branch 0x{}: commitment mismatch", hex::encode(path))
+            }
+            Issue::StemCommitmentMismatch { stem } => {
+                write!(f, "stem 0x{}: commitment mismatch", hex::encode(stem))
+            }
+            Issue::MisplacedStem { path, stem } => write!(
+                f,
+                "branch 0x{}: child stem 0x{} is not on its path",
+                hex::encode(path),
+                hex::encode(stem)
+            ),
+            Issue::MissingStem { path, stem } => write!(
+                f,
+                "branch 0x{}: child stem 0x{} is missing",
+                hex::encode(path),
+                hex::encode(stem)
+            ),
+            Issue::EmptyBranch { path } => {
+                write!(f, "branch 0x{}: no children", hex::encode(path))
+            }
+            Issue::EmptyStem { stem } => write!(f, "stem 0x{}: no leaves", hex::encode(stem)),
+            Issue::OrphanBranch { path } => {
+                write!(
+                    f,
+                    "branch 0x{}: not reachable from the root",
+                    hex::encode(path)
+                )
+            }
+            Issue::OrphanStem { stem } => {
+                write!(
+                    f,
+                    "stem 0x{}: not reachable from the root",
+                    hex::encode(stem)
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FsckReport {
+    pub branches: u64,
+    pub stems: u64,
+    pub leaves: u64,
+    pub issues: Vec<Issue>,
+}
+
+impl FsckReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+struct Checker<'a, D, C> {
+    db: &'a D,
+    committer: &'a C,
+    report: FsckReport,
+    reached_paths: HashSet<Vec<u8>>,
+    reached_stems: HashSet<[u8; 31]>,
+}
+
+impl<'a, D: ReadOnlyHigherDb, C: Committer> Checker<'a, D, C> {
+    // Check the branch at `path` and everything below it
+    // Returns the stored hash of its commitment, as used by the parent
+    fn check_branch(&mut self, path: &[u8]) -> Option<Fr> {
+        self.reached_paths.insert(path.to_vec());
+        let stored = self.db.get_branch_meta(path);
+        let mut children = self.db.get_branch_children(path);
+        children.sort_by_key(|(index, _)| *index);
+
+        // An empty database has no root yet
+        if path.is_empty() && stored.is_none() && children.is_empty() {
+            return None;
+        }
+        self.report.branches += 1;
+        if children.is_empty() {
+            self.report.issues.push(Issue::EmptyBranch {
+                path: path.to_vec(),
+            });
+        }
+
+        let mut child_hashes = Vec::with_capacity(children.len());
+        for (index, child) in children {
+            let mut child_path = path.to_vec();
+            child_path.push(index);
+            let hash = match child {
+                BranchChild::Branch(_) => self.check_branch(&child_path),
+                BranchChild::Stem(stem) => self.check_stem(&child_path, stem),
+            };
+            if let Some(hash) = hash {
+                child_hashes.push((index, hash));
+            }
+        }
+
+        let expected = compute_branch_meta(self.committer, &child_hashes);
+        let stored = match stored {
+            Some(stored) => stored,
+            None => {
+                self.report.issues.push(Issue::BranchCommitmentMismatch {
+                    path: path.to_vec(),
+                });
+                return Some(expected.hash_commitment);
+            }
+        };
+        if stored.commitment != expected.commitment
+            || stored.hash_commitment != group_to_field(&stored.commitment)
+        {
+            self.report.issues.push(Issue::BranchCommitmentMismatch {
+                path: path.to_vec(),
+            });
+        }
+        Some(stored.hash_commitment)
+    }
+
+    fn check_stem(&mut self, path: &[u8], stem: [u8; 31]) -> Option<Fr> {
+        self.reached_paths.insert(path.to_vec());
+        if stem[..path.len()] != path[..] {
+            self.report.issues.push(Issue::MisplacedStem {
+                path: path[..path.len() - 1].to_vec(),
+                stem,
+            });
+        }
+
+        let stored = match self.db.get_stem_meta(stem) {
+            Some(stored) => stored,
+            None => {
+                self.report.issues.push(Issue::MissingStem {
+                    path: path[..path.len() - 1].to_vec(),
+                    stem,
+                });
+                return None;
+            }
+        };
+        if !self.reached_stems.insert(stem) {
+            // Already checked through another slot, which is reported as misplaced
+            return Some(stored.hash_stem_commitment);
+        }
+        self.report.stems += 1;
+
+        let leaves = self.db.get_stem_children(stem);
+        self.report.leaves += leaves.len() as u64;
+        if leaves.is_empty() {
+            self.report.issues.push(Issue::EmptyStem { stem });
+        }
+
+        let expected = compute_stem_meta(self.committer, stem, &leaves);
+        if stem_meta_to_bytes(&stored) != stem_meta_to_bytes(&expected) {
+            self.report
+                .issues
+                .push(Issue::StemCommitmentMismatch { stem });
+        }
+        Some(stored.hash_stem_commitment)
+    }
+}
+
+pub fn check_integrity<D, C>(db: &D, committer: &C) -> FsckReport
+where
+    D: ReadOnlyHigherDb + StoredNodes,
+    C: Committer,
+{
+    let mut checker = Checker {
+        db,
+        committer,
+        report: FsckReport::default(),
+        reached_paths: HashSet::new(),
+        reached_stems: HashSet::new(),
+    };
+    checker.check_branch(&[]);
+
+    let mut orphan_paths: Vec<_> = db
+        .stored_branch_paths()
+        .into_iter()
+        .filter(|path| !checker.reached_paths.contains(path))
+        .collect();
+    orphan_paths.sort();
+    let mut orphan_stems: Vec<_> = db
+        .stored_stems()
+        .into_iter()
+        .filter(|stem| !checker.reached_stems.contains(stem))
+        .collect();
+    orphan_stems.sort();
+
+    let mut report = checker.report;
+    report.issues.extend(
+        orphan_paths
+            .into_iter()
+            .map(|path| Issue::OrphanBranch { path }),
+    );
+    report.issues.extend(
+        orphan_stems
+            .into_iter()
+            .map(|stem| Issue::OrphanStem { stem }),
+    );
+    report
+}
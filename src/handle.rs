@@ -0,0 +1,356 @@
+use crate::iterator::TrieCursor;
+use crate::{
+    BulkLoaderVariant, Database, DatabaseVariant, TrieIterator, TrieVariant, VerkleBulkLoader,
+    VerkleError, VerkleTrie,
+};
+use once_cell::sync::Lazy;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// Registry of the objects handed out over FFI
+//
+// A handle is not the address of the object but an id in the registry, and ids are never
+// reused. Every exported function looks its handles up first, so a handle that was freed,
+// never created or that refers to another kind of object is reported as an error instead of
+// being dereferenced
+//
+// A lookup holds the object, locked, until the call is done with it. Calls on the same object
+// from several threads take turns, and freeing an object while another thread uses it fails
+// with `HandleInUse`
+//
+// Databases are borrowed by the tries, read only databases, namespaces and bulk loaders
+// created from them. The registry counts these borrows and refuses to free a database while
+// it has any. A call on a borrower, freeing it included, also locks the databases it borrows,
+// the outermost first, so it takes turns with the calls on them and on their other borrowers
+
+enum Object {
+    Trie(TrieVariant),
+    Database(DatabaseVariant),
+    BulkLoader(BulkLoaderVariant),
+    Iterator(TrieCursor),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Trie,
+    Database,
+    BulkLoader,
+    Iterator,
+}
+
+impl Object {
+    fn kind(&self) -> Kind {
+        match self {
+            Object::Trie(_) => Kind::Trie,
+            Object::Database(_) => Kind::Database,
+            Object::BulkLoader(_) => Kind::BulkLoader,
+            Object::Iterator(_) => Kind::Iterator,
+        }
+    }
+}
+
+struct Entry {
+    // Every call using the object holds a clone, so the object outlives the calls in flight
+    // and does not move while borrowers keep references into it
+    object: Arc<Mutex<Object>>,
+    // Known without locking the object, which another call may hold
+    kind: Kind,
+    // Database this object borrows
+    parent: Option<usize>,
+    borrowers: usize,
+}
+
+struct Registry {
+    last_id: usize,
+    entries: HashMap<usize, Entry>,
+}
+
+// The objects hold references into their databases, so they are not `Send`. They are only
+// used behind their lock, and a database is not freed while objects borrow it
+unsafe impl Send for Registry {}
+
+static REGISTRY: Lazy<Mutex<Registry>> = Lazy::new(|| {
+    Mutex::new(Registry {
+        last_id: 0,
+        entries: HashMap::new(),
+    })
+});
+
+thread_local! {
+    static LAST_ERROR: Cell<VerkleError> = Cell::new(VerkleError::Ok);
+}
+
+fn registry() -> MutexGuard<'static, Registry> {
+    REGISTRY.lock().expect("handle registry poisoned")
+}
+
+// Outcome of the last handle lookup on this thread
+pub fn last_error() -> VerkleError {
+    LAST_ERROR.with(|error| error.get())
+}
+
+pub(crate) fn set_last_error(error: VerkleError) {
+    LAST_ERROR.with(|last| last.set(error));
+}
+
+fn record<T>(result: Result<T, VerkleError>) -> Result<T, VerkleError> {
+    set_last_error(match &result {
+        Ok(_) => VerkleError::Ok,
+        Err(error) => *error,
+    });
+    result
+}
+
+fn register(object: Object, parent: Option<usize>) -> usize {
+    let mut registry = registry();
+    registry.last_id += 1;
+    let id = registry.last_id;
+    if let Some(parent) = parent {
+        registry
+            .entries
+            .get_mut(&parent)
+            .expect("parent handle is not registered")
+            .borrowers += 1;
+    }
+    let entry = Entry {
+        kind: object.kind(),
+        object: Arc::new(Mutex::new(object)),
+        parent,
+        borrowers: 0,
+    };
+    registry.entries.insert(id, entry);
+    id
+}
+
+// A locked object, kept alive until this is dropped
+struct Held {
+    // Borrows from `_object`, declared first so that it is dropped first
+    guard: MutexGuard<'static, Object>,
+    _object: Arc<Mutex<Object>>,
+}
+
+impl Held {
+    fn lock(object: Arc<Mutex<Object>>) -> Self {
+        let lock: &'static Mutex<Object> = unsafe { &*Arc::as_ptr(&object) };
+        // A call that panicked left the object as it was when it stopped, as before
+        let guard = lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Held {
+            guard,
+            _object: object,
+        }
+    }
+}
+
+// The databases an object borrows, locked for a call on it, the outermost first
+pub(crate) struct Parents {
+    _held: Vec<Held>,
+}
+
+impl Parents {
+    fn lock(parents: Vec<Arc<Mutex<Object>>>) -> Self {
+        Parents {
+            _held: parents.into_iter().map(Held::lock).collect(),
+        }
+    }
+}
+
+// An object looked up by a call. It and the databases it borrows stay alive and locked until
+// this is dropped
+pub(crate) struct InUse<T: 'static> {
+    // Points into the object behind `held`
+    inner: *mut T,
+    // Declared first so that the object is unlocked before its databases
+    _held: Held,
+    _parents: Parents,
+}
+
+impl<T> InUse<T> {
+    fn new(
+        object: Arc<Mutex<Object>>,
+        parents: Vec<Arc<Mutex<Object>>>,
+        project: fn(&mut Object) -> &mut T,
+    ) -> Self {
+        let parents = Parents::lock(parents);
+        let mut held = Held::lock(object);
+        InUse {
+            inner: project(&mut held.guard),
+            _held: held,
+            _parents: parents,
+        }
+    }
+
+    // For objects that keep a reference into this one, like a trie into its database
+    // The registry keeps this object alive while they are registered, see `register`, and
+    // they only use the reference while their calls hold this object locked
+    pub(crate) fn borrow_static(&mut self) -> &'static mut T {
+        unsafe { &mut *self.inner }
+    }
+}
+
+impl<T> Deref for InUse<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.inner }
+    }
+}
+
+impl<T> DerefMut for InUse<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.inner }
+    }
+}
+
+// The databases borrowed by `entry`, the outermost first
+// A database is not freed while it is borrowed, so they are all registered
+fn parents_of(registry: &Registry, entry: &Entry) -> Vec<Arc<Mutex<Object>>> {
+    let mut parents = vec![];
+    let mut parent = entry.parent;
+    while let Some(id) = parent {
+        let entry = &registry.entries[&id];
+        parents.push(entry.object.clone());
+        parent = entry.parent;
+    }
+    parents.reverse();
+    parents
+}
+
+fn lookup<T>(
+    id: usize,
+    kind: Kind,
+    project: fn(&mut Object) -> &mut T,
+) -> Result<InUse<T>, VerkleError> {
+    if id == 0 {
+        return Err(VerkleError::NullHandle);
+    }
+    // The clones are taken under the registry lock, so that a release either sees them or
+    // happens before it. The objects are locked after it is unlocked, since a call registers
+    // new objects while it holds its own
+    let (object, parents) = {
+        let registry = registry();
+        let entry = registry
+            .entries
+            .get(&id)
+            .ok_or(VerkleError::InvalidHandle)?;
+        if entry.kind != kind {
+            return Err(VerkleError::WrongHandleType);
+        }
+        (entry.object.clone(), parents_of(&registry, entry))
+    };
+    Ok(InUse::new(object, parents, project))
+}
+
+// The object is returned with the databases it borrows still locked, so that dropping it
+// takes turns with the calls on them
+fn release(id: usize, kind: Kind) -> Result<(Object, Parents), VerkleError> {
+    if id == 0 {
+        return Err(VerkleError::NullHandle);
+    }
+    let parents = {
+        let registry = registry();
+        let entry = registry
+            .entries
+            .get(&id)
+            .ok_or(VerkleError::InvalidHandle)?;
+        parents_of(&registry, entry)
+    };
+    let parents = Parents::lock(parents);
+    // Checked again, another thread may have freed it while the databases were locked
+    let mut registry = registry();
+    let entry = registry
+        .entries
+        .get(&id)
+        .ok_or(VerkleError::InvalidHandle)?;
+    if entry.kind != kind {
+        return Err(VerkleError::WrongHandleType);
+    }
+    // Borrowed by other objects or used by a call on another thread
+    if entry.borrowers > 0 || Arc::strong_count(&entry.object) > 1 {
+        return Err(VerkleError::HandleInUse);
+    }
+    let entry = registry.entries.remove(&id).unwrap();
+    if let Some(parent) = entry.parent {
+        if let Some(parent) = registry.entries.get_mut(&parent) {
+            parent.borrowers -= 1;
+        }
+    }
+    let object = match Arc::try_unwrap(entry.object) {
+        Ok(object) => object,
+        Err(_) => unreachable!("clones are only taken under the registry lock"),
+    };
+    let object = object
+        .into_inner()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    Ok((object, parents))
+}
+
+// Whether tries or bulk loaders created from the database below the overlay `db` are
+// registered. Their batches and caches were built on the state the database had before, so an
+// overlay is not committed into it while they are
+pub(crate) fn base_has_writers(db: *mut Database) -> bool {
+    let registry = registry();
+    let base = match registry.entries.get(&(db as usize)) {
+        Some(entry) => entry.parent,
+        None => return false,
+    };
+    base.is_some()
+        && registry.entries.values().any(|entry| {
+            entry.parent == base && (entry.kind == Kind::Trie || entry.kind == Kind::BulkLoader)
+        })
+}
+
+pub(crate) fn register_trie(trie: TrieVariant, db: Option<*mut Database>) -> *mut VerkleTrie {
+    register(Object::Trie(trie), db.map(|db| db as usize)) as *mut VerkleTrie
+}
+
+pub(crate) fn register_db(db: DatabaseVariant, parent: Option<*mut Database>) -> *mut Database {
+    register(Object::Database(db), parent.map(|db| db as usize)) as *mut Database
+}
+
+pub(crate) fn register_bulk_loader(
+    loader: BulkLoaderVariant,
+    db: *mut Database,
+) -> *mut VerkleBulkLoader {
+    register(Object::BulkLoader(loader), Some(db as usize)) as *mut VerkleBulkLoader
+}
+
+pub(crate) fn register_iterator(iter: TrieCursor) -> *mut TrieIterator {
+    register(Object::Iterator(iter), None) as *mut TrieIterator
+}
+
+// Lookup and free functions of one kind of handle
+macro_rules! handle_kind {
+    ($handle:ty, $variant:ident, $object:ty, $lookup:ident, $free:ident) => {
+        pub(crate) fn $lookup(handle: *mut $handle) -> Result<InUse<$object>, VerkleError> {
+            record(lookup(
+                handle as usize,
+                Kind::$variant,
+                |object| match object {
+                    Object::$variant(object) => object,
+                    _ => unreachable!(),
+                },
+            ))
+        }
+
+        pub(crate) fn $free(handle: *mut $handle) -> Result<($object, Parents), VerkleError> {
+            let released = release(handle as usize, Kind::$variant);
+            record(released.map(|(object, parents)| match object {
+                Object::$variant(object) => (object, parents),
+                _ => unreachable!(),
+            }))
+        }
+    };
+}
+
+handle_kind!(VerkleTrie, Trie, TrieVariant, trie, free_trie);
+handle_kind!(Database, Database, DatabaseVariant, db, free_db);
+handle_kind!(
+    VerkleBulkLoader,
+    BulkLoader,
+    BulkLoaderVariant,
+    bulk_loader,
+    free_bulk_loader
+);
+handle_kind!(TrieIterator, Iterator, TrieCursor, iterator, free_iterator);
@@ -0,0 +1,54 @@
+use crate::proof::{point_to_bytes, scalar_to_bytes};
+use std::convert::TryInto;
+use std::slice;
+use verkle_trie::database::{BranchMeta, ReadOnlyHigherDb, StemMeta};
+
+// Serialized form of the node metadata used for debugging root mismatches against other clients
+// Every point is compressed and every field element is 32 bytes
+//
+// BranchMeta: commitment || hash_commitment
+// StemMeta:   c_1 || hash_c1 || c_2 || hash_c2 || stem_commitment || hash_stem_commitment
+
+pub const BRANCH_META_SIZE: usize = 64;
+pub const STEM_META_SIZE: usize = 192;
+
+pub fn branch_meta_to_bytes(meta: &BranchMeta) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(BRANCH_META_SIZE);
+    bytes.extend_from_slice(&point_to_bytes(&meta.commitment));
+    bytes.extend_from_slice(&scalar_to_bytes(&meta.hash_commitment));
+    bytes
+}
+
+pub fn stem_meta_to_bytes(meta: &StemMeta) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(STEM_META_SIZE);
+    bytes.extend_from_slice(&point_to_bytes(&meta.c_1));
+    bytes.extend_from_slice(&scalar_to_bytes(&meta.hash_c1));
+    bytes.extend_from_slice(&point_to_bytes(&meta.c_2));
+    bytes.extend_from_slice(&scalar_to_bytes(&meta.hash_c2));
+    bytes.extend_from_slice(&point_to_bytes(&meta.stem_commitment));
+    bytes.extend_from_slice(&scalar_to_bytes(&meta.hash_stem_commitment));
+    bytes
+}
+
+// The path is the sequence of child indices from the root, an empty path is the root itself
+pub fn get_branch_meta_bytes<D: ReadOnlyHigherDb>(db: &D, path: &[u8]) -> Option<Vec<u8>> {
+    db.get_branch_meta(path).map(|meta| branch_meta_to_bytes(&meta))
+}
+
+pub fn get_stem_meta_bytes<D: ReadOnlyHigherDb>(db: &D, stem: [u8; 31]) -> Option<Vec<u8>> {
+    db.get_stem_meta(stem).map(|meta| stem_meta_to_bytes(&meta))
+}
+
+pub fn get_path_from_argument(path: *const u8, path_len: usize) -> Vec<u8> {
+    if path_len == 0 {
+        return Vec::new();
+    }
+    assert!(!path.is_null());
+    unsafe { slice::from_raw_parts(path, path_len) }.to_vec()
+}
+
+pub fn get_stem_from_argument(stem: *const u8) -> [u8; 31] {
+    assert!(!stem.is_null());
+    let _raw_slice = unsafe { slice::from_raw_parts(stem, 31) };
+    _raw_slice.try_into().expect("slice with incorrect length")
+}
@@ -0,0 +1,85 @@
+use crate::VerkleTrie;
+use verkle_trie::database::{BranchChild, ReadOnlyHigherDb};
+
+enum Pending {
+    Branch(Vec<u8>),
+    Stem([u8; 31]),
+    Leaf([u8; 32], [u8; 32]),
+}
+
+// Object behind a `TrieIterator` handle, the iterator together with the handle of the trie
+// it walks. Nodes are fetched lazily, so the trie should not be modified while iterating
+pub struct TrieCursor {
+    pub trie: *mut VerkleTrie,
+    pub leaves: LeafIterator,
+}
+
+impl TrieCursor {
+    pub fn new(trie: *mut VerkleTrie, start_key: [u8; 32]) -> Self {
+        TrieCursor {
+            trie,
+            leaves: LeafIterator::new(start_key),
+        }
+    }
+
+    pub fn next<D: ReadOnlyHigherDb>(&mut self, db: &D) -> Option<([u8; 32], [u8; 32])> {
+        self.leaves.next(db)
+    }
+}
+
+// Depth first walk of the trie that yields the leaves in key order, starting at `start_key`
+pub struct LeafIterator {
+    start_key: [u8; 32],
+    stack: Vec<Pending>,
+}
+
+impl LeafIterator {
+    pub fn new(start_key: [u8; 32]) -> Self {
+        LeafIterator {
+            start_key,
+            stack: vec![Pending::Branch(vec![])],
+        }
+    }
+
+    pub fn next<D: ReadOnlyHigherDb>(&mut self, db: &D) -> Option<([u8; 32], [u8; 32])> {
+        while let Some(pending) = self.stack.pop() {
+            match pending {
+                Pending::Leaf(key, value) => return Some((key, value)),
+                Pending::Stem(stem) => {
+                    let mut leaves = db.get_stem_children(stem);
+                    leaves.sort_by_key(|(suffix, _)| *suffix);
+                    // Push in reverse so that the smallest suffix is popped first
+                    for (suffix, value) in leaves.into_iter().rev() {
+                        let mut key = [0u8; 32];
+                        key[..31].copy_from_slice(&stem);
+                        key[31] = suffix;
+                        if key >= self.start_key {
+                            self.stack.push(Pending::Leaf(key, value));
+                        }
+                    }
+                }
+                Pending::Branch(branch_id) => {
+                    let mut children = db.get_branch_children(&branch_id);
+                    children.sort_by_key(|(index, _)| *index);
+                    for (index, child) in children.into_iter().rev() {
+                        let mut path = branch_id.clone();
+                        path.push(index);
+                        // Every key below this child is smaller than the start key
+                        if path[..] < self.start_key[..path.len()] {
+                            continue;
+                        }
+                        match child {
+                            BranchChild::Stem(stem) => {
+                                if stem[..] >= self.start_key[..31] {
+                                    self.stack.push(Pending::Stem(stem));
+                                }
+                            }
+                            BranchChild::Branch(_) => self.stack.push(Pending::Branch(path)),
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
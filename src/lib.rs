@@ -2,40 +2,115 @@
 #![feature(core_panic)]
 extern crate core;
 
+pub mod bulk_load;
+mod commitment;
 mod database;
+#[cfg(feature = "failpoints")]
+pub mod failpoint;
+pub mod fsck;
+pub mod handle;
+pub mod inspect;
+pub mod iterator;
+pub mod parallel;
+pub mod proof;
+pub mod snapshot;
 mod verkle_variants;
 pub mod utils;
 
-use crate::database::traits::{ReadOnlyDB, DB};
-use crate::Database::VerkleMemoryDb;
-use crate::Database::{VerkleDiskDb, VerkleReadOnlyDiskDb};
+use crate::bulk_load::{BulkLoadError, BulkLoader};
+use crate::database::disk_db::last_flush_record;
+#[cfg(feature = "rocks_db")]
+use crate::database::schema::OpenError;
+use crate::database::traits::{CommitError, ReadOnlyDB, DB};
+use crate::fsck::check_integrity;
+use crate::inspect::{
+    get_branch_meta_bytes, get_path_from_argument, get_stem_from_argument, get_stem_meta_bytes,
+};
+use crate::iterator::TrieCursor;
+use crate::parallel::insert_parallel;
+use crate::proof::range::{
+    create_range_proof, verify_range_proof as verify_range_proof_inner, RangeProof,
+};
+use crate::snapshot::{
+    export_state as export_state_inner, import_state as import_state_inner, SnapshotError,
+};
+use crate::DatabaseVariant::VerkleMemoryDb;
+#[cfg(feature = "rocks_db")]
+use crate::DatabaseVariant::{VerkleDiskDb, VerkleReadOnlyDiskDb};
 use std::convert::TryInto;
 use std::ffi::CStr;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
 use std::mem::transmute;
 use std::ops::Deref;
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_void};
 use std::slice;
+use verkle_trie::committer::test::TestCommitter;
+use verkle_trie::database::generic::GenericBatchDB;
 use verkle_trie::database::Flush;
+use verkle_trie::from_to_bytes::ToBytes;
+use verkle_trie::group_to_field;
+use verkle_variants::precompute::{LagrangeCommitter, SHARED_LAGRANGE_COMMITTER};
 use verkle_variants::{traits::FFI, trie};
 
-#[repr(C)]
-pub enum VerkleTrie {
+// Handles of the objects owned by this library, see `handle`. They are never dereferenced,
+// C and C# only see them as opaque pointers
+pub struct VerkleTrie {
+    _private: [u8; 0],
+}
+
+pub struct Database {
+    _private: [u8; 0],
+}
+
+pub struct VerkleBulkLoader {
+    _private: [u8; 0],
+}
+
+pub struct TrieIterator {
+    _private: [u8; 0],
+}
+
+pub(crate) enum TrieVariant {
     MemoryTest(trie::VerkleTrieMemoryTest),
     MemoryLagrange(trie::VerkleTrieMemoryLagrange),
     MemoryReadOnlyTest(trie::VerkleTrieReadOnlyMemoryTest),
     MemoryReadonlyLagrange(trie::VerkleTrieReadOnlyMemoryLagrange),
+    #[cfg(feature = "rocks_db")]
     RocksdbTest(trie::VerkleTrieRocksDBTest),
+    #[cfg(feature = "rocks_db")]
     RocksdbLagrange(trie::VerkleTrieRocksDBLagrange),
+    #[cfg(feature = "rocks_db")]
     RocksdbReadOnlyTest(trie::VerkleTrieReadOnlyRocksDBTest),
+    #[cfg(feature = "rocks_db")]
     RocksdbReadOnlyLagrange(trie::VerkleTrieReadOnlyRocksDBLagrange),
+    ExternalTest(trie::VerkleTrieExternalTest),
+    ExternalLagrange(trie::VerkleTrieExternalLagrange),
+    RedbTest(trie::VerkleTrieRedbTest),
+    RedbLagrange(trie::VerkleTrieRedbLagrange),
+    RedbReadOnlyTest(trie::VerkleTrieReadOnlyRedbTest),
+    RedbReadOnlyLagrange(trie::VerkleTrieReadOnlyRedbLagrange),
 }
 
-#[repr(C)]
-pub enum Database {
+pub(crate) enum DatabaseVariant {
+    #[cfg(feature = "rocks_db")]
     VerkleDiskDb(database::disk_db::VerkleRocksDB),
+    #[cfg(feature = "rocks_db")]
     VerkleReadOnlyDiskDb(database::disk_db::VerkleReadOnlyRocksDB),
     VerkleMemoryDb(database::memory_db::VerkleMemoryDB),
     VerkleReadOnlyMemoryDb(database::memory_db::VerkleReadOnlyMemoryDB),
+    VerkleExternalDb(database::external_db::VerkleExternalDB),
+    VerkleRedbDb(database::redb_db::VerkleRedbDB),
+    VerkleReadOnlyRedbDb(database::redb_db::VerkleReadOnlyRedbDB),
+}
+
+pub(crate) enum BulkLoaderVariant {
+    #[cfg(feature = "rocks_db")]
+    RocksdbTest(BulkLoader<database::disk_db::VerkleRocksDB, TestCommitter>),
+    #[cfg(feature = "rocks_db")]
+    RocksdbLagrange(BulkLoader<database::disk_db::VerkleRocksDB, LagrangeCommitter>),
+    RedbTest(BulkLoader<database::redb_db::VerkleRedbDB, TestCommitter>),
+    RedbLagrange(BulkLoader<database::redb_db::VerkleRedbDB, LagrangeCommitter>),
 }
 
 #[repr(C)]
@@ -44,12 +119,48 @@ pub struct Proof {
     pub len: usize,
 }
 
+// Leaves of a range together with the proof that they are the complete range
+#[repr(C)]
+pub struct LeafRange {
+    pub keys: *const [u8; 32],
+    pub values: *const [u8; 32],
+    pub len: usize,
+    pub proof: Proof,
+}
+
+// Leaves changed by a read only overlay, see `diff_read_only_db`. `old_values[i]` is the value
+// below the overlay when `existed[i]` is 1, zero when the leaf was created by the overlay
+#[repr(C)]
+pub struct LeafDiff {
+    pub keys: *const [u8; 32],
+    pub old_values: *const [u8; 32],
+    pub new_values: *const [u8; 32],
+    pub existed: *const u8,
+    pub len: usize,
+}
+
+// Summary of `check_db_integrity`, `issues` holds one line of text per issue found
+#[repr(C)]
+pub struct IntegrityReport {
+    pub branches: u64,
+    pub stems: u64,
+    pub leaves: u64,
+    pub mismatched: u64,
+    pub dangling: u64,
+    pub orphans: u64,
+    pub issues: Proof,
+}
+
 #[repr(C)]
 pub enum DatabaseScheme {
     MemoryDb,
     RocksDb,
     MemoryDbReadOnly,
     RocksDbReadOnly,
+    // Key value store of the host, see `create_external_verkle_db`
+    External,
+    // redb file, a pure Rust alternative to RocksDB
+    Redb,
 }
 
 #[repr(C)]
@@ -58,6 +169,144 @@ pub enum CommitScheme {
     PrecomputeLagrange,
 }
 
+// Passes one key and value to Rust, which copies them before returning
+pub type ExternalSink = extern "C" fn(
+    sink: *mut c_void,
+    key: *const u8,
+    key_len: usize,
+    value: *const u8,
+    value_len: usize,
+);
+
+// Callbacks of a key value store provided by the host. `context` is passed back to every
+// callback, which is only ever called from the thread of the FFI call that needs it
+#[repr(C)]
+pub struct ExternalStore {
+    pub context: *mut c_void,
+    // Pass the value stored under `key` to `emit` together with `sink`, if there is one
+    pub get: Option<
+        extern "C" fn(
+            context: *mut c_void,
+            key: *const u8,
+            key_len: usize,
+            sink: *mut c_void,
+            emit: ExternalSink,
+        ),
+    >,
+    // Store the `len` pairs of `keys` and `values`, either all of them or none
+    // Returns 1 on success
+    pub put_batch: Option<
+        extern "C" fn(
+            context: *mut c_void,
+            keys: *const Proof,
+            values: *const Proof,
+            len: usize,
+        ) -> u8,
+    >,
+    // Pass every pair whose key starts with `prefix` to `emit` together with `sink`
+    pub iterate_prefix: Option<
+        extern "C" fn(
+            context: *mut c_void,
+            prefix: *const u8,
+            prefix_len: usize,
+            sink: *mut c_void,
+            emit: ExternalSink,
+        ),
+    >,
+    // Make the stored batches durable. Returns 1 on success
+    pub flush: Option<extern "C" fn(context: *mut c_void) -> u8>,
+    // Called once the database is freed, may be null
+    pub release: Option<extern "C" fn(context: *mut c_void)>,
+}
+
+// Compression of the tables of a RocksDB database
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RocksDbCompression {
+    // Default of RocksDB
+    Snappy,
+    None,
+    Lz4,
+    Zstd,
+}
+
+// Tuning of a RocksDB database, see `create_verkle_db_with_options`
+// A zeroed struct keeps the defaults of RocksDB. Leaves, stems and branches are stored in
+// their own column families, which share the block cache. Bloom filters are only built for
+// these, and branches are never compressed
+#[repr(C)]
+pub struct RocksDbOptions {
+    // Size in bytes of the LRU cache of uncompressed blocks, 0 keeps the default cache
+    pub block_cache_size: u64,
+    // Bits per key of the bloom filter of every table, 0 builds no filter
+    pub bloom_filter_bits: u32,
+    pub compression: RocksDbCompression,
+    // Size in bytes of a memtable before it is written to disk, 0 keeps the default
+    pub write_buffer_size: u64,
+    // Number of table files kept open, 0 keeps the default and -1 keeps every file open
+    pub max_open_files: i32,
+}
+
+impl Default for RocksDbOptions {
+    fn default() -> Self {
+        RocksDbOptions {
+            block_cache_size: 0,
+            bloom_filter_bits: 0,
+            compression: RocksDbCompression::Snappy,
+            write_buffer_size: 0,
+            max_open_files: 0,
+        }
+    }
+}
+
+// Why the last call on this thread failed to resolve one of its handles or to open a
+// database, see `verkle_last_error`. Calls with an invalid handle return null, 0 or nothing
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerkleError {
+    Ok,
+    NullHandle,
+    // Freed or never created
+    InvalidHandle,
+    // A handle of another kind of object, e.g. a database passed as a trie
+    WrongHandleType,
+    // A database that tries, read only databases, namespaces or bulk loaders still borrow
+    HandleInUse,
+    // A database written with a schema this version cannot open or migrate from
+    UnsupportedSchema,
+    // The storage failed, e.g. a database directory that cannot be opened
+    StorageError,
+    // A null pointer passed where the call writes its result
+    NullArgument,
+}
+
+// Version of the exported functions and `#[repr(C)]` types, bumped on every incompatible
+// change. Bindings compare it with `rust_verkle_abi_version` before calling anything else
+pub const RUST_VERKLE_ABI_VERSION: u32 = 2;
+
+#[no_mangle]
+pub extern "C" fn rust_verkle_abi_version() -> u32 {
+    RUST_VERKLE_ABI_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn verkle_last_error() -> VerkleError {
+    handle::last_error()
+}
+
+// Look a handle up, returning `$on_error` if it is not valid. The lookup records the error
+// for `verkle_last_error`. The object stays locked until the result is dropped
+macro_rules! resolve {
+    ($kind:ident, $handle:expr, $on_error:expr) => {
+        match handle::$kind($handle) {
+            Ok(object) => object,
+            Err(_) => return $on_error,
+        }
+    };
+}
+
+// Returns null if a RocksDB or redb database cannot be opened, `verkle_last_error` tells why
+// RocksDB databases can only be opened when built with the `rocks_db` feature
 #[no_mangle]
 pub extern "C" fn create_verkle_db(
     database_scheme: DatabaseScheme,
@@ -66,53 +315,304 @@ pub extern "C" fn create_verkle_db(
     let db_path = unsafe { CStr::from_ptr(db_path).to_str().expect("Invalid pathname") };
 
     let db = match database_scheme {
-        DatabaseScheme::RocksDb => {
-            let _db = database::disk_db::VerkleRocksDB::create_db(db_path);
-            Some(VerkleDiskDb(_db))
-        }
+        DatabaseScheme::RocksDb => return open_rocks_db(db_path, &RocksDbOptions::default()),
         DatabaseScheme::MemoryDb => {
             let _db = database::memory_db::VerkleMemoryDB::create_db(db_path);
             Some(VerkleMemoryDb(_db))
         }
+        DatabaseScheme::Redb => return open_redb_db(db_path),
+        // Needs the callbacks of the host, see `create_external_verkle_db`
+        DatabaseScheme::External => return std::ptr::null_mut(),
         _ => None,
     };
-    let ret = unsafe { transmute(Box::new(db.unwrap())) };
-    ret
+    match db {
+        Some(db) => handle::register_db(db, None),
+        None => std::ptr::null_mut(),
+    }
 }
 
+// Create a RocksDB database at `db_path` tuned with `options`, see `RocksDbOptions`
+// The options are only used while the database is open, it can be reopened without them
+// Returns null if `options` is null or the database cannot be opened, `verkle_last_error`
+// tells why. Databases of an older schema are migrated first
 #[no_mangle]
-pub extern "C" fn create_read_only_verkle_db(db: *mut Database) -> *mut Database {
-    let _db = unsafe { &mut *db };
-    let db_object = match _db {
-        Database::VerkleDiskDb(db) => {
-            let db = Database::VerkleReadOnlyDiskDb(
-                database::disk_db::VerkleReadOnlyRocksDB::create_from_db(db),
-            );
-            unsafe { transmute(Box::new(db)) }
+pub extern "C" fn create_verkle_db_with_options(
+    db_path: *const c_char,
+    options: *const RocksDbOptions,
+) -> *mut Database {
+    if options.is_null() {
+        return std::ptr::null_mut();
+    }
+    let db_path = unsafe { CStr::from_ptr(db_path).to_str().expect("Invalid pathname") };
+    let options = unsafe { &*options };
+    open_rocks_db(db_path, options)
+}
+
+// Open the RocksDB database at `db_path`, recording the outcome for `verkle_last_error`
+#[cfg(feature = "rocks_db")]
+fn open_rocks_db(db_path: &str, options: &RocksDbOptions) -> *mut Database {
+    match database::disk_db::VerkleRocksDB::create_db_with_options(db_path, options) {
+        Ok(_db) => {
+            handle::set_last_error(VerkleError::Ok);
+            handle::register_db(VerkleDiskDb(_db), None)
+        }
+        Err(err) => {
+            handle::set_last_error(match err {
+                OpenError::UnsupportedSchema(_) => VerkleError::UnsupportedSchema,
+                _ => VerkleError::StorageError,
+            });
+            std::ptr::null_mut()
+        }
+    }
+}
+
+// Built without RocksDB, every open fails
+#[cfg(not(feature = "rocks_db"))]
+fn open_rocks_db(_db_path: &str, _options: &RocksDbOptions) -> *mut Database {
+    handle::set_last_error(VerkleError::StorageError);
+    std::ptr::null_mut()
+}
+
+// Open the redb database at `db_path`, recording the outcome for `verkle_last_error`
+fn open_redb_db(db_path: &str) -> *mut Database {
+    match database::redb_db::VerkleRedbDB::open_db(db_path) {
+        Ok(_db) => {
+            handle::set_last_error(VerkleError::Ok);
+            handle::register_db(DatabaseVariant::VerkleRedbDb(_db), None)
         }
-        Database::VerkleMemoryDb(db) => {
-            let db = Database::VerkleReadOnlyMemoryDb(
-                database::memory_db::VerkleReadOnlyMemoryDB::create_from_db(db),
-            );
-            unsafe { transmute(Box::new(db)) }
+        Err(_) => {
+            handle::set_last_error(VerkleError::StorageError);
+            std::ptr::null_mut()
         }
-        _ => _db,
+    }
+}
+
+// Create a database stored in the key value store of the host, see `ExternalStore`
+// The nodes are stored with the same keys and values as in RocksDB
+// Returns null if one of the callbacks other than `release` is missing
+#[no_mangle]
+pub extern "C" fn create_external_verkle_db(store: *const ExternalStore) -> *mut Database {
+    if store.is_null() {
+        return std::ptr::null_mut();
+    }
+    let store = unsafe { &*store };
+    match database::external_db::ExternalKVDb::from_store(store) {
+        Some(kv_db) => handle::register_db(
+            DatabaseVariant::VerkleExternalDb(GenericBatchDB { inner: kv_db }),
+            None,
+        ),
+        None => std::ptr::null_mut(),
+    }
+}
+
+// Create an overlay over `db`: tries created from it read `db` but keep their changes in the
+// overlay. Given an overlay, the new one is stacked on it and reads through it, so overlays
+// can be nested to any depth. Each keeps its own changes, see
+// `clear_temp_changes_read_only_db` and `merge_read_only_db`
+#[no_mangle]
+pub extern "C" fn create_read_only_verkle_db(db: *mut Database) -> *mut Database {
+    let parent = db;
+    let mut _db = resolve!(db, db, std::ptr::null_mut());
+    let db_object = match _db.borrow_static() {
+        #[cfg(feature = "rocks_db")]
+        DatabaseVariant::VerkleDiskDb(db) => DatabaseVariant::VerkleReadOnlyDiskDb(
+            database::disk_db::VerkleReadOnlyRocksDB::create_from_db(db),
+        ),
+        DatabaseVariant::VerkleMemoryDb(db) => DatabaseVariant::VerkleReadOnlyMemoryDb(
+            database::memory_db::VerkleReadOnlyMemoryDB::create_from_db(db),
+        ),
+        DatabaseVariant::VerkleRedbDb(db) => DatabaseVariant::VerkleReadOnlyRedbDb(
+            database::redb_db::VerkleReadOnlyRedbDB::create_from_db(db),
+        ),
+        #[cfg(feature = "rocks_db")]
+        DatabaseVariant::VerkleReadOnlyDiskDb(db) => DatabaseVariant::VerkleReadOnlyDiskDb(
+            database::disk_db::VerkleReadOnlyRocksDB::create_from_overlay(db),
+        ),
+        DatabaseVariant::VerkleReadOnlyMemoryDb(db) => DatabaseVariant::VerkleReadOnlyMemoryDb(
+            database::memory_db::VerkleReadOnlyMemoryDB::create_from_overlay(db),
+        ),
+        DatabaseVariant::VerkleReadOnlyRedbDb(db) => DatabaseVariant::VerkleReadOnlyRedbDb(
+            database::redb_db::VerkleReadOnlyRedbDB::create_from_overlay(db),
+        ),
+        // Overlays are only built over RocksDB, redb and memory databases
+        DatabaseVariant::VerkleExternalDb(_) => return std::ptr::null_mut(),
     };
 
-    db_object
+    handle::register_db(db_object, Some(parent))
+}
+
+// Open the namespace `name` of `db`. Tries created from the returned database keep their
+// nodes and flush record apart from the ones of `db` and of every other namespace, in the same
+// RocksDB or redb database. A namespace is used as any database: read only overlays, bulk
+// loaders and integrity checks work on it, and namespaces can be opened in it
+// Returns null if `db` is not a RocksDB or redb database, or `name` is empty or longer than
+// 255 bytes
+#[no_mangle]
+pub extern "C" fn create_namespaced_verkle_db(
+    db: *mut Database,
+    name: *const u8,
+    name_len: usize,
+) -> *mut Database {
+    let parent = db;
+    let mut _db = resolve!(db, db, std::ptr::null_mut());
+    let _name = proof_ptr_to_proof_vec(name, name_len);
+    let db_object = match &mut *_db {
+        #[cfg(feature = "rocks_db")]
+        DatabaseVariant::VerkleDiskDb(db) => db
+            .create_namespace(&_name)
+            .map(DatabaseVariant::VerkleDiskDb),
+        DatabaseVariant::VerkleRedbDb(db) => db
+            .create_namespace(&_name)
+            .map(DatabaseVariant::VerkleRedbDb),
+        _ => None,
+    };
+
+    match db_object {
+        Some(db_object) => handle::register_db(db_object, Some(parent)),
+        None => std::ptr::null_mut(),
+    }
+}
+
+// Free a database created with `create_verkle_db`, `create_read_only_verkle_db` or
+// `create_namespaced_verkle_db`
+// Returns 0 and leaves the database open while tries, read only databases, namespaces or bulk
+// loaders created from it are not freed, see `verkle_last_error`
+#[no_mangle]
+pub extern "C" fn verkle_db_free(db: *mut Database) -> u8 {
+    if db.is_null() {
+        return 1;
+    }
+    handle::free_db(db).is_ok() as u8
 }
 
 #[no_mangle]
 pub extern "C" fn clear_temp_changes_read_only_db(db: *mut Database) {
-    let _db = unsafe { &mut *db };
+    let mut _db = resolve!(db, db, ());
 
-    match _db {
-        Database::VerkleReadOnlyDiskDb(db) => db.clear_temp_changes(),
-        Database::VerkleReadOnlyMemoryDb(db) => db.clear_temp_changes(),
+    match &mut *_db {
+        #[cfg(feature = "rocks_db")]
+        DatabaseVariant::VerkleReadOnlyDiskDb(db) => db.clear_temp_changes(),
+        DatabaseVariant::VerkleReadOnlyMemoryDb(db) => db.clear_temp_changes(),
+        DatabaseVariant::VerkleReadOnlyRedbDb(db) => db.clear_temp_changes(),
         _ => (),
     };
 }
 
+// Move the changes of an overlay stacked on another one into that one, leaving it empty
+// Tries over the parent see the merged changes once their own changes are flushed
+// Returns 0 if `db` is not an overlay stacked on another overlay
+#[no_mangle]
+pub extern "C" fn merge_read_only_db(db: *mut Database) -> u8 {
+    let mut _db = resolve!(db, db, 0);
+
+    let merged = match &mut *_db {
+        #[cfg(feature = "rocks_db")]
+        DatabaseVariant::VerkleReadOnlyDiskDb(db) => db.merge_into_parent(),
+        DatabaseVariant::VerkleReadOnlyMemoryDb(db) => db.merge_into_parent(),
+        DatabaseVariant::VerkleReadOnlyRedbDb(db) => db.merge_into_parent(),
+        _ => false,
+    };
+    merged as u8
+}
+
+// Write the changes of an overlay into the database below it and empty the overlay. RocksDB
+// and redb write them in a single atomic batch. The database then holds the state of the
+// overlay, with the same root as if the changes had been inserted into a trie over it
+// Other overlays over the database were built on its previous state and should be cleared
+// Returns 0 and keeps the changes if `db` is stacked on another overlay, which has to be
+// merged first with `merge_read_only_db`, or if the write fails, in which case
+// `verkle_last_error` is `StorageError`. Tries and bulk loaders created from the database
+// have to be freed first, until then 0 is returned and `verkle_last_error` is `HandleInUse`
+#[no_mangle]
+pub extern "C" fn commit_temp_changes_read_only_db(db: *mut Database) -> u8 {
+    if handle::base_has_writers(db) {
+        handle::set_last_error(VerkleError::HandleInUse);
+        return 0;
+    }
+    let mut _db = resolve!(db, db, 0);
+
+    let result = match &mut *_db {
+        #[cfg(feature = "rocks_db")]
+        DatabaseVariant::VerkleReadOnlyDiskDb(db) => db.commit_temp_changes(),
+        DatabaseVariant::VerkleReadOnlyMemoryDb(db) => db.commit_temp_changes(),
+        DatabaseVariant::VerkleReadOnlyRedbDb(db) => db.commit_temp_changes(),
+        _ => return 0,
+    };
+    if let Err(CommitError::Storage(_)) = result {
+        handle::set_last_error(VerkleError::StorageError);
+    }
+    result.is_ok() as u8
+}
+
+// The leaves whose value an overlay changed, in key order, with their value below the overlay
+// (the database, or the overlay it is stacked on) and their value in it. Only the changes
+// flushed by its tries are in the overlay: changes still in the batch of a trie, inserted
+// since its last flush, are not reported. Returns null if `db` is not an overlay
+// The result is freed with `verkle_leaf_diff_free`
+#[no_mangle]
+pub extern "C" fn diff_read_only_db(db: *mut Database) -> *mut LeafDiff {
+    let mut _db = resolve!(db, db, std::ptr::null_mut());
+
+    let changes = match &mut *_db {
+        #[cfg(feature = "rocks_db")]
+        DatabaseVariant::VerkleReadOnlyDiskDb(db) => db.changed_leaves(),
+        DatabaseVariant::VerkleReadOnlyMemoryDb(db) => db.changed_leaves(),
+        DatabaseVariant::VerkleReadOnlyRedbDb(db) => db.changed_leaves(),
+        _ => return std::ptr::null_mut(),
+    };
+
+    let keys: Vec<[u8; 32]> = changes.iter().map(|(key, _, _)| *key).collect();
+    let old_values: Vec<[u8; 32]> = changes
+        .iter()
+        .map(|(_, old_value, _)| old_value.unwrap_or([0u8; 32]))
+        .collect();
+    let new_values: Vec<[u8; 32]> = changes.iter().map(|(_, _, value)| *value).collect();
+    let existed: Vec<u8> = changes
+        .iter()
+        .map(|(_, old_value, _)| old_value.is_some() as u8)
+        .collect();
+    // Boxed so that capacity equals `len` and `verkle_leaf_diff_free` can rebuild them
+    let diff = LeafDiff {
+        len: keys.len(),
+        keys: Box::into_raw(keys.into_boxed_slice()) as *const [u8; 32],
+        old_values: Box::into_raw(old_values.into_boxed_slice()) as *const [u8; 32],
+        new_values: Box::into_raw(new_values.into_boxed_slice()) as *const [u8; 32],
+        existed: Box::into_raw(existed.into_boxed_slice()) as *const u8,
+    };
+    unsafe { transmute(Box::new(diff)) }
+}
+
+// Free a `LeafDiff` returned by `diff_read_only_db`, null is ignored
+#[no_mangle]
+pub extern "C" fn verkle_leaf_diff_free(diff: *mut LeafDiff) {
+    if diff.is_null() {
+        return;
+    }
+    unsafe {
+        let diff = Box::from_raw(diff);
+        let len = diff.len;
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            diff.keys as *mut [u8; 32],
+            len,
+        )));
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            diff.old_values as *mut [u8; 32],
+            len,
+        )));
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            diff.new_values as *mut [u8; 32],
+            len,
+        )));
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            diff.existed as *mut u8,
+            len,
+        )));
+    }
+}
+
+// Returns null for the read only and external schemes, their tries are created from a
+// database. Also returns null if a RocksDB or redb database cannot be opened, in which case
+// `verkle_last_error` is `StorageError`
 #[no_mangle]
 pub extern "C" fn verkle_trie_new(
     database_scheme: DatabaseScheme,
@@ -120,71 +620,209 @@ pub extern "C" fn verkle_trie_new(
     db_path: *const c_char,
 ) -> *mut VerkleTrie {
     let db_path = unsafe { CStr::from_ptr(db_path).to_str().expect("Invalid pathname") };
+    let on_disk = matches!(
+        database_scheme,
+        DatabaseScheme::RocksDb | DatabaseScheme::Redb
+    );
 
     let vt = match database_scheme {
         DatabaseScheme::MemoryDb => match commit_scheme {
             CommitScheme::TestCommitment => {
-                let _vt = trie::VerkleTrieMemoryTest::verkle_trie_new(db_path);
-                Some(VerkleTrie::MemoryTest(_vt))
+                trie::VerkleTrieMemoryTest::verkle_trie_new(db_path).map(TrieVariant::MemoryTest)
             }
             CommitScheme::PrecomputeLagrange => {
-                let _vt = trie::VerkleTrieMemoryLagrange::verkle_trie_new(db_path);
-                Some(VerkleTrie::MemoryLagrange(_vt))
+                trie::VerkleTrieMemoryLagrange::verkle_trie_new(db_path)
+                    .map(TrieVariant::MemoryLagrange)
             }
         },
+        #[cfg(feature = "rocks_db")]
         DatabaseScheme::RocksDb => match commit_scheme {
             CommitScheme::TestCommitment => {
-                let _vt = trie::VerkleTrieRocksDBTest::verkle_trie_new(db_path);
-                Some(VerkleTrie::RocksdbTest(_vt))
+                trie::VerkleTrieRocksDBTest::verkle_trie_new(db_path).map(TrieVariant::RocksdbTest)
             }
             CommitScheme::PrecomputeLagrange => {
-                let _vt = trie::VerkleTrieRocksDBLagrange::verkle_trie_new(db_path);
-                Some(VerkleTrie::RocksdbLagrange(_vt))
+                trie::VerkleTrieRocksDBLagrange::verkle_trie_new(db_path)
+                    .map(TrieVariant::RocksdbLagrange)
+            }
+        },
+        DatabaseScheme::Redb => match commit_scheme {
+            CommitScheme::TestCommitment => {
+                trie::VerkleTrieRedbTest::verkle_trie_new(db_path).map(TrieVariant::RedbTest)
+            }
+            CommitScheme::PrecomputeLagrange => {
+                trie::VerkleTrieRedbLagrange::verkle_trie_new(db_path)
+                    .map(TrieVariant::RedbLagrange)
             }
         },
-        // DatabaseScheme::RocksDbReadOnly => match commit_scheme {
-        //     CommitScheme::TestCommitment => {
-        //         let _vt = trie::VerkleTrieRocksDBTest::verkle_trie_new(db_path);
-        //         VerkleTrie::RocksdbReadOnlyTest(_vt)
-        //     }
-        //     CommitScheme::PrecomputeLagrange => {
-        //         let _vt = trie::VerkleTrieRocksDBLagrange::verkle_trie_new(db_path);
-        //         VerkleTrie::RocksdbReadOnlyLagrange(_vt)
-        //     }
-        // },
         _ => None,
     };
-    let ret = unsafe { transmute(Box::new(vt.unwrap())) };
-    ret
+    match vt {
+        Some(vt) => handle::register_trie(vt, None),
+        None => {
+            if on_disk {
+                handle::set_last_error(VerkleError::StorageError);
+            }
+            std::ptr::null_mut()
+        }
+    }
+}
+
+// Free a trie created with `verkle_trie_new` or `create_trie_from_db`. Changes that were
+// not flushed are lost
+// Returns 0 if the handle is not a valid trie, see `verkle_last_error`
+#[no_mangle]
+pub extern "C" fn verkle_trie_free(vt: *mut VerkleTrie) -> u8 {
+    if vt.is_null() {
+        return 1;
+    }
+    handle::free_trie(vt).is_ok() as u8
 }
 
 #[no_mangle]
 pub extern "C" fn verkle_trie_get(vt: *mut VerkleTrie, key: *const u8) -> *const u8 {
-    let _vt = unsafe { &mut *vt };
-    match _vt {
-        VerkleTrie::MemoryTest(vt) => vt.verkle_trie_get(key),
-        VerkleTrie::MemoryLagrange(vt) => vt.verkle_trie_get(key),
-        VerkleTrie::MemoryReadOnlyTest(vt) => vt.verkle_trie_get(key),
-        VerkleTrie::MemoryReadonlyLagrange(vt) => vt.verkle_trie_get(key),
-        VerkleTrie::RocksdbTest(vt) => vt.verkle_trie_get(key),
-        VerkleTrie::RocksdbLagrange(vt) => vt.verkle_trie_get(key),
-        VerkleTrie::RocksdbReadOnlyTest(vt) => vt.verkle_trie_get(key),
-        VerkleTrie::RocksdbReadOnlyLagrange(vt) => vt.verkle_trie_get(key),
+    let mut _vt = resolve!(trie, vt, std::ptr::null());
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => vt.verkle_trie_get(key),
+        TrieVariant::MemoryLagrange(vt) => vt.verkle_trie_get(key),
+        TrieVariant::MemoryReadOnlyTest(vt) => vt.verkle_trie_get(key),
+        TrieVariant::MemoryReadonlyLagrange(vt) => vt.verkle_trie_get(key),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.verkle_trie_get(key),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => vt.verkle_trie_get(key),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => vt.verkle_trie_get(key),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => vt.verkle_trie_get(key),
+        TrieVariant::ExternalTest(vt) => vt.verkle_trie_get(key),
+        TrieVariant::ExternalLagrange(vt) => vt.verkle_trie_get(key),
+        TrieVariant::RedbTest(vt) => vt.verkle_trie_get(key),
+        TrieVariant::RedbLagrange(vt) => vt.verkle_trie_get(key),
+        TrieVariant::RedbReadOnlyTest(vt) => vt.verkle_trie_get(key),
+        TrieVariant::RedbReadOnlyLagrange(vt) => vt.verkle_trie_get(key),
     }
 }
 
+// Flush the changes of the trie to storage in one atomic write
+// Returns 1 on success. On failure nothing is written, the changes are kept in memory so the
+// flush can be retried, and `verkle_last_error` is `VerkleError_StorageError`
 #[no_mangle]
-pub extern "C" fn verkle_trie_flush(vt: *mut VerkleTrie) {
-    let _vt = unsafe { &mut *vt };
-    match _vt {
-        VerkleTrie::MemoryTest(vt) => vt.storage.flush(),
-        VerkleTrie::MemoryLagrange(vt) => vt.storage.flush(),
-        VerkleTrie::MemoryReadOnlyTest(vt) => vt.storage.flush(),
-        VerkleTrie::MemoryReadonlyLagrange(vt) => vt.storage.flush(),
-        VerkleTrie::RocksdbTest(vt) => vt.storage.flush(),
-        VerkleTrie::RocksdbLagrange(vt) => vt.storage.flush(),
-        VerkleTrie::RocksdbReadOnlyTest(vt) => vt.storage.flush(),
-        VerkleTrie::RocksdbReadOnlyLagrange(vt) => vt.storage.flush(),
+pub extern "C" fn verkle_trie_flush(vt: *mut VerkleTrie) -> u8 {
+    let mut _vt = resolve!(trie, vt, 0);
+    let result = match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => {
+            vt.storage.flush();
+            Ok(())
+        }
+        TrieVariant::MemoryLagrange(vt) => {
+            vt.storage.flush();
+            Ok(())
+        }
+        TrieVariant::MemoryReadOnlyTest(vt) => {
+            vt.storage.flush();
+            Ok(())
+        }
+        TrieVariant::MemoryReadonlyLagrange(vt) => {
+            vt.storage.flush();
+            Ok(())
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.storage.try_flush(None),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => vt.storage.try_flush(None),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => vt.storage.try_flush(None),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => vt.storage.try_flush(None),
+        TrieVariant::ExternalTest(vt) => vt.storage.try_flush(None),
+        TrieVariant::ExternalLagrange(vt) => vt.storage.try_flush(None),
+        TrieVariant::RedbTest(vt) => vt.storage.try_flush(None),
+        TrieVariant::RedbLagrange(vt) => vt.storage.try_flush(None),
+        TrieVariant::RedbReadOnlyTest(vt) => vt.storage.try_flush(None),
+        TrieVariant::RedbReadOnlyLagrange(vt) => vt.storage.try_flush(None),
+    };
+    if result.is_err() {
+        handle::set_last_error(VerkleError::StorageError);
+    }
+    result.is_ok() as u8
+}
+
+// Flush the changes of `block` to storage in one atomic write, together with a record of
+// the block number and the root after it, see `get_last_flushed_block`
+// Returns 1 on success. On failure nothing is written and the changes are kept in memory,
+// so the flush can be retried, see `verkle_trie_flush`. Memory databases always succeed and
+// keep no record
+#[no_mangle]
+pub extern "C" fn verkle_trie_flush_block(vt: *mut VerkleTrie, block: u64) -> u8 {
+    let mut _vt = resolve!(trie, vt, 0);
+    let result = match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => {
+            vt.storage.flush();
+            Ok(())
+        }
+        TrieVariant::MemoryLagrange(vt) => {
+            vt.storage.flush();
+            Ok(())
+        }
+        TrieVariant::MemoryReadOnlyTest(vt) => {
+            vt.storage.flush();
+            Ok(())
+        }
+        TrieVariant::MemoryReadonlyLagrange(vt) => {
+            vt.storage.flush();
+            Ok(())
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.storage.try_flush(Some(block)),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => vt.storage.try_flush(Some(block)),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => vt.storage.try_flush(Some(block)),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => vt.storage.try_flush(Some(block)),
+        TrieVariant::ExternalTest(vt) => vt.storage.try_flush(Some(block)),
+        TrieVariant::ExternalLagrange(vt) => vt.storage.try_flush(Some(block)),
+        TrieVariant::RedbTest(vt) => vt.storage.try_flush(Some(block)),
+        TrieVariant::RedbLagrange(vt) => vt.storage.try_flush(Some(block)),
+        TrieVariant::RedbReadOnlyTest(vt) => vt.storage.try_flush(Some(block)),
+        TrieVariant::RedbReadOnlyLagrange(vt) => vt.storage.try_flush(Some(block)),
+    };
+    if result.is_err() {
+        handle::set_last_error(VerkleError::StorageError);
+    }
+    result.is_ok() as u8
+}
+
+// Read the record of the last block flushed with `verkle_trie_flush_block` into `block` and
+// `root` (32 bytes). After a crash, the state in storage is the one of that block
+// Returns 0 if no block was flushed yet or the database is not a RocksDB or redb database
+// A null `block` or `root` returns 0 as well, with `NullArgument` as the last error
+#[no_mangle]
+pub extern "C" fn get_last_flushed_block(db: *mut Database, block: *mut u64, root: *mut u8) -> u8 {
+    let mut _db = resolve!(db, db, 0);
+    if block.is_null() || root.is_null() {
+        handle::set_last_error(VerkleError::NullArgument);
+        return 0;
+    }
+    let record = match &mut *_db {
+        #[cfg(feature = "rocks_db")]
+        DatabaseVariant::VerkleDiskDb(db) => last_flush_record(db),
+        #[cfg(feature = "rocks_db")]
+        DatabaseVariant::VerkleReadOnlyDiskDb(db) => last_flush_record(db),
+        DatabaseVariant::VerkleExternalDb(db) => last_flush_record(db),
+        DatabaseVariant::VerkleRedbDb(db) => last_flush_record(db),
+        DatabaseVariant::VerkleReadOnlyRedbDb(db) => last_flush_record(db),
+        _ => None,
+    };
+    match record {
+        Some(record) => {
+            unsafe {
+                *block = record.block;
+                slice::from_raw_parts_mut(root, 32).copy_from_slice(&record.root);
+            }
+            1
+        }
+        None => 0,
     }
 }
 
@@ -193,87 +831,150 @@ pub extern "C" fn create_trie_from_db(
     commit_scheme: CommitScheme,
     db: *mut Database,
 ) -> *mut VerkleTrie {
-    let _db = unsafe { &mut *db };
-    let vt = match _db {
-        Database::VerkleDiskDb(db) => match commit_scheme {
+    let mut _db = resolve!(db, db, std::ptr::null_mut());
+    handle::register_trie(trie_from_db(commit_scheme, _db.borrow_static()), Some(db))
+}
+
+fn trie_from_db(commit_scheme: CommitScheme, db: &'static mut DatabaseVariant) -> TrieVariant {
+    let vt = match db {
+        #[cfg(feature = "rocks_db")]
+        DatabaseVariant::VerkleDiskDb(db) => match commit_scheme {
             CommitScheme::TestCommitment => {
                 let _vt = trie::VerkleTrieRocksDBTest::create_from_db(db);
-                Some(VerkleTrie::RocksdbTest(_vt))
+                Some(TrieVariant::RocksdbTest(_vt))
             }
             CommitScheme::PrecomputeLagrange => {
                 let _vt = trie::VerkleTrieRocksDBLagrange::create_from_db(db);
-                Some(VerkleTrie::RocksdbLagrange(_vt))
+                Some(TrieVariant::RocksdbLagrange(_vt))
             }
         },
-        Database::VerkleMemoryDb(db) => match commit_scheme {
+        DatabaseVariant::VerkleMemoryDb(db) => match commit_scheme {
             CommitScheme::TestCommitment => {
                 let _vt = trie::VerkleTrieMemoryTest::create_from_db(db);
-                Some(VerkleTrie::MemoryTest(_vt))
+                Some(TrieVariant::MemoryTest(_vt))
             }
             CommitScheme::PrecomputeLagrange => {
                 let _vt = trie::VerkleTrieMemoryLagrange::create_from_db(db);
-                Some(VerkleTrie::MemoryLagrange(_vt))
+                Some(TrieVariant::MemoryLagrange(_vt))
             }
         },
-        Database::VerkleReadOnlyDiskDb(db) => match commit_scheme {
+        #[cfg(feature = "rocks_db")]
+        DatabaseVariant::VerkleReadOnlyDiskDb(db) => match commit_scheme {
             CommitScheme::TestCommitment => {
                 let _vt = trie::VerkleTrieReadOnlyRocksDBTest::create_from_db(db);
-                Some(VerkleTrie::RocksdbReadOnlyTest(_vt))
+                Some(TrieVariant::RocksdbReadOnlyTest(_vt))
             }
             CommitScheme::PrecomputeLagrange => {
                 let _vt = trie::VerkleTrieReadOnlyRocksDBLagrange::create_from_db(db);
-                Some(VerkleTrie::RocksdbReadOnlyLagrange(_vt))
+                Some(TrieVariant::RocksdbReadOnlyLagrange(_vt))
             }
         },
-        Database::VerkleReadOnlyMemoryDb(db) => match commit_scheme {
+        DatabaseVariant::VerkleReadOnlyMemoryDb(db) => match commit_scheme {
             CommitScheme::TestCommitment => {
                 let _vt = trie::VerkleTrieReadOnlyMemoryTest::create_from_db(db);
-                Some(VerkleTrie::MemoryReadOnlyTest(_vt))
+                Some(TrieVariant::MemoryReadOnlyTest(_vt))
             }
             CommitScheme::PrecomputeLagrange => {
                 let _vt = trie::VerkleTrieReadOnlyMemoryLagrange::create_from_db(db);
-                Some(VerkleTrie::MemoryReadonlyLagrange(_vt))
+                Some(TrieVariant::MemoryReadonlyLagrange(_vt))
+            }
+        },
+        DatabaseVariant::VerkleExternalDb(db) => match commit_scheme {
+            CommitScheme::TestCommitment => {
+                let _vt = trie::VerkleTrieExternalTest::create_from_db(db);
+                Some(TrieVariant::ExternalTest(_vt))
+            }
+            CommitScheme::PrecomputeLagrange => {
+                let _vt = trie::VerkleTrieExternalLagrange::create_from_db(db);
+                Some(TrieVariant::ExternalLagrange(_vt))
+            }
+        },
+        DatabaseVariant::VerkleRedbDb(db) => match commit_scheme {
+            CommitScheme::TestCommitment => {
+                let _vt = trie::VerkleTrieRedbTest::create_from_db(db);
+                Some(TrieVariant::RedbTest(_vt))
+            }
+            CommitScheme::PrecomputeLagrange => {
+                let _vt = trie::VerkleTrieRedbLagrange::create_from_db(db);
+                Some(TrieVariant::RedbLagrange(_vt))
+            }
+        },
+        DatabaseVariant::VerkleReadOnlyRedbDb(db) => match commit_scheme {
+            CommitScheme::TestCommitment => {
+                let _vt = trie::VerkleTrieReadOnlyRedbTest::create_from_db(db);
+                Some(TrieVariant::RedbReadOnlyTest(_vt))
+            }
+            CommitScheme::PrecomputeLagrange => {
+                let _vt = trie::VerkleTrieReadOnlyRedbLagrange::create_from_db(db);
+                Some(TrieVariant::RedbReadOnlyLagrange(_vt))
             }
         },
     };
 
-    let ret = unsafe { transmute(Box::new(vt.unwrap())) };
-    ret
+    vt.unwrap()
 }
 
 #[no_mangle]
 pub extern "C" fn verkle_trie_clear(vt: *mut VerkleTrie) {
-    let _vt = unsafe { &mut *vt };
-    match _vt {
-        VerkleTrie::MemoryTest(vt) => {
+    let mut _vt = resolve!(trie, vt, ());
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => {
+            vt.storage.batch.clear();
+            vt.storage.cache.clear()
+        }
+        TrieVariant::MemoryLagrange(vt) => {
+            vt.storage.batch.clear();
+            vt.storage.cache.clear()
+        }
+        TrieVariant::MemoryReadOnlyTest(vt) => {
+            vt.storage.batch.clear();
+            vt.storage.cache.clear()
+        }
+        TrieVariant::MemoryReadonlyLagrange(vt) => {
+            vt.storage.batch.clear();
+            vt.storage.cache.clear()
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => {
+            vt.storage.batch.clear();
+            vt.storage.cache.clear()
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => {
             vt.storage.batch.clear();
             vt.storage.cache.clear()
         }
-        VerkleTrie::MemoryLagrange(vt) => {
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => {
             vt.storage.batch.clear();
             vt.storage.cache.clear()
         }
-        VerkleTrie::MemoryReadOnlyTest(vt) => {
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => {
             vt.storage.batch.clear();
             vt.storage.cache.clear()
         }
-        VerkleTrie::MemoryReadonlyLagrange(vt) => {
+        TrieVariant::ExternalTest(vt) => {
             vt.storage.batch.clear();
             vt.storage.cache.clear()
         }
-        VerkleTrie::RocksdbTest(vt) => {
+        TrieVariant::ExternalLagrange(vt) => {
             vt.storage.batch.clear();
             vt.storage.cache.clear()
         }
-        VerkleTrie::RocksdbLagrange(vt) => {
+        TrieVariant::RedbTest(vt) => {
             vt.storage.batch.clear();
             vt.storage.cache.clear()
         }
-        VerkleTrie::RocksdbReadOnlyTest(vt) => {
+        TrieVariant::RedbLagrange(vt) => {
             vt.storage.batch.clear();
             vt.storage.cache.clear()
         }
-        VerkleTrie::RocksdbReadOnlyLagrange(vt) => {
+        TrieVariant::RedbReadOnlyTest(vt) => {
+            vt.storage.batch.clear();
+            vt.storage.cache.clear()
+        }
+        TrieVariant::RedbReadOnlyLagrange(vt) => {
             vt.storage.batch.clear();
             vt.storage.cache.clear()
         }
@@ -282,46 +983,188 @@ pub extern "C" fn verkle_trie_clear(vt: *mut VerkleTrie) {
 
 #[no_mangle]
 pub extern "C" fn verkle_trie_insert(vt: *mut VerkleTrie, key: *const u8, value: *const u8) {
-    let _vt = unsafe { &mut *vt };
-    match _vt {
-        VerkleTrie::MemoryTest(vt) => vt.verkle_trie_insert(key, value),
-        VerkleTrie::MemoryLagrange(vt) => vt.verkle_trie_insert(key, value),
-        VerkleTrie::MemoryReadOnlyTest(vt) => vt.verkle_trie_insert(key, value),
-        VerkleTrie::MemoryReadonlyLagrange(vt) => vt.verkle_trie_insert(key, value),
-        VerkleTrie::RocksdbTest(vt) => vt.verkle_trie_insert(key, value),
-        VerkleTrie::RocksdbLagrange(vt) => vt.verkle_trie_insert(key, value),
-        VerkleTrie::RocksdbReadOnlyTest(vt) => vt.verkle_trie_insert(key, value),
-        VerkleTrie::RocksdbReadOnlyLagrange(vt) => vt.verkle_trie_insert(key, value),
+    let mut _vt = resolve!(trie, vt, ());
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => vt.verkle_trie_insert(key, value),
+        TrieVariant::MemoryLagrange(vt) => vt.verkle_trie_insert(key, value),
+        TrieVariant::MemoryReadOnlyTest(vt) => vt.verkle_trie_insert(key, value),
+        TrieVariant::MemoryReadonlyLagrange(vt) => vt.verkle_trie_insert(key, value),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.verkle_trie_insert(key, value),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => vt.verkle_trie_insert(key, value),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => vt.verkle_trie_insert(key, value),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => vt.verkle_trie_insert(key, value),
+        TrieVariant::ExternalTest(vt) => vt.verkle_trie_insert(key, value),
+        TrieVariant::ExternalLagrange(vt) => vt.verkle_trie_insert(key, value),
+        TrieVariant::RedbTest(vt) => vt.verkle_trie_insert(key, value),
+        TrieVariant::RedbLagrange(vt) => vt.verkle_trie_insert(key, value),
+        TrieVariant::RedbReadOnlyTest(vt) => vt.verkle_trie_insert(key, value),
+        TrieVariant::RedbReadOnlyLagrange(vt) => vt.verkle_trie_insert(key, value),
     }
 }
 
 #[no_mangle]
 pub extern "C" fn get_root_hash(vt: *mut VerkleTrie) -> *const u8 {
-    let _vt = unsafe { &mut *vt };
-    match _vt {
-        VerkleTrie::MemoryTest(vt) => vt.get_root_hash(),
-        VerkleTrie::MemoryLagrange(vt) => vt.get_root_hash(),
-        VerkleTrie::MemoryReadOnlyTest(vt) => vt.get_root_hash(),
-        VerkleTrie::MemoryReadonlyLagrange(vt) => vt.get_root_hash(),
-        VerkleTrie::RocksdbTest(vt) => vt.get_root_hash(),
-        VerkleTrie::RocksdbLagrange(vt) => vt.get_root_hash(),
-        VerkleTrie::RocksdbReadOnlyTest(vt) => vt.get_root_hash(),
-        VerkleTrie::RocksdbReadOnlyLagrange(vt) => vt.get_root_hash(),
+    let mut _vt = resolve!(trie, vt, std::ptr::null());
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => vt.get_root_hash(),
+        TrieVariant::MemoryLagrange(vt) => vt.get_root_hash(),
+        TrieVariant::MemoryReadOnlyTest(vt) => vt.get_root_hash(),
+        TrieVariant::MemoryReadonlyLagrange(vt) => vt.get_root_hash(),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.get_root_hash(),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => vt.get_root_hash(),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => vt.get_root_hash(),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => vt.get_root_hash(),
+        TrieVariant::ExternalTest(vt) => vt.get_root_hash(),
+        TrieVariant::ExternalLagrange(vt) => vt.get_root_hash(),
+        TrieVariant::RedbTest(vt) => vt.get_root_hash(),
+        TrieVariant::RedbLagrange(vt) => vt.get_root_hash(),
+        TrieVariant::RedbReadOnlyTest(vt) => vt.get_root_hash(),
+        TrieVariant::RedbReadOnlyLagrange(vt) => vt.get_root_hash(),
+    }
+}
+
+// Compressed serialization of the root commitment, as stored in block headers
+#[no_mangle]
+pub extern "C" fn get_root_commitment(vt: *mut VerkleTrie) -> *mut Proof {
+    let mut _vt = resolve!(trie, vt, std::ptr::null_mut());
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => vt.get_root_commitment(),
+        TrieVariant::MemoryLagrange(vt) => vt.get_root_commitment(),
+        TrieVariant::MemoryReadOnlyTest(vt) => vt.get_root_commitment(),
+        TrieVariant::MemoryReadonlyLagrange(vt) => vt.get_root_commitment(),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.get_root_commitment(),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => vt.get_root_commitment(),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => vt.get_root_commitment(),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => vt.get_root_commitment(),
+        TrieVariant::ExternalTest(vt) => vt.get_root_commitment(),
+        TrieVariant::ExternalLagrange(vt) => vt.get_root_commitment(),
+        TrieVariant::RedbTest(vt) => vt.get_root_commitment(),
+        TrieVariant::RedbLagrange(vt) => vt.get_root_commitment(),
+        TrieVariant::RedbReadOnlyTest(vt) => vt.get_root_commitment(),
+        TrieVariant::RedbReadOnlyLagrange(vt) => vt.get_root_commitment(),
+    }
+}
+
+// Map a serialized commitment to the field element used as its hash, so that a commitment
+// can be compared against the output of `get_root_hash`. The hash is written into `hash`
+// (32 bytes). Returns 1 if it was written and 0 for an invalid point or a null `hash`
+#[no_mangle]
+pub extern "C" fn commitment_to_hash(ptr: *const u8, len: usize, hash: *mut u8) -> u8 {
+    if hash.is_null() {
+        handle::set_last_error(VerkleError::NullArgument);
+        return 0;
+    }
+    let commitment_bytes = proof_ptr_to_proof_vec(ptr, len);
+    match proof::point_from_bytes(&commitment_bytes) {
+        Some(commitment) => {
+            let _hash = group_to_field(&commitment).to_bytes();
+            unsafe { slice::from_raw_parts_mut(hash, 32).copy_from_slice(&_hash) };
+            1
+        }
+        None => 0,
+    }
+}
+
+// Serialized `BranchMeta` of the branch node at `path`, null if there is no such branch
+#[no_mangle]
+pub extern "C" fn get_branch_meta(
+    vt: *mut VerkleTrie,
+    path: *const u8,
+    path_len: usize,
+) -> *mut Proof {
+    let mut _vt = resolve!(trie, vt, std::ptr::null_mut());
+    let _path = get_path_from_argument(path, path_len);
+    let meta = match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => get_branch_meta_bytes(&vt.storage, &_path),
+        TrieVariant::MemoryLagrange(vt) => get_branch_meta_bytes(&vt.storage, &_path),
+        TrieVariant::MemoryReadOnlyTest(vt) => get_branch_meta_bytes(&vt.storage, &_path),
+        TrieVariant::MemoryReadonlyLagrange(vt) => get_branch_meta_bytes(&vt.storage, &_path),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => get_branch_meta_bytes(&vt.storage, &_path),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => get_branch_meta_bytes(&vt.storage, &_path),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => get_branch_meta_bytes(&vt.storage, &_path),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => get_branch_meta_bytes(&vt.storage, &_path),
+        TrieVariant::ExternalTest(vt) => get_branch_meta_bytes(&vt.storage, &_path),
+        TrieVariant::ExternalLagrange(vt) => get_branch_meta_bytes(&vt.storage, &_path),
+        TrieVariant::RedbTest(vt) => get_branch_meta_bytes(&vt.storage, &_path),
+        TrieVariant::RedbLagrange(vt) => get_branch_meta_bytes(&vt.storage, &_path),
+        TrieVariant::RedbReadOnlyTest(vt) => get_branch_meta_bytes(&vt.storage, &_path),
+        TrieVariant::RedbReadOnlyLagrange(vt) => get_branch_meta_bytes(&vt.storage, &_path),
+    };
+    match meta {
+        Some(meta) => proof_vec_to_proof_ptr(meta),
+        None => std::ptr::null_mut(),
+    }
+}
+
+// Serialized `StemMeta` of the 31 byte `stem`, null if the stem is not in the trie
+#[no_mangle]
+pub extern "C" fn get_stem_meta(vt: *mut VerkleTrie, stem: *const u8) -> *mut Proof {
+    let mut _vt = resolve!(trie, vt, std::ptr::null_mut());
+    let _stem = get_stem_from_argument(stem);
+    let meta = match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => get_stem_meta_bytes(&vt.storage, _stem),
+        TrieVariant::MemoryLagrange(vt) => get_stem_meta_bytes(&vt.storage, _stem),
+        TrieVariant::MemoryReadOnlyTest(vt) => get_stem_meta_bytes(&vt.storage, _stem),
+        TrieVariant::MemoryReadonlyLagrange(vt) => get_stem_meta_bytes(&vt.storage, _stem),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => get_stem_meta_bytes(&vt.storage, _stem),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => get_stem_meta_bytes(&vt.storage, _stem),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => get_stem_meta_bytes(&vt.storage, _stem),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => get_stem_meta_bytes(&vt.storage, _stem),
+        TrieVariant::ExternalTest(vt) => get_stem_meta_bytes(&vt.storage, _stem),
+        TrieVariant::ExternalLagrange(vt) => get_stem_meta_bytes(&vt.storage, _stem),
+        TrieVariant::RedbTest(vt) => get_stem_meta_bytes(&vt.storage, _stem),
+        TrieVariant::RedbLagrange(vt) => get_stem_meta_bytes(&vt.storage, _stem),
+        TrieVariant::RedbReadOnlyTest(vt) => get_stem_meta_bytes(&vt.storage, _stem),
+        TrieVariant::RedbReadOnlyLagrange(vt) => get_stem_meta_bytes(&vt.storage, _stem),
+    };
+    match meta {
+        Some(meta) => proof_vec_to_proof_ptr(meta),
+        None => std::ptr::null_mut(),
     }
 }
 
 #[no_mangle]
 pub extern "C" fn get_verkle_proof(vt: *mut VerkleTrie, key: *const u8) -> *mut Proof {
-    let _vt = unsafe { &mut *vt };
-    match _vt {
-        VerkleTrie::MemoryTest(vt) => vt.get_verkle_proof(key),
-        VerkleTrie::MemoryLagrange(vt) => vt.get_verkle_proof(key),
-        VerkleTrie::MemoryReadOnlyTest(vt) => vt.get_verkle_proof(key),
-        VerkleTrie::MemoryReadonlyLagrange(vt) => vt.get_verkle_proof(key),
-        VerkleTrie::RocksdbTest(vt) => vt.get_verkle_proof(key),
-        VerkleTrie::RocksdbLagrange(vt) => vt.get_verkle_proof(key),
-        VerkleTrie::RocksdbReadOnlyTest(vt) => vt.get_verkle_proof(key),
-        VerkleTrie::RocksdbReadOnlyLagrange(vt) => vt.get_verkle_proof(key),
+    let mut _vt = resolve!(trie, vt, std::ptr::null_mut());
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => vt.get_verkle_proof(key),
+        TrieVariant::MemoryLagrange(vt) => vt.get_verkle_proof(key),
+        TrieVariant::MemoryReadOnlyTest(vt) => vt.get_verkle_proof(key),
+        TrieVariant::MemoryReadonlyLagrange(vt) => vt.get_verkle_proof(key),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.get_verkle_proof(key),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => vt.get_verkle_proof(key),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => vt.get_verkle_proof(key),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => vt.get_verkle_proof(key),
+        TrieVariant::ExternalTest(vt) => vt.get_verkle_proof(key),
+        TrieVariant::ExternalLagrange(vt) => vt.get_verkle_proof(key),
+        TrieVariant::RedbTest(vt) => vt.get_verkle_proof(key),
+        TrieVariant::RedbLagrange(vt) => vt.get_verkle_proof(key),
+        TrieVariant::RedbReadOnlyTest(vt) => vt.get_verkle_proof(key),
+        TrieVariant::RedbReadOnlyLagrange(vt) => vt.get_verkle_proof(key),
     }
 }
 
@@ -333,20 +1176,30 @@ pub extern "C" fn verify_verkle_proof(
     key: *const u8,
     value: *const u8,
 ) -> u8 {
-    let _vt = unsafe { &mut *vt };
-    match _vt {
-        VerkleTrie::MemoryTest(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
-        VerkleTrie::MemoryLagrange(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
-        VerkleTrie::MemoryReadOnlyTest(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
-        VerkleTrie::MemoryReadonlyLagrange(vt) => {
+    let mut _vt = resolve!(trie, vt, 0);
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
+        TrieVariant::MemoryLagrange(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
+        TrieVariant::MemoryReadOnlyTest(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
+        TrieVariant::MemoryReadonlyLagrange(vt) => {
             vt.verify_verkle_proof(ptr, proof_len, key, value)
         }
-        VerkleTrie::RocksdbTest(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
-        VerkleTrie::RocksdbLagrange(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
-        VerkleTrie::RocksdbReadOnlyTest(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
-        VerkleTrie::RocksdbReadOnlyLagrange(vt) => {
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => {
             vt.verify_verkle_proof(ptr, proof_len, key, value)
         }
+        TrieVariant::ExternalTest(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
+        TrieVariant::ExternalLagrange(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
+        TrieVariant::RedbTest(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
+        TrieVariant::RedbLagrange(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
+        TrieVariant::RedbReadOnlyTest(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
+        TrieVariant::RedbReadOnlyLagrange(vt) => vt.verify_verkle_proof(ptr, proof_len, key, value),
     }
 }
 
@@ -356,16 +1209,26 @@ pub extern "C" fn get_verkle_proof_multiple(
     keys: *const [u8; 32],
     len: usize,
 ) -> *mut Proof {
-    let _vt = unsafe { &mut *vt };
-    match _vt {
-        VerkleTrie::MemoryTest(vt) => vt.get_verkle_proof_multiple(keys, len),
-        VerkleTrie::MemoryLagrange(vt) => vt.get_verkle_proof_multiple(keys, len),
-        VerkleTrie::MemoryReadOnlyTest(vt) => vt.get_verkle_proof_multiple(keys, len),
-        VerkleTrie::MemoryReadonlyLagrange(vt) => vt.get_verkle_proof_multiple(keys, len),
-        VerkleTrie::RocksdbTest(vt) => vt.get_verkle_proof_multiple(keys, len),
-        VerkleTrie::RocksdbLagrange(vt) => vt.get_verkle_proof_multiple(keys, len),
-        VerkleTrie::RocksdbReadOnlyTest(vt) => vt.get_verkle_proof_multiple(keys, len),
-        VerkleTrie::RocksdbReadOnlyLagrange(vt) => vt.get_verkle_proof_multiple(keys, len),
+    let mut _vt = resolve!(trie, vt, std::ptr::null_mut());
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => vt.get_verkle_proof_multiple(keys, len),
+        TrieVariant::MemoryLagrange(vt) => vt.get_verkle_proof_multiple(keys, len),
+        TrieVariant::MemoryReadOnlyTest(vt) => vt.get_verkle_proof_multiple(keys, len),
+        TrieVariant::MemoryReadonlyLagrange(vt) => vt.get_verkle_proof_multiple(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.get_verkle_proof_multiple(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => vt.get_verkle_proof_multiple(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => vt.get_verkle_proof_multiple(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => vt.get_verkle_proof_multiple(keys, len),
+        TrieVariant::ExternalTest(vt) => vt.get_verkle_proof_multiple(keys, len),
+        TrieVariant::ExternalLagrange(vt) => vt.get_verkle_proof_multiple(keys, len),
+        TrieVariant::RedbTest(vt) => vt.get_verkle_proof_multiple(keys, len),
+        TrieVariant::RedbLagrange(vt) => vt.get_verkle_proof_multiple(keys, len),
+        TrieVariant::RedbReadOnlyTest(vt) => vt.get_verkle_proof_multiple(keys, len),
+        TrieVariant::RedbReadOnlyLagrange(vt) => vt.get_verkle_proof_multiple(keys, len),
     }
 }
 
@@ -378,30 +1241,52 @@ pub extern "C" fn verify_verkle_proof_multiple(
     vals: *const [u8; 32],
     len: usize,
 ) -> u8 {
-    let _vt = unsafe { &mut *vt };
-    match _vt {
-        VerkleTrie::MemoryTest(vt) => {
+    let mut _vt = resolve!(trie, vt, 0);
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => {
+            vt.verify_verkle_proof_multiple(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::MemoryLagrange(vt) => {
+            vt.verify_verkle_proof_multiple(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::MemoryReadOnlyTest(vt) => {
+            vt.verify_verkle_proof_multiple(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::MemoryReadonlyLagrange(vt) => {
+            vt.verify_verkle_proof_multiple(ptr, proof_len, keys, vals, len)
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => {
+            vt.verify_verkle_proof_multiple(ptr, proof_len, keys, vals, len)
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => {
+            vt.verify_verkle_proof_multiple(ptr, proof_len, keys, vals, len)
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => {
             vt.verify_verkle_proof_multiple(ptr, proof_len, keys, vals, len)
         }
-        VerkleTrie::MemoryLagrange(vt) => {
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => {
             vt.verify_verkle_proof_multiple(ptr, proof_len, keys, vals, len)
         }
-        VerkleTrie::MemoryReadOnlyTest(vt) => {
+        TrieVariant::ExternalTest(vt) => {
             vt.verify_verkle_proof_multiple(ptr, proof_len, keys, vals, len)
         }
-        VerkleTrie::MemoryReadonlyLagrange(vt) => {
+        TrieVariant::ExternalLagrange(vt) => {
             vt.verify_verkle_proof_multiple(ptr, proof_len, keys, vals, len)
         }
-        VerkleTrie::RocksdbTest(vt) => {
+        TrieVariant::RedbTest(vt) => {
             vt.verify_verkle_proof_multiple(ptr, proof_len, keys, vals, len)
         }
-        VerkleTrie::RocksdbLagrange(vt) => {
+        TrieVariant::RedbLagrange(vt) => {
             vt.verify_verkle_proof_multiple(ptr, proof_len, keys, vals, len)
         }
-        VerkleTrie::RocksdbReadOnlyTest(vt) => {
+        TrieVariant::RedbReadOnlyTest(vt) => {
             vt.verify_verkle_proof_multiple(ptr, proof_len, keys, vals, len)
         }
-        VerkleTrie::RocksdbReadOnlyLagrange(vt) => {
+        TrieVariant::RedbReadOnlyLagrange(vt) => {
             vt.verify_verkle_proof_multiple(ptr, proof_len, keys, vals, len)
         }
     }
@@ -414,16 +1299,863 @@ pub extern "C" fn verkle_trie_insert_multiple(
     vals: *const [u8; 32],
     len: usize,
 ) {
-    let _vt = unsafe { &mut *vt };
-    match _vt {
-        VerkleTrie::MemoryTest(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
-        VerkleTrie::MemoryLagrange(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
-        VerkleTrie::MemoryReadOnlyTest(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
-        VerkleTrie::MemoryReadonlyLagrange(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
-        VerkleTrie::RocksdbTest(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
-        VerkleTrie::RocksdbLagrange(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
-        VerkleTrie::RocksdbReadOnlyTest(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
-        VerkleTrie::RocksdbReadOnlyLagrange(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+    verkle_trie_insert_multiple_with_threshold(
+        vt,
+        keys,
+        vals,
+        len,
+        parallel::DEFAULT_PARALLEL_INSERT_THRESHOLD,
+    )
+}
+
+// Batches of at least `parallel_threshold` keys commit independent subtrees in parallel, see
+// `parallel`. usize::MAX always inserts sequentially and 0 always in parallel
+#[no_mangle]
+pub extern "C" fn verkle_trie_insert_multiple_with_threshold(
+    vt: *mut VerkleTrie,
+    keys: *const [u8; 32],
+    vals: *const [u8; 32],
+    len: usize,
+    parallel_threshold: usize,
+) {
+    let mut _vt = resolve!(trie, vt, ());
+    if len > 0 && len >= parallel_threshold {
+        let _keys = get_vector_from_slice_argument(keys, len);
+        let _vals = get_vector_from_slice_argument(vals, len);
+        let leaves: Vec<_> = _keys.into_iter().zip(_vals.into_iter()).collect();
+        let lagrange = &*SHARED_LAGRANGE_COMMITTER;
+        match &mut *_vt {
+            TrieVariant::MemoryTest(vt) => insert_parallel(&mut vt.storage, &TestCommitter, leaves),
+            TrieVariant::MemoryLagrange(vt) => insert_parallel(&mut vt.storage, lagrange, leaves),
+            TrieVariant::MemoryReadOnlyTest(vt) => {
+                insert_parallel(&mut vt.storage, &TestCommitter, leaves)
+            }
+            TrieVariant::MemoryReadonlyLagrange(vt) => {
+                insert_parallel(&mut vt.storage, lagrange, leaves)
+            }
+            #[cfg(feature = "rocks_db")]
+            TrieVariant::RocksdbTest(vt) => {
+                insert_parallel(&mut vt.storage, &TestCommitter, leaves)
+            }
+            #[cfg(feature = "rocks_db")]
+            TrieVariant::RocksdbLagrange(vt) => insert_parallel(&mut vt.storage, lagrange, leaves),
+            #[cfg(feature = "rocks_db")]
+            TrieVariant::RocksdbReadOnlyTest(vt) => {
+                insert_parallel(&mut vt.storage, &TestCommitter, leaves)
+            }
+            #[cfg(feature = "rocks_db")]
+            TrieVariant::RocksdbReadOnlyLagrange(vt) => {
+                insert_parallel(&mut vt.storage, lagrange, leaves)
+            }
+            // The callbacks of the host are only called from the thread of the call
+            TrieVariant::ExternalTest(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+            TrieVariant::ExternalLagrange(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+            TrieVariant::RedbTest(vt) => insert_parallel(&mut vt.storage, &TestCommitter, leaves),
+            TrieVariant::RedbLagrange(vt) => insert_parallel(&mut vt.storage, lagrange, leaves),
+            TrieVariant::RedbReadOnlyTest(vt) => {
+                insert_parallel(&mut vt.storage, &TestCommitter, leaves)
+            }
+            TrieVariant::RedbReadOnlyLagrange(vt) => {
+                insert_parallel(&mut vt.storage, lagrange, leaves)
+            }
+        }
+        return;
+    }
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+        TrieVariant::MemoryLagrange(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+        TrieVariant::MemoryReadOnlyTest(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+        TrieVariant::MemoryReadonlyLagrange(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+        TrieVariant::ExternalTest(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+        TrieVariant::ExternalLagrange(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+        TrieVariant::RedbTest(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+        TrieVariant::RedbLagrange(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+        TrieVariant::RedbReadOnlyTest(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+        TrieVariant::RedbReadOnlyLagrange(vt) => vt.verkle_trie_insert_multiple(keys, vals, len),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn get_verkle_proof_ssz(
+    vt: *mut VerkleTrie,
+    keys: *const [u8; 32],
+    len: usize,
+) -> *mut Proof {
+    let mut _vt = resolve!(trie, vt, std::ptr::null_mut());
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => vt.get_verkle_proof_ssz(keys, len),
+        TrieVariant::MemoryLagrange(vt) => vt.get_verkle_proof_ssz(keys, len),
+        TrieVariant::MemoryReadOnlyTest(vt) => vt.get_verkle_proof_ssz(keys, len),
+        TrieVariant::MemoryReadonlyLagrange(vt) => vt.get_verkle_proof_ssz(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.get_verkle_proof_ssz(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => vt.get_verkle_proof_ssz(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => vt.get_verkle_proof_ssz(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => vt.get_verkle_proof_ssz(keys, len),
+        TrieVariant::ExternalTest(vt) => vt.get_verkle_proof_ssz(keys, len),
+        TrieVariant::ExternalLagrange(vt) => vt.get_verkle_proof_ssz(keys, len),
+        TrieVariant::RedbTest(vt) => vt.get_verkle_proof_ssz(keys, len),
+        TrieVariant::RedbLagrange(vt) => vt.get_verkle_proof_ssz(keys, len),
+        TrieVariant::RedbReadOnlyTest(vt) => vt.get_verkle_proof_ssz(keys, len),
+        TrieVariant::RedbReadOnlyLagrange(vt) => vt.get_verkle_proof_ssz(keys, len),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn verify_verkle_proof_ssz(
+    vt: *mut VerkleTrie,
+    ptr: *const u8,
+    proof_len: usize,
+    keys: *const [u8; 32],
+    vals: *const [u8; 32],
+    len: usize,
+) -> u8 {
+    let mut _vt = resolve!(trie, vt, 0);
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => vt.verify_verkle_proof_ssz(ptr, proof_len, keys, vals, len),
+        TrieVariant::MemoryLagrange(vt) => {
+            vt.verify_verkle_proof_ssz(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::MemoryReadOnlyTest(vt) => {
+            vt.verify_verkle_proof_ssz(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::MemoryReadonlyLagrange(vt) => {
+            vt.verify_verkle_proof_ssz(ptr, proof_len, keys, vals, len)
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.verify_verkle_proof_ssz(ptr, proof_len, keys, vals, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => {
+            vt.verify_verkle_proof_ssz(ptr, proof_len, keys, vals, len)
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => {
+            vt.verify_verkle_proof_ssz(ptr, proof_len, keys, vals, len)
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => {
+            vt.verify_verkle_proof_ssz(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::ExternalTest(vt) => {
+            vt.verify_verkle_proof_ssz(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::ExternalLagrange(vt) => {
+            vt.verify_verkle_proof_ssz(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::RedbTest(vt) => vt.verify_verkle_proof_ssz(ptr, proof_len, keys, vals, len),
+        TrieVariant::RedbLagrange(vt) => {
+            vt.verify_verkle_proof_ssz(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::RedbReadOnlyTest(vt) => {
+            vt.verify_verkle_proof_ssz(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::RedbReadOnlyLagrange(vt) => {
+            vt.verify_verkle_proof_ssz(ptr, proof_len, keys, vals, len)
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn get_execution_witness_ssz(
+    vt: *mut VerkleTrie,
+    keys: *const [u8; 32],
+    len: usize,
+) -> *mut Proof {
+    let mut _vt = resolve!(trie, vt, std::ptr::null_mut());
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => vt.get_execution_witness_ssz(keys, len),
+        TrieVariant::MemoryLagrange(vt) => vt.get_execution_witness_ssz(keys, len),
+        TrieVariant::MemoryReadOnlyTest(vt) => vt.get_execution_witness_ssz(keys, len),
+        TrieVariant::MemoryReadonlyLagrange(vt) => vt.get_execution_witness_ssz(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.get_execution_witness_ssz(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => vt.get_execution_witness_ssz(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => vt.get_execution_witness_ssz(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => vt.get_execution_witness_ssz(keys, len),
+        TrieVariant::ExternalTest(vt) => vt.get_execution_witness_ssz(keys, len),
+        TrieVariant::ExternalLagrange(vt) => vt.get_execution_witness_ssz(keys, len),
+        TrieVariant::RedbTest(vt) => vt.get_execution_witness_ssz(keys, len),
+        TrieVariant::RedbLagrange(vt) => vt.get_execution_witness_ssz(keys, len),
+        TrieVariant::RedbReadOnlyTest(vt) => vt.get_execution_witness_ssz(keys, len),
+        TrieVariant::RedbReadOnlyLagrange(vt) => vt.get_execution_witness_ssz(keys, len),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn verify_execution_witness_ssz(
+    vt: *mut VerkleTrie,
+    ptr: *const u8,
+    witness_len: usize,
+) -> u8 {
+    let mut _vt = resolve!(trie, vt, 0);
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => vt.verify_execution_witness_ssz(ptr, witness_len),
+        TrieVariant::MemoryLagrange(vt) => vt.verify_execution_witness_ssz(ptr, witness_len),
+        TrieVariant::MemoryReadOnlyTest(vt) => vt.verify_execution_witness_ssz(ptr, witness_len),
+        TrieVariant::MemoryReadonlyLagrange(vt) => {
+            vt.verify_execution_witness_ssz(ptr, witness_len)
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.verify_execution_witness_ssz(ptr, witness_len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => vt.verify_execution_witness_ssz(ptr, witness_len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => vt.verify_execution_witness_ssz(ptr, witness_len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => {
+            vt.verify_execution_witness_ssz(ptr, witness_len)
+        }
+        TrieVariant::ExternalTest(vt) => vt.verify_execution_witness_ssz(ptr, witness_len),
+        TrieVariant::ExternalLagrange(vt) => vt.verify_execution_witness_ssz(ptr, witness_len),
+        TrieVariant::RedbTest(vt) => vt.verify_execution_witness_ssz(ptr, witness_len),
+        TrieVariant::RedbLagrange(vt) => vt.verify_execution_witness_ssz(ptr, witness_len),
+        TrieVariant::RedbReadOnlyTest(vt) => vt.verify_execution_witness_ssz(ptr, witness_len),
+        TrieVariant::RedbReadOnlyLagrange(vt) => vt.verify_execution_witness_ssz(ptr, witness_len),
+    }
+}
+
+// Re-encode a proof from the upstream `VerkleProof::write` format into SSZ
+// Returns null if the input is not a valid proof
+#[no_mangle]
+pub extern "C" fn verkle_proof_to_ssz(ptr: *const u8, proof_len: usize) -> *mut Proof {
+    let proof_bytes = proof_ptr_to_proof_vec(ptr, proof_len);
+    match proof::read_proof(&proof_bytes) {
+        Some(proof) => proof_vec_to_proof_ptr(proof::ssz::encode_verkle_proof(&proof)),
+        None => std::ptr::null_mut(),
+    }
+}
+
+// Re-encode an SSZ proof into the upstream `VerkleProof::write` format
+// Returns null if the input is not a valid proof
+#[no_mangle]
+pub extern "C" fn verkle_proof_from_ssz(ptr: *const u8, proof_len: usize) -> *mut Proof {
+    let proof_bytes = proof_ptr_to_proof_vec(ptr, proof_len);
+    match proof::ssz::decode_verkle_proof(&proof_bytes) {
+        Ok(proof) => {
+            let mut bytes = Vec::new();
+            proof.write(&mut bytes).expect("Could write proof");
+            proof_vec_to_proof_ptr(bytes)
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn get_verkle_proof_json(
+    vt: *mut VerkleTrie,
+    keys: *const [u8; 32],
+    len: usize,
+) -> *mut Proof {
+    let mut _vt = resolve!(trie, vt, std::ptr::null_mut());
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => vt.get_verkle_proof_json(keys, len),
+        TrieVariant::MemoryLagrange(vt) => vt.get_verkle_proof_json(keys, len),
+        TrieVariant::MemoryReadOnlyTest(vt) => vt.get_verkle_proof_json(keys, len),
+        TrieVariant::MemoryReadonlyLagrange(vt) => vt.get_verkle_proof_json(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => vt.get_verkle_proof_json(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => vt.get_verkle_proof_json(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => vt.get_verkle_proof_json(keys, len),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => vt.get_verkle_proof_json(keys, len),
+        TrieVariant::ExternalTest(vt) => vt.get_verkle_proof_json(keys, len),
+        TrieVariant::ExternalLagrange(vt) => vt.get_verkle_proof_json(keys, len),
+        TrieVariant::RedbTest(vt) => vt.get_verkle_proof_json(keys, len),
+        TrieVariant::RedbLagrange(vt) => vt.get_verkle_proof_json(keys, len),
+        TrieVariant::RedbReadOnlyTest(vt) => vt.get_verkle_proof_json(keys, len),
+        TrieVariant::RedbReadOnlyLagrange(vt) => vt.get_verkle_proof_json(keys, len),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn verify_verkle_proof_json(
+    vt: *mut VerkleTrie,
+    ptr: *const u8,
+    proof_len: usize,
+    keys: *const [u8; 32],
+    vals: *const [u8; 32],
+    len: usize,
+) -> u8 {
+    let mut _vt = resolve!(trie, vt, 0);
+    match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => vt.verify_verkle_proof_json(ptr, proof_len, keys, vals, len),
+        TrieVariant::MemoryLagrange(vt) => {
+            vt.verify_verkle_proof_json(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::MemoryReadOnlyTest(vt) => {
+            vt.verify_verkle_proof_json(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::MemoryReadonlyLagrange(vt) => {
+            vt.verify_verkle_proof_json(ptr, proof_len, keys, vals, len)
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => {
+            vt.verify_verkle_proof_json(ptr, proof_len, keys, vals, len)
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => {
+            vt.verify_verkle_proof_json(ptr, proof_len, keys, vals, len)
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => {
+            vt.verify_verkle_proof_json(ptr, proof_len, keys, vals, len)
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => {
+            vt.verify_verkle_proof_json(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::ExternalTest(vt) => {
+            vt.verify_verkle_proof_json(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::ExternalLagrange(vt) => {
+            vt.verify_verkle_proof_json(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::RedbTest(vt) => vt.verify_verkle_proof_json(ptr, proof_len, keys, vals, len),
+        TrieVariant::RedbLagrange(vt) => {
+            vt.verify_verkle_proof_json(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::RedbReadOnlyTest(vt) => {
+            vt.verify_verkle_proof_json(ptr, proof_len, keys, vals, len)
+        }
+        TrieVariant::RedbReadOnlyLagrange(vt) => {
+            vt.verify_verkle_proof_json(ptr, proof_len, keys, vals, len)
+        }
+    }
+}
+
+// Re-encode a proof from the upstream `VerkleProof::write` format into a UTF-8 JSON buffer
+// Returns null if the input is not a valid proof
+#[no_mangle]
+pub extern "C" fn verkle_proof_to_json(ptr: *const u8, proof_len: usize) -> *mut Proof {
+    let proof_bytes = proof_ptr_to_proof_vec(ptr, proof_len);
+    match proof::read_proof(&proof_bytes) {
+        Some(proof) => {
+            proof_vec_to_proof_ptr(proof::json::encode_verkle_proof(&proof).into_bytes())
+        }
+        None => std::ptr::null_mut(),
+    }
+}
+
+// Parse a UTF-8 JSON proof into the upstream `VerkleProof::write` format, so that it can be
+// passed to `verify_verkle_proof_multiple`. Returns null if the input is not a valid proof
+#[no_mangle]
+pub extern "C" fn verkle_proof_from_json(ptr: *const u8, proof_len: usize) -> *mut Proof {
+    let proof_bytes = proof_ptr_to_proof_vec(ptr, proof_len);
+    let proof = std::str::from_utf8(&proof_bytes)
+        .ok()
+        .and_then(|proof_json| proof::json::decode_verkle_proof(proof_json).ok());
+    match proof {
+        Some(proof) => {
+            let mut bytes = Vec::new();
+            proof.write(&mut bytes).expect("Could write proof");
+            proof_vec_to_proof_ptr(bytes)
+        }
+        None => std::ptr::null_mut(),
+    }
+}
+
+// Create an iterator over the leaves of the trie, in key order, starting at `start_key`
+// A null `start_key` starts at the smallest key
+#[no_mangle]
+pub extern "C" fn verkle_trie_iter_new(
+    vt: *mut VerkleTrie,
+    start_key: *const u8,
+) -> *mut TrieIterator {
+    resolve!(trie, vt, std::ptr::null_mut());
+    let _start_key = if start_key.is_null() {
+        [0u8; 32]
+    } else {
+        get_array_from_slice_argument(start_key)
+    };
+    handle::register_iterator(TrieCursor::new(vt, _start_key))
+}
+
+// Write the next key and value into `key` and `value` (32 bytes each)
+// Returns 1 if a leaf was written and 0 once the iterator is exhausted or its trie was freed
+// A null `key` or `value` returns 0 with `NullArgument` as the last error, the leaf is kept
+#[no_mangle]
+pub extern "C" fn verkle_trie_iter_next(
+    iter: *mut TrieIterator,
+    key: *mut u8,
+    value: *mut u8,
+) -> u8 {
+    let mut _iter = resolve!(iterator, iter, 0);
+    let mut _vt = resolve!(trie, _iter.trie, 0);
+    if key.is_null() || value.is_null() {
+        handle::set_last_error(VerkleError::NullArgument);
+        return 0;
+    }
+    let next = match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => _iter.next(&vt.storage),
+        TrieVariant::MemoryLagrange(vt) => _iter.next(&vt.storage),
+        TrieVariant::MemoryReadOnlyTest(vt) => _iter.next(&vt.storage),
+        TrieVariant::MemoryReadonlyLagrange(vt) => _iter.next(&vt.storage),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => _iter.next(&vt.storage),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => _iter.next(&vt.storage),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => _iter.next(&vt.storage),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => _iter.next(&vt.storage),
+        TrieVariant::ExternalTest(vt) => _iter.next(&vt.storage),
+        TrieVariant::ExternalLagrange(vt) => _iter.next(&vt.storage),
+        TrieVariant::RedbTest(vt) => _iter.next(&vt.storage),
+        TrieVariant::RedbLagrange(vt) => _iter.next(&vt.storage),
+        TrieVariant::RedbReadOnlyTest(vt) => _iter.next(&vt.storage),
+        TrieVariant::RedbReadOnlyLagrange(vt) => _iter.next(&vt.storage),
+    };
+    match next {
+        Some((_key, _value)) => {
+            unsafe {
+                slice::from_raw_parts_mut(key, 32).copy_from_slice(&_key);
+                slice::from_raw_parts_mut(value, 32).copy_from_slice(&_value);
+            }
+            1
+        }
+        None => 0,
+    }
+}
+
+// Free a `Proof` returned by any of the functions above, null is ignored
+#[no_mangle]
+pub extern "C" fn verkle_proof_free(proof: *mut Proof) {
+    if proof.is_null() {
+        return;
+    }
+    unsafe {
+        let proof = Box::from_raw(proof);
+        let bytes = std::ptr::slice_from_raw_parts_mut(proof.ptr as *mut u8, proof.len);
+        drop(Box::from_raw(bytes));
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn verkle_trie_iter_free(iter: *mut TrieIterator) {
+    if iter.is_null() {
+        return;
+    }
+    let _ = handle::free_iterator(iter);
+}
+
+// Prove the leaves between `first_key` and `last_key`, serving at most `max_leaves` of them
+// The proven range ends at the last returned leaf, see `proof::range::RangeProof`
+// The result is freed with `verkle_leaf_range_free`
+#[no_mangle]
+pub extern "C" fn get_range_proof(
+    vt: *mut VerkleTrie,
+    first_key: *const u8,
+    last_key: *const u8,
+    max_leaves: usize,
+) -> *mut LeafRange {
+    let mut _vt = resolve!(trie, vt, std::ptr::null_mut());
+    let _first_key = get_array_from_slice_argument(first_key);
+    let _last_key = get_array_from_slice_argument(last_key);
+    let (leaves, range_proof) = match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => create_range_proof(vt, _first_key, _last_key, max_leaves),
+        TrieVariant::MemoryLagrange(vt) => {
+            create_range_proof(vt, _first_key, _last_key, max_leaves)
+        }
+        TrieVariant::MemoryReadOnlyTest(vt) => {
+            create_range_proof(vt, _first_key, _last_key, max_leaves)
+        }
+        TrieVariant::MemoryReadonlyLagrange(vt) => {
+            create_range_proof(vt, _first_key, _last_key, max_leaves)
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => create_range_proof(vt, _first_key, _last_key, max_leaves),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => {
+            create_range_proof(vt, _first_key, _last_key, max_leaves)
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => {
+            create_range_proof(vt, _first_key, _last_key, max_leaves)
+        }
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => {
+            create_range_proof(vt, _first_key, _last_key, max_leaves)
+        }
+        TrieVariant::ExternalTest(vt) => create_range_proof(vt, _first_key, _last_key, max_leaves),
+        TrieVariant::ExternalLagrange(vt) => {
+            create_range_proof(vt, _first_key, _last_key, max_leaves)
+        }
+        TrieVariant::RedbTest(vt) => create_range_proof(vt, _first_key, _last_key, max_leaves),
+        TrieVariant::RedbLagrange(vt) => create_range_proof(vt, _first_key, _last_key, max_leaves),
+        TrieVariant::RedbReadOnlyTest(vt) => {
+            create_range_proof(vt, _first_key, _last_key, max_leaves)
+        }
+        TrieVariant::RedbReadOnlyLagrange(vt) => {
+            create_range_proof(vt, _first_key, _last_key, max_leaves)
+        }
+    };
+
+    let mut proof_bytes = Vec::new();
+    range_proof
+        .write(&mut proof_bytes)
+        .expect("Could write proof");
+
+    // The buffers are boxed so that `verkle_leaf_range_free` can rebuild them from `len`
+    let keys: Vec<[u8; 32]> = leaves.iter().map(|(key, _)| *key).collect();
+    let values: Vec<[u8; 32]> = leaves.iter().map(|(_, value)| *value).collect();
+    let range = LeafRange {
+        keys: Box::into_raw(keys.into_boxed_slice()) as *const [u8; 32],
+        values: Box::into_raw(values.into_boxed_slice()) as *const [u8; 32],
+        len: leaves.len(),
+        proof: Proof {
+            len: proof_bytes.len(),
+            ptr: Box::into_raw(proof_bytes.into_boxed_slice()) as *const u8,
+        },
+    };
+    unsafe { transmute(Box::new(range)) }
+}
+
+// Free a `LeafRange` returned by `get_range_proof`, its proof included, null is ignored
+#[no_mangle]
+pub extern "C" fn verkle_leaf_range_free(range: *mut LeafRange) {
+    if range.is_null() {
+        return;
+    }
+    unsafe {
+        let range = Box::from_raw(range);
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            range.keys as *mut [u8; 32],
+            range.len,
+        )));
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            range.values as *mut [u8; 32],
+            range.len,
+        )));
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            range.proof.ptr as *mut u8,
+            range.proof.len,
+        )));
+    }
+}
+
+// Check that `keys`/`vals` are all the leaves from `first_key` to the last key under the
+// serialized root commitment. Returns 1 if the range is proven complete
+#[no_mangle]
+pub extern "C" fn verify_range_proof(
+    root: *const u8,
+    root_len: usize,
+    first_key: *const u8,
+    keys: *const [u8; 32],
+    vals: *const [u8; 32],
+    len: usize,
+    ptr: *const u8,
+    proof_len: usize,
+) -> u8 {
+    let root_bytes = proof_ptr_to_proof_vec(root, root_len);
+    let root = match proof::point_from_bytes(&root_bytes) {
+        Some(root) => root,
+        None => return 0,
+    };
+    let _first_key = get_array_from_slice_argument(first_key);
+    let leaves: Vec<_> = if len == 0 {
+        Vec::new()
+    } else {
+        let _keys = get_vector_from_slice_argument(keys, len);
+        let _vals = get_vector_from_slice_argument(vals, len);
+        _keys.into_iter().zip(_vals.into_iter()).collect()
+    };
+    let proof_bytes = proof_ptr_to_proof_vec(ptr, proof_len);
+    let range_proof = match RangeProof::read(&proof_bytes[..]) {
+        Ok(range_proof) => range_proof,
+        Err(_) => return 0,
+    };
+    verify_range_proof_inner(root, _first_key, &leaves, range_proof) as u8
+}
+
+// Start a bulk import of sorted leaves straight into the storage of `db`
+// Only RocksDB and redb databases without a trie are supported, returns null for any other
+// database
+#[no_mangle]
+pub extern "C" fn bulk_loader_new(
+    commit_scheme: CommitScheme,
+    db: *mut Database,
+) -> *mut VerkleBulkLoader {
+    let parent = db;
+    let mut _db = resolve!(db, db, std::ptr::null_mut());
+    let loader = match _db.borrow_static() {
+        #[cfg(feature = "rocks_db")]
+        DatabaseVariant::VerkleDiskDb(db) => match commit_scheme {
+            CommitScheme::TestCommitment => {
+                BulkLoader::new(db, &TestCommitter).map(BulkLoaderVariant::RocksdbTest)
+            }
+            CommitScheme::PrecomputeLagrange => {
+                BulkLoader::new(db, &*SHARED_LAGRANGE_COMMITTER)
+                    .map(BulkLoaderVariant::RocksdbLagrange)
+            }
+        },
+        DatabaseVariant::VerkleRedbDb(db) => match commit_scheme {
+            CommitScheme::TestCommitment => {
+                BulkLoader::new(db, &TestCommitter).map(BulkLoaderVariant::RedbTest)
+            }
+            CommitScheme::PrecomputeLagrange => {
+                BulkLoader::new(db, &*SHARED_LAGRANGE_COMMITTER)
+                    .map(BulkLoaderVariant::RedbLagrange)
+            }
+        },
+        _ => return std::ptr::null_mut(),
+    };
+    let loader = match loader {
+        Ok(loader) => loader,
+        Err(_) => return std::ptr::null_mut(),
+    };
+    handle::register_bulk_loader(loader, parent)
+}
+
+// Add the next chunk of leaves. Keys must be strictly increasing, also across chunks
+// Returns 1 on success and 0 if the keys are not sorted, in which case none of the chunk is
+// added, or if a write failed, in which case `verkle_last_error` is `StorageError`
+#[no_mangle]
+pub extern "C" fn bulk_loader_add_leaves(
+    loader: *mut VerkleBulkLoader,
+    keys: *const [u8; 32],
+    vals: *const [u8; 32],
+    len: usize,
+) -> u8 {
+    if len == 0 {
+        return 1;
+    }
+    let mut _loader = resolve!(bulk_loader, loader, 0);
+    let _keys = get_vector_from_slice_argument(keys, len);
+    let _vals = get_vector_from_slice_argument(vals, len);
+    let leaves: Vec<_> = _keys.into_iter().zip(_vals.into_iter()).collect();
+    let result = match &mut *_loader {
+        #[cfg(feature = "rocks_db")]
+        BulkLoaderVariant::RocksdbTest(loader) => loader.add_leaves(&leaves),
+        #[cfg(feature = "rocks_db")]
+        BulkLoaderVariant::RocksdbLagrange(loader) => loader.add_leaves(&leaves),
+        BulkLoaderVariant::RedbTest(loader) => loader.add_leaves(&leaves),
+        BulkLoaderVariant::RedbLagrange(loader) => loader.add_leaves(&leaves),
+    };
+    bulk_load_result(result)
+}
+
+// Commit the remaining nodes and free the loader. If `expected_root` is not null, the
+// serialized root commitment is compared against it before the root is written
+// Returns 1 if the import completed and the root matches, 0 otherwise. A failed write is
+// reported as `StorageError` by `verkle_last_error`
+#[no_mangle]
+pub extern "C" fn bulk_loader_finish(
+    loader: *mut VerkleBulkLoader,
+    expected_root: *const u8,
+    root_len: usize,
+) -> u8 {
+    // The database stays locked until the loader is finished
+    let (_loader, _db) = match handle::free_bulk_loader(loader) {
+        Ok(released) => released,
+        Err(_) => return 0,
+    };
+    let expected = if expected_root.is_null() {
+        None
+    } else {
+        let root_bytes = proof_ptr_to_proof_vec(expected_root, root_len);
+        match proof::point_from_bytes(&root_bytes) {
+            Some(root) => Some(root),
+            None => return 0,
+        }
+    };
+    let result = match _loader {
+        #[cfg(feature = "rocks_db")]
+        BulkLoaderVariant::RocksdbTest(loader) => loader.finish(expected),
+        #[cfg(feature = "rocks_db")]
+        BulkLoaderVariant::RocksdbLagrange(loader) => loader.finish(expected),
+        BulkLoaderVariant::RedbTest(loader) => loader.finish(expected),
+        BulkLoaderVariant::RedbLagrange(loader) => loader.finish(expected),
+    };
+    bulk_load_result(result.map(|_| ()))
+}
+
+// A failed write is reported as `StorageError` by `verkle_last_error`
+fn bulk_load_result(result: Result<(), BulkLoadError>) -> u8 {
+    if let Err(BulkLoadError::WriteFailed(_)) = result {
+        handle::set_last_error(VerkleError::StorageError);
+    }
+    result.is_ok() as u8
+}
+
+// Export every leaf of the trie into the snapshot file at `path`, see `snapshot` for the format
+// With `include_commitments` set to 1 the stem commitments are exported as well
+// Returns 1 on success and 0 if the file could not be written. A stem whose commitment is
+// missing from storage is reported as `StorageError` by `verkle_last_error`
+#[no_mangle]
+pub extern "C" fn export_state(
+    vt: *mut VerkleTrie,
+    path: *const c_char,
+    include_commitments: u8,
+) -> u8 {
+    let mut _vt = resolve!(trie, vt, 0);
+    let path = unsafe { CStr::from_ptr(path).to_str().expect("Invalid pathname") };
+    let file = match File::create(path) {
+        Ok(file) => file,
+        Err(_) => return 0,
+    };
+    let writer = BufWriter::new(file);
+    let commitments = include_commitments != 0;
+    let result = match &mut *_vt {
+        TrieVariant::MemoryTest(vt) => export_state_inner(vt, commitments, writer),
+        TrieVariant::MemoryLagrange(vt) => export_state_inner(vt, commitments, writer),
+        TrieVariant::MemoryReadOnlyTest(vt) => export_state_inner(vt, commitments, writer),
+        TrieVariant::MemoryReadonlyLagrange(vt) => export_state_inner(vt, commitments, writer),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbTest(vt) => export_state_inner(vt, commitments, writer),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbLagrange(vt) => export_state_inner(vt, commitments, writer),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyTest(vt) => export_state_inner(vt, commitments, writer),
+        #[cfg(feature = "rocks_db")]
+        TrieVariant::RocksdbReadOnlyLagrange(vt) => export_state_inner(vt, commitments, writer),
+        TrieVariant::ExternalTest(vt) => export_state_inner(vt, commitments, writer),
+        TrieVariant::ExternalLagrange(vt) => export_state_inner(vt, commitments, writer),
+        TrieVariant::RedbTest(vt) => export_state_inner(vt, commitments, writer),
+        TrieVariant::RedbLagrange(vt) => export_state_inner(vt, commitments, writer),
+        TrieVariant::RedbReadOnlyTest(vt) => export_state_inner(vt, commitments, writer),
+        TrieVariant::RedbReadOnlyLagrange(vt) => export_state_inner(vt, commitments, writer),
+    };
+    if let Err(SnapshotError::MissingStemMeta(_)) = result {
+        handle::set_last_error(VerkleError::StorageError);
+    }
+    result.is_ok() as u8
+}
+
+// Rebuild the state stored in the snapshot file at `path` into `db`, which must not hold a trie
+// Only RocksDB, redb, external and memory databases can be imported into
+// Returns 1 if the checksum and the rebuilt root match the snapshot, 0 otherwise, in which
+// case no root was written. A failed write is reported as `StorageError` by `verkle_last_error`
+#[no_mangle]
+pub extern "C" fn import_state(
+    commit_scheme: CommitScheme,
+    db: *mut Database,
+    path: *const c_char,
+) -> u8 {
+    let path = unsafe { CStr::from_ptr(path).to_str().expect("Invalid pathname") };
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return 0,
+    };
+    let reader = BufReader::new(file);
+    let mut _db = resolve!(db, db, 0);
+    let result = match _db.borrow_static() {
+        #[cfg(feature = "rocks_db")]
+        DatabaseVariant::VerkleDiskDb(db) => match commit_scheme {
+            CommitScheme::TestCommitment => import_state_inner(db, &TestCommitter, reader),
+            CommitScheme::PrecomputeLagrange => {
+                import_state_inner(db, &*SHARED_LAGRANGE_COMMITTER, reader)
+            }
+        },
+        DatabaseVariant::VerkleMemoryDb(db) => match commit_scheme {
+            CommitScheme::TestCommitment => import_state_inner(db, &TestCommitter, reader),
+            CommitScheme::PrecomputeLagrange => {
+                import_state_inner(db, &*SHARED_LAGRANGE_COMMITTER, reader)
+            }
+        },
+        DatabaseVariant::VerkleExternalDb(db) => match commit_scheme {
+            CommitScheme::TestCommitment => import_state_inner(db, &TestCommitter, reader),
+            CommitScheme::PrecomputeLagrange => {
+                import_state_inner(db, &*SHARED_LAGRANGE_COMMITTER, reader)
+            }
+        },
+        DatabaseVariant::VerkleRedbDb(db) => match commit_scheme {
+            CommitScheme::TestCommitment => import_state_inner(db, &TestCommitter, reader),
+            CommitScheme::PrecomputeLagrange => {
+                import_state_inner(db, &*SHARED_LAGRANGE_COMMITTER, reader)
+            }
+        },
+        _ => return 0,
+    };
+    if let Err(SnapshotError::Storage(_)) = result {
+        handle::set_last_error(VerkleError::StorageError);
+    }
+    result.is_ok() as u8
+}
+
+// Walk the trie persisted in `db`, recompute every commitment with the given scheme and
+// report mismatched, dangling and orphaned nodes. Changes that were not flushed are not seen
+// Read only databases cannot be checked, returns null for them
+// The report is freed with `verkle_integrity_report_free`
+#[no_mangle]
+pub extern "C" fn check_db_integrity(
+    commit_scheme: CommitScheme,
+    db: *mut Database,
+) -> *mut IntegrityReport {
+    let mut _db = resolve!(db, db, std::ptr::null_mut());
+    let report = match &mut *_db {
+        #[cfg(feature = "rocks_db")]
+        DatabaseVariant::VerkleDiskDb(db) => match commit_scheme {
+            CommitScheme::TestCommitment => check_integrity(db, &TestCommitter),
+            CommitScheme::PrecomputeLagrange => check_integrity(db, &*SHARED_LAGRANGE_COMMITTER),
+        },
+        DatabaseVariant::VerkleMemoryDb(db) => match commit_scheme {
+            CommitScheme::TestCommitment => check_integrity(db, &TestCommitter),
+            CommitScheme::PrecomputeLagrange => check_integrity(db, &*SHARED_LAGRANGE_COMMITTER),
+        },
+        DatabaseVariant::VerkleExternalDb(db) => match commit_scheme {
+            CommitScheme::TestCommitment => check_integrity(db, &TestCommitter),
+            CommitScheme::PrecomputeLagrange => check_integrity(db, &*SHARED_LAGRANGE_COMMITTER),
+        },
+        DatabaseVariant::VerkleRedbDb(db) => match commit_scheme {
+            CommitScheme::TestCommitment => check_integrity(db, &TestCommitter),
+            CommitScheme::PrecomputeLagrange => check_integrity(db, &*SHARED_LAGRANGE_COMMITTER),
+        },
+        _ => return std::ptr::null_mut(),
+    };
+
+    let mut issues = String::new();
+    for issue in report.issues.iter() {
+        issues.push_str(&issue.to_string());
+        issues.push('\n');
+    }
+    // Boxed so that `verkle_integrity_report_free` can rebuild the bytes from `len`
+    let issues = issues.into_bytes().into_boxed_slice();
+    let count = |filter: fn(&fsck::Issue) -> bool| {
+        report.issues.iter().filter(|issue| filter(issue)).count() as u64
+    };
+    let integrity = IntegrityReport {
+        branches: report.branches,
+        stems: report.stems,
+        leaves: report.leaves,
+        mismatched: count(fsck::Issue::is_mismatch),
+        dangling: count(fsck::Issue::is_dangling),
+        orphans: count(fsck::Issue::is_orphan),
+        issues: Proof {
+            len: issues.len(),
+            ptr: Box::into_raw(issues) as *const u8,
+        },
+    };
+    unsafe { transmute(Box::new(integrity)) }
+}
+
+// Free an `IntegrityReport` returned by `check_db_integrity`, null is ignored
+#[no_mangle]
+pub extern "C" fn verkle_integrity_report_free(report: *mut IntegrityReport) {
+    if report.is_null() {
+        return;
+    }
+    unsafe {
+        let report = Box::from_raw(report);
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            report.issues.ptr as *mut u8,
+            report.issues.len,
+        )));
     }
 }
 
@@ -435,23 +2167,32 @@ pub fn get_array_from_slice_argument(sl: *const u8) -> [u8; 32] {
     _raw_slice.try_into().expect("slice with incorrect length")
 }
 
+// An empty list may come with a null pointer
 pub fn get_vector_from_slice_argument(ptr: *const [u8; 32], len: usize) -> Vec<[u8; 32]> {
+    if len == 0 {
+        return Vec::new();
+    }
     assert!(!ptr.is_null());
     let _raw_slice = unsafe { slice::from_raw_parts(ptr, len) };
-    let mut raw_slice = vec![_raw_slice[0]];
-    for i in 1..=len - 1 {
-        raw_slice.push(_raw_slice[i]);
-    }
-    raw_slice
+    _raw_slice.to_vec()
 }
 
 pub fn proof_ptr_to_proof_vec(ptr: *const u8, len: usize) -> Vec<u8> {
+    if len == 0 {
+        return Vec::new();
+    }
     assert!(!ptr.is_null());
     let _raw_slice = unsafe { slice::from_raw_parts(ptr, len) };
-    // println!("{:?}",_raw_slice);
-    let mut raw_slice = vec![_raw_slice[0]];
-    for i in 1..=len - 1 {
-        raw_slice.push(_raw_slice[i]);
-    }
-    raw_slice
+    _raw_slice.to_vec()
+}
+
+// The bytes are boxed so that capacity equals `len` and `verkle_proof_free` can rebuild them
+pub fn proof_vec_to_proof_ptr(bytes: Vec<u8>) -> *mut Proof {
+    let _len = bytes.len();
+    let _ptr = Box::into_raw(bytes.into_boxed_slice()) as *const u8;
+    let proof = Proof {
+        ptr: _ptr,
+        len: _len,
+    };
+    unsafe { transmute(Box::new(proof)) }
 }
@@ -1,5 +1,5 @@
 use rust_verkle::*;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::mem::transmute;
 use std::os::raw::c_char;
 
@@ -8,7 +8,53 @@ pub fn str_to_cstr(val: &str) -> *const c_char {
     unsafe { CStr::from_bytes_with_nul_unchecked(byte).as_ptr() }
 }
 
+// Check the RocksDB database at `path`, see `check_db_integrity`
+// Exits with status 1 if any issue was found
+fn fsck(path: &str, commit_scheme: CommitScheme) {
+    let db_path = CString::new(path).expect("Invalid pathname");
+    let db = create_verkle_db(DatabaseScheme::RocksDb, db_path.as_ptr());
+    if db.is_null() {
+        println!("could not open {}: {:?}", path, verkle_last_error());
+        std::process::exit(1);
+    }
+
+    println!("checking {}...", path);
+    let report_ptr = check_db_integrity(commit_scheme, db);
+    let report = unsafe { &*report_ptr };
+    let issues = unsafe { std::slice::from_raw_parts(report.issues.ptr, report.issues.len) };
+    print!("{}", String::from_utf8_lossy(issues));
+    println!(
+        "branches: {}, stems: {}, leaves: {}",
+        report.branches, report.stems, report.leaves
+    );
+    println!(
+        "mismatched: {}, dangling: {}, orphans: {}",
+        report.mismatched, report.dangling, report.orphans
+    );
+
+    let found = report.mismatched + report.dangling + report.orphans;
+    verkle_integrity_report_free(report_ptr);
+    if found > 0 {
+        std::process::exit(1);
+    }
+    println!("No issues found");
+}
+
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 && args[1] == "fsck" {
+        let commit_scheme = match args.get(3).map(|scheme| scheme.as_str()) {
+            Some("test") => CommitScheme::TestCommitment,
+            None | Some("lagrange") => CommitScheme::PrecomputeLagrange,
+            Some(scheme) => panic!("unknown commit scheme {}, use test or lagrange", scheme),
+        };
+        match args.get(2) {
+            Some(path) => fsck(path, commit_scheme),
+            None => println!("usage: {} fsck <rocksdb path> [test|lagrange]", args[0]),
+        }
+        return;
+    }
+
     let database_scheme = DatabaseScheme::MemoryDb;
     let commit_scheme = CommitScheme::TestCommitment;
     let path = str_to_cstr("./db/dummy");
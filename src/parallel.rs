@@ -0,0 +1,182 @@
+use crate::commitment::compute_branch_meta;
+use crate::database::verkle_db::VerkleTreeDb;
+use ark_ff::Zero;
+use bandersnatch::{EdwardsProjective, Fr};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use verkle_trie::committer::Committer;
+use verkle_trie::config::Config;
+use verkle_trie::database::memory_db::MemoryDb;
+use verkle_trie::database::{
+    BranchChild, BranchMeta, ReadOnlyHigherDb, StemMeta, WriteOnlyHigherDb,
+};
+use verkle_trie::{Trie, TrieTrait};
+
+// Batch insert that computes the commitments of independent subtrees in parallel
+//
+// `Trie::insert` updates the path of every key one after the other. Here every touched stem
+// is committed first, in parallel, then the stems are placed in the trie and finally the
+// touched branches are recommitted level by level, deepest first: branches of the same level
+// are in disjoint subtrees, so each level is committed in parallel as well
+//
+// Whether this is faster depends on the batch and the machine, compare both paths with
+// `cargo bench --bench parallel_insert` before choosing a threshold
+
+// Default of the batch size from which `verkle_trie_insert_multiple` inserts in parallel
+pub const DEFAULT_PARALLEL_INSERT_THRESHOLD: usize = 1_000;
+
+// Lets the scratch tries of every thread share one committer
+struct SharedCommitter<'a, C>(&'a C);
+
+impl<C: Committer> Committer for SharedCommitter<'_, C> {
+    fn commit_lagrange(&self, evaluations: &[Fr]) -> EdwardsProjective {
+        self.0.commit_lagrange(evaluations)
+    }
+
+    fn scalar_mul(&self, value: Fr, lagrange_index: usize) -> EdwardsProjective {
+        self.0.scalar_mul(value, lagrange_index)
+    }
+}
+
+// Commitments of a stem holding `leaves`, computed by the trie itself: the leaves are
+// inserted into an empty scratch trie, whose stem commitments do not depend on its depth
+fn stem_meta<C: Committer>(committer: &C, stem: [u8; 31], leaves: &[(u8, [u8; 32])]) -> StemMeta {
+    let config = Config {
+        db: MemoryDb::new(),
+        committer: SharedCommitter(committer),
+    };
+    let mut scratch = Trie::new(config);
+    scratch.insert(
+        leaves
+            .iter()
+            .map(|(suffix, value)| (stem_key(&stem, *suffix), *value)),
+    );
+    scratch
+        .storage
+        .get_stem_meta(stem)
+        .expect("stem inserted into the scratch trie is missing")
+}
+
+fn stem_key(stem: &[u8; 31], suffix: u8) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    key[..31].copy_from_slice(stem);
+    key[31] = suffix;
+    key
+}
+
+fn shared_prefix(a: &[u8; 31], b: &[u8; 31]) -> usize {
+    a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count()
+}
+
+pub(crate) fn insert_parallel<S, C>(
+    db: &mut VerkleTreeDb<S>,
+    committer: &C,
+    leaves: Vec<([u8; 32], [u8; 32])>,
+) where
+    VerkleTreeDb<S>: ReadOnlyHigherDb + Sync,
+    C: Committer + Sync,
+{
+    // Later values of the same key win, as with sequential inserts
+    let mut stems: BTreeMap<[u8; 31], BTreeMap<u8, [u8; 32]>> = BTreeMap::new();
+    for (key, value) in leaves {
+        let mut stem = [0u8; 31];
+        stem.copy_from_slice(&key[..31]);
+        stems.entry(stem).or_default().insert(key[31], value);
+    }
+
+    // Commit every stem with its existing leaves and the new ones
+    let shared_db: &VerkleTreeDb<S> = db;
+    let committed: Vec<_> = stems
+        .into_par_iter()
+        .map(|(stem, new_leaves)| {
+            let mut all_leaves: BTreeMap<u8, [u8; 32]> =
+                shared_db.get_stem_children(stem).into_iter().collect();
+            all_leaves.extend(new_leaves.iter().map(|(suffix, value)| (*suffix, *value)));
+            let all_leaves: Vec<_> = all_leaves.into_iter().collect();
+            let meta = stem_meta(committer, stem, &all_leaves);
+            (stem, meta, new_leaves)
+        })
+        .collect();
+
+    // Place the stems, splitting the slots they share with other stems
+    let mut touched: BTreeSet<Vec<u8>> = BTreeSet::new();
+    for (stem, meta, new_leaves) in committed {
+        let mut depth = 0;
+        loop {
+            match db.get_branch_child(&stem[..depth], stem[depth]) {
+                Some(BranchChild::Branch(_)) => depth += 1,
+                Some(BranchChild::Stem(existing)) if existing != stem => {
+                    // Both stems go below new branches down to where they diverge
+                    let shared = shared_prefix(&existing, &stem);
+                    let placeholder = BranchMeta {
+                        commitment: EdwardsProjective::zero(),
+                        hash_commitment: Fr::zero(),
+                    };
+                    for branch_depth in depth + 1..=shared {
+                        db.insert_branch(
+                            stem[..branch_depth].to_vec(),
+                            placeholder,
+                            branch_depth as u8,
+                        );
+                    }
+                    let existing_meta = db
+                        .get_stem_meta(existing)
+                        .expect("stem referenced from a branch is missing");
+                    let existing_depth = shared + 1;
+                    db.add_stem_as_branch_child(
+                        existing[..existing_depth].to_vec(),
+                        existing,
+                        existing_depth as u8,
+                    );
+                    db.insert_stem(existing, existing_meta, existing_depth as u8);
+                    depth = shared;
+                    break;
+                }
+                _ => break,
+            }
+        }
+
+        let stem_depth = depth + 1;
+        for (suffix, value) in new_leaves {
+            db.insert_leaf(stem_key(&stem, suffix), value, stem_depth as u8);
+        }
+        db.insert_stem(stem, meta, stem_depth as u8);
+        db.add_stem_as_branch_child(stem[..stem_depth].to_vec(), stem, stem_depth as u8);
+        for branch_depth in 0..stem_depth {
+            touched.insert(stem[..branch_depth].to_vec());
+        }
+    }
+
+    // Recommit the touched branches, one level at a time from the deepest
+    let mut levels: HashMap<usize, Vec<Vec<u8>>> = HashMap::new();
+    for path in touched {
+        levels.entry(path.len()).or_default().push(path);
+    }
+    let mut depths: Vec<_> = levels.keys().copied().collect();
+    depths.sort_unstable_by(|a, b| b.cmp(a));
+    for depth in depths {
+        let shared_db: &VerkleTreeDb<S> = db;
+        let committed: Vec<_> = levels[&depth]
+            .par_iter()
+            .map(|path| {
+                let children: Vec<(u8, Fr)> = shared_db
+                    .get_branch_children(path)
+                    .into_iter()
+                    .map(|(index, child)| match child {
+                        BranchChild::Branch(meta) => (index, meta.hash_commitment),
+                        BranchChild::Stem(stem) => {
+                            let meta = shared_db
+                                .get_stem_meta(stem)
+                                .expect("stem referenced from a branch is missing");
+                            (index, meta.hash_stem_commitment)
+                        }
+                    })
+                    .collect();
+                (path.clone(), compute_branch_meta(committer, &children))
+            })
+            .collect();
+        for (path, meta) in committed {
+            db.insert_branch(path, meta, depth as u8);
+        }
+    }
+}
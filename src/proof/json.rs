@@ -0,0 +1,198 @@
+use crate::proof::ssz::{IPA_PROOF_DEPTH, MAX_COMMITMENTS_PER_STEM, MAX_STEMS};
+use crate::proof::{
+    depth_extension_from_byte, depth_extension_to_byte, point_from_bytes, point_to_bytes,
+    scalar_from_bytes, scalar_to_bytes,
+};
+use bandersnatch::EdwardsProjective;
+use ipa_multipoint::ipa::IPAProof;
+use ipa_multipoint::multiproof::MultiPointProof;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::convert::TryInto;
+use verkle_trie::proof::{ExtPresent, VerificationHint, VerkleProof};
+
+// Hex encoded JSON form of a proof, as returned by the verkle `eth_getProof` style RPC
+// Every byte string is 0x-prefixed lowercase hex
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpaProofJson {
+    pub cl: Vec<String>,
+    pub cr: Vec<String>,
+    pub final_evaluation: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerkleProofJson {
+    pub other_stems: Vec<String>,
+    pub depth_extension_present: String,
+    pub commitments_by_path: Vec<String>,
+    pub d: String,
+    pub ipa_proof: IpaProofJson,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonError {
+    Parse(String),
+    InvalidHex(String),
+    InvalidLength {
+        expected: usize,
+        got: usize,
+    },
+    InvalidExtensionStatus(u8),
+    // The number of entries of a list does not match the shape of the proof
+    InvalidCount {
+        field: &'static str,
+        max: usize,
+        got: usize,
+    },
+    InvalidPoint,
+    InvalidScalar,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn from_hex(value: &str) -> Result<Vec<u8>, JsonError> {
+    let stripped = value.strip_prefix("0x").unwrap_or(value);
+    hex::decode(stripped).map_err(|_| JsonError::InvalidHex(value.to_string()))
+}
+
+fn from_hex_fixed(value: &str, size: usize) -> Result<Vec<u8>, JsonError> {
+    let bytes = from_hex(value)?;
+    if bytes.len() != size {
+        return Err(JsonError::InvalidLength {
+            expected: size,
+            got: bytes.len(),
+        });
+    }
+    Ok(bytes)
+}
+
+fn point_to_hex(point: &EdwardsProjective) -> String {
+    to_hex(&point_to_bytes(point))
+}
+
+fn point_from_hex(value: &str) -> Result<EdwardsProjective, JsonError> {
+    point_from_bytes(&from_hex_fixed(value, 32)?).ok_or(JsonError::InvalidPoint)
+}
+
+fn points_from_hex(values: &[String]) -> Result<Vec<EdwardsProjective>, JsonError> {
+    values.iter().map(|value| point_from_hex(value)).collect()
+}
+
+fn check_count(field: &'static str, min: usize, max: usize, got: usize) -> Result<(), JsonError> {
+    if got < min || got > max {
+        return Err(JsonError::InvalidCount { field, max, got });
+    }
+    Ok(())
+}
+
+impl From<&VerkleProof> for VerkleProofJson {
+    fn from(proof: &VerkleProof) -> Self {
+        let hint = &proof.verification_hint;
+        let depth_extension_present: Vec<u8> = hint
+            .depths
+            .iter()
+            .zip(hint.extension_present.iter())
+            .map(|(depth, ext)| depth_extension_to_byte(*depth, ext))
+            .collect();
+        let open_proof = &proof.proof.open_proof;
+
+        VerkleProofJson {
+            other_stems: hint
+                .diff_stem_no_proof
+                .iter()
+                .map(|stem| to_hex(stem))
+                .collect(),
+            depth_extension_present: to_hex(&depth_extension_present),
+            commitments_by_path: proof.comms_sorted.iter().map(point_to_hex).collect(),
+            d: point_to_hex(&proof.proof.g_x_comm),
+            ipa_proof: IpaProofJson {
+                cl: open_proof.L_vec.iter().map(point_to_hex).collect(),
+                cr: open_proof.R_vec.iter().map(point_to_hex).collect(),
+                final_evaluation: to_hex(&scalar_to_bytes(&open_proof.a)),
+            },
+        }
+    }
+}
+
+impl VerkleProofJson {
+    pub fn to_proof(&self) -> Result<VerkleProof, JsonError> {
+        let diff_stem_no_proof = self
+            .other_stems
+            .iter()
+            .map(|stem| Ok(from_hex_fixed(stem, 31)?.try_into().unwrap()))
+            .collect::<Result<BTreeSet<[u8; 31]>, JsonError>>()?;
+
+        // One byte per proven stem
+        let depth_extension_present = from_hex(&self.depth_extension_present)?;
+        let stems = depth_extension_present.len();
+        check_count("depthExtensionPresent", 1, MAX_STEMS, stems)?;
+        let mut depths = Vec::new();
+        let mut extension_present = Vec::new();
+        for byte in depth_extension_present {
+            let (depth, ext) =
+                depth_extension_from_byte(byte).ok_or(JsonError::InvalidExtensionStatus(byte))?;
+            depths.push(depth);
+            extension_present.push(ext);
+        }
+        // Every other stem is the one found in place of a proven stem that is absent
+        let different_stems = extension_present
+            .iter()
+            .filter(|ext| matches!(ext, ExtPresent::DifferentStem))
+            .count();
+        check_count("otherStems", 0, different_stems, diff_stem_no_proof.len())?;
+        check_count(
+            "commitmentsByPath",
+            0,
+            stems * MAX_COMMITMENTS_PER_STEM,
+            self.commitments_by_path.len(),
+        )?;
+        check_count(
+            "cl",
+            IPA_PROOF_DEPTH,
+            IPA_PROOF_DEPTH,
+            self.ipa_proof.cl.len(),
+        )?;
+        check_count(
+            "cr",
+            IPA_PROOF_DEPTH,
+            IPA_PROOF_DEPTH,
+            self.ipa_proof.cr.len(),
+        )?;
+
+        let final_evaluation =
+            scalar_from_bytes(&from_hex_fixed(&self.ipa_proof.final_evaluation, 32)?)
+                .ok_or(JsonError::InvalidScalar)?;
+
+        Ok(VerkleProof {
+            verification_hint: VerificationHint {
+                depths,
+                extension_present,
+                diff_stem_no_proof,
+            },
+            comms_sorted: points_from_hex(&self.commitments_by_path)?,
+            proof: MultiPointProof {
+                open_proof: IPAProof {
+                    L_vec: points_from_hex(&self.ipa_proof.cl)?,
+                    R_vec: points_from_hex(&self.ipa_proof.cr)?,
+                    a: final_evaluation,
+                },
+                g_x_comm: point_from_hex(&self.d)?,
+            },
+        })
+    }
+}
+
+pub fn encode_verkle_proof(proof: &VerkleProof) -> String {
+    serde_json::to_string(&VerkleProofJson::from(proof)).expect("could not serialize proof")
+}
+
+pub fn decode_verkle_proof(json: &str) -> Result<VerkleProof, JsonError> {
+    let proof: VerkleProofJson =
+        serde_json::from_str(json).map_err(|err| JsonError::Parse(err.to_string()))?;
+    proof.to_proof()
+}
@@ -0,0 +1,304 @@
+pub mod json;
+pub mod range;
+pub mod ssz;
+
+use ark_ec::ProjectiveCurve;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use bandersnatch::{EdwardsAffine, EdwardsProjective, Fr};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryInto;
+use verkle_trie::proof::{ExtPresent, VerkleProof};
+
+// A single leaf of a stem that was touched by a block, together with the value it
+// held before (current_value) and after (new_value) execution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuffixStateDiff {
+    pub suffix: u8,
+    pub current_value: Option<[u8; 32]>,
+    pub new_value: Option<[u8; 32]>,
+}
+
+// All the touched leaves that share the same stem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StemStateDiff {
+    pub stem: [u8; 31],
+    pub suffix_diffs: Vec<SuffixStateDiff>,
+}
+
+pub type StateDiff = Vec<StemStateDiff>;
+
+// The witness that is shipped along with a block so that a stateless client
+// can execute it: the values that were read and the proof for those values
+#[derive(Debug, Clone)]
+pub struct ExecutionWitness {
+    pub state_diff: StateDiff,
+    pub verkle_proof: VerkleProof,
+}
+
+impl ExecutionWitness {
+    // Build the witness from the keys that were proven, the values that were
+    // present in the trie for those keys and the proof generated for them
+    pub fn new(keys: &[[u8; 32]], values: &[Option<[u8; 32]>], proof: VerkleProof) -> Self {
+        ExecutionWitness {
+            state_diff: state_diff_from_keys_values(keys, values),
+            verkle_proof: proof,
+        }
+    }
+
+    // Flatten the state diff back into the (keys, values) form expected by `VerkleProof::check`
+    pub fn keys_values(&self) -> (Vec<[u8; 32]>, Vec<Option<[u8; 32]>>) {
+        let mut keys = Vec::new();
+        let mut values = Vec::new();
+        for stem_diff in self.state_diff.iter() {
+            for suffix_diff in stem_diff.suffix_diffs.iter() {
+                let mut key = [0u8; 32];
+                key[..31].copy_from_slice(&stem_diff.stem);
+                key[31] = suffix_diff.suffix;
+                keys.push(key);
+                values.push(suffix_diff.current_value);
+            }
+        }
+        (keys, values)
+    }
+}
+
+// Group the keys by stem, ordering both the stems and the suffixes
+pub fn state_diff_from_keys_values(keys: &[[u8; 32]], values: &[Option<[u8; 32]>]) -> StateDiff {
+    let mut stems: BTreeMap<[u8; 31], BTreeMap<u8, Option<[u8; 32]>>> = BTreeMap::new();
+    for (key, value) in keys.iter().zip(values.iter()) {
+        let mut stem = [0u8; 31];
+        stem.copy_from_slice(&key[..31]);
+        stems.entry(stem).or_default().insert(key[31], *value);
+    }
+
+    stems
+        .into_iter()
+        .map(|(stem, suffixes)| StemStateDiff {
+            stem,
+            suffix_diffs: suffixes
+                .into_iter()
+                .map(|(suffix, current_value)| SuffixStateDiff {
+                    suffix,
+                    current_value,
+                    new_value: None,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+const STEM_SIZE: usize = 31;
+const POINT_SIZE: usize = 32;
+const SCALAR_SIZE: usize = 32;
+// The opening proof of a width 256 polynomial: log2(256) rounds of two points
+const IPA_ROUNDS: usize = 8;
+
+// Bytes of `bytes` starting at `*offset`, moving the offset past them
+fn take<'a>(bytes: &'a [u8], offset: &mut usize, len: usize) -> Option<&'a [u8]> {
+    let end = offset.checked_add(len)?;
+    let taken = bytes.get(*offset..end)?;
+    *offset = end;
+    Some(taken)
+}
+
+fn take_count(bytes: &[u8], offset: &mut usize) -> Option<usize> {
+    let count = take(bytes, offset, 4)?;
+    Some(u32::from_le_bytes(count.try_into().ok()?) as usize)
+}
+
+// Walk the layout written by `VerkleProof::write` without building the proof:
+// - the stems without proof: a u32 count followed by 31 bytes per stem
+// - the hint: a u32 count followed by one depth and extension status byte per stem
+// - the commitments: a u32 count followed by 32 bytes per point
+// - the multipoint proof: the commitment to g(x), then the L and R points of every round
+//   and the final scalar
+// `VerkleProof::read` unwraps every point it decodes, so all of them are checked here
+fn is_well_formed_proof(bytes: &[u8]) -> bool {
+    let well_formed = || -> Option<()> {
+        let mut offset = 0;
+        let stems = take_count(bytes, &mut offset)?;
+        take(bytes, &mut offset, stems.checked_mul(STEM_SIZE)?)?;
+
+        let depths = take_count(bytes, &mut offset)?;
+        for byte in take(bytes, &mut offset, depths)? {
+            depth_extension_from_byte(*byte)?;
+        }
+
+        let comms = take_count(bytes, &mut offset)?;
+        // g(x) and the L and R points of every round follow the commitments
+        let points = comms.checked_add(1 + 2 * IPA_ROUNDS)?;
+        for point in take(bytes, &mut offset, points.checked_mul(POINT_SIZE)?)?.chunks(POINT_SIZE) {
+            point_from_bytes(point)?;
+        }
+        scalar_from_bytes(take(bytes, &mut offset, SCALAR_SIZE)?)?;
+
+        (offset == bytes.len()).then(|| ())
+    };
+    well_formed().is_some()
+}
+
+// Parse a proof in the upstream `VerkleProof::write` format received from a peer
+// The bytes are validated first since `VerkleProof::read` panics on some malformed input
+pub fn read_proof(bytes: &[u8]) -> Option<VerkleProof> {
+    if !is_well_formed_proof(bytes) {
+        return None;
+    }
+    VerkleProof::read(bytes).ok()
+}
+
+// Check a proof received from a peer
+// `VerkleProof::check` trusts the shape of the proof and indexes into it freely, so the
+// hint and the commitments must match the keys before checking, see `commitments_by_path`
+// A stem proven absent because another stem sits in its slot needs exactly one such stem,
+// either proven along with it or listed among the stems without proof
+pub fn check_proof(
+    proof: VerkleProof,
+    keys: Vec<[u8; 32]>,
+    values: Vec<Option<[u8; 32]>>,
+    root: EdwardsProjective,
+) -> bool {
+    if keys.is_empty() || keys.len() != values.len() {
+        return false;
+    }
+    if commitments_by_path(&proof, &keys, root).is_none() {
+        return false;
+    }
+
+    let hint = &proof.verification_hint;
+    let stems: BTreeSet<&[u8]> = keys.iter().map(|key| &key[..31]).collect();
+    let slots: Vec<(&[u8], usize, &ExtPresent)> = stems
+        .iter()
+        .zip(hint.depths.iter().zip(hint.extension_present.iter()))
+        .map(|(stem, (depth, ext))| (*stem, *depth as usize, ext))
+        .collect();
+    let occupants: Vec<(&[u8], usize)> = slots
+        .iter()
+        .filter(|(_, _, ext)| matches!(ext, ExtPresent::Present))
+        .map(|(stem, depth, _)| (*stem, *depth))
+        .collect();
+    for (stem, depth, ext) in slots.iter() {
+        if !matches!(ext, ExtPresent::DifferentStem) {
+            continue;
+        }
+        let prefix = &stem[..*depth];
+        let proven = occupants
+            .iter()
+            .filter(|(other, other_depth)| other_depth == depth && other.starts_with(prefix))
+            .count();
+        let unproven = hint
+            .diff_stem_no_proof
+            .iter()
+            .filter(|other| other.starts_with(prefix) && &other[..] != *stem)
+            .count();
+        if proven + unproven != 1 {
+            return false;
+        }
+    }
+
+    proof.check(keys, values, root).0
+}
+
+// Commitment of every node that a proof of `keys` opens, by path, the root included
+// The proof lists the commitments other than the root sorted by path: the branches down to
+// the slot of each stem, the extension sitting in that slot and, for a stem that is present,
+// its C1 or C2 at the extension's path followed by 2 or 3 respectively
+// None if the hint does not match the keys or the proof lists another number of commitments
+pub(crate) fn commitments_by_path(
+    proof: &VerkleProof,
+    keys: &[[u8; 32]],
+    root: EdwardsProjective,
+) -> Option<BTreeMap<Vec<u8>, EdwardsProjective>> {
+    let stems: BTreeSet<&[u8]> = keys.iter().map(|key| &key[..31]).collect();
+    let hint = &proof.verification_hint;
+    if hint.depths.len() != stems.len() || hint.extension_present.len() != stems.len() {
+        return None;
+    }
+    let slots: BTreeMap<&[u8], (usize, &ExtPresent)> = stems
+        .into_iter()
+        .zip(hint.depths.iter().zip(hint.extension_present.iter()))
+        .map(|(stem, (depth, ext))| (stem, (*depth as usize, ext)))
+        .collect();
+
+    let mut paths = BTreeSet::new();
+    for key in keys {
+        let stem = &key[..31];
+        let (depth, ext) = slots[stem];
+        if depth == 0 || depth > 31 {
+            return None;
+        }
+        for i in 0..depth {
+            paths.insert(stem[..i].to_vec());
+        }
+        match ext {
+            ExtPresent::None => {}
+            ExtPresent::DifferentStem => {
+                paths.insert(stem[..depth].to_vec());
+            }
+            ExtPresent::Present => {
+                paths.insert(stem[..depth].to_vec());
+                let mut suffix_path = stem[..depth].to_vec();
+                suffix_path.push(if key[31] < 128 { 2 } else { 3 });
+                paths.insert(suffix_path);
+            }
+        }
+    }
+
+    if paths.len() != proof.comms_sorted.len() + 1 {
+        return None;
+    }
+    let comms = std::iter::once(root).chain(proof.comms_sorted.iter().copied());
+    Some(paths.into_iter().zip(comms).collect())
+}
+
+// Compressed serialization of a group element, as used on the wire by the other clients
+pub fn point_to_bytes(point: &EdwardsProjective) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    point
+        .into_affine()
+        .serialize(&mut bytes[..])
+        .expect("could not serialize point");
+    bytes
+}
+
+pub fn point_from_bytes(bytes: &[u8]) -> Option<EdwardsProjective> {
+    if bytes.len() != 32 {
+        return None;
+    }
+    let affine = EdwardsAffine::deserialize(bytes).ok()?;
+    Some(affine.into_projective())
+}
+
+pub fn scalar_to_bytes(scalar: &Fr) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    scalar
+        .serialize(&mut bytes[..])
+        .expect("could not serialize scalar");
+    bytes
+}
+
+pub fn scalar_from_bytes(bytes: &[u8]) -> Option<Fr> {
+    if bytes.len() != 32 {
+        return None;
+    }
+    Fr::deserialize(bytes).ok()
+}
+
+// The extension status lives in the lowest 3 bits and the depth in the upper 5 bits
+pub(crate) fn depth_extension_to_byte(depth: u8, ext: &ExtPresent) -> u8 {
+    let status = match ext {
+        ExtPresent::None => 0,
+        ExtPresent::DifferentStem => 1,
+        ExtPresent::Present => 2,
+    };
+    status | (depth << 3)
+}
+
+pub(crate) fn depth_extension_from_byte(byte: u8) -> Option<(u8, ExtPresent)> {
+    let ext = match byte & 0b111 {
+        0 => ExtPresent::None,
+        1 => ExtPresent::DifferentStem,
+        2 => ExtPresent::Present,
+        _ => return None,
+    };
+    Some((byte >> 3, ext))
+}
@@ -0,0 +1,392 @@
+use crate::commitment::compute_stem_meta;
+use crate::database::verkle_db::VerkleTreeDb;
+use crate::iterator::LeafIterator;
+use crate::proof::{check_proof, commitments_by_path};
+use bandersnatch::EdwardsProjective;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::convert::TryInto;
+use std::io::{Read, Write};
+use verkle_trie::committer::test::TestCommitter;
+use verkle_trie::database::{BranchChild, ReadOnlyHigherDb};
+use verkle_trie::proof::{ExtPresent, VerkleProof};
+use verkle_trie::{Trie, TrieTrait};
+
+// A proof that a list of leaves is every leaf of the trie in the range
+// [first_key, last leaf key], or [first_key, 0xff..ff] when the list is empty
+//
+// Next to the leaves, the proof opens one "probe" key for every empty slot inside the range,
+// every slot holding a stem outside of it and every stem of the range none of whose leaves
+// are served. Those are proven absent, and together with the depth and extension status of
+// every stem this lets the verifier check that no slot of the range was left out
+//
+// Missing suffixes of a stem in the range are not opened one by one. The proof carries the
+// leaves of the stem outside of the range instead, so that the verifier recomputes the
+// commitment of the stem from all its leaves and compares it with the extension commitment
+// opened by the proof
+pub struct RangeProof {
+    pub probes: Vec<[u8; 32]>,
+    pub stem_leaves: Vec<([u8; 32], [u8; 32])>,
+    pub proof: VerkleProof,
+}
+
+impl RangeProof {
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(&(self.probes.len() as u32).to_le_bytes())?;
+        for probe in self.probes.iter() {
+            writer.write_all(probe)?;
+        }
+        writer.write_all(&(self.stem_leaves.len() as u32).to_le_bytes())?;
+        for (key, value) in self.stem_leaves.iter() {
+            writer.write_all(key)?;
+            writer.write_all(value)?;
+        }
+        self.proof.write(writer)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> std::io::Result<RangeProof> {
+        let mut num_probes = [0u8; 4];
+        reader.read_exact(&mut num_probes)?;
+        let num_probes = u32::from_le_bytes(num_probes) as usize;
+        let mut probes = Vec::new();
+        for _ in 0..num_probes {
+            let mut probe = [0u8; 32];
+            reader.read_exact(&mut probe)?;
+            probes.push(probe);
+        }
+        let mut num_stem_leaves = [0u8; 4];
+        reader.read_exact(&mut num_stem_leaves)?;
+        let num_stem_leaves = u32::from_le_bytes(num_stem_leaves) as usize;
+        let mut stem_leaves = Vec::new();
+        for _ in 0..num_stem_leaves {
+            let mut key = [0u8; 32];
+            let mut value = [0u8; 32];
+            reader.read_exact(&mut key)?;
+            reader.read_exact(&mut value)?;
+            stem_leaves.push((key, value));
+        }
+        let proof = VerkleProof::read(reader)?;
+        Ok(RangeProof {
+            probes,
+            stem_leaves,
+            proof,
+        })
+    }
+}
+
+// Smallest and largest key that start with `prefix`
+fn prefix_bounds(prefix: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let mut low = [0u8; 32];
+    let mut high = [0xffu8; 32];
+    low[..prefix.len()].copy_from_slice(prefix);
+    high[..prefix.len()].copy_from_slice(prefix);
+    (low, high)
+}
+
+fn next_key(key: [u8; 32]) -> Option<[u8; 32]> {
+    let mut next = key;
+    for byte in next.iter_mut().rev() {
+        if *byte == 0xff {
+            *byte = 0;
+        } else {
+            *byte += 1;
+            return Some(next);
+        }
+    }
+    None
+}
+
+fn stem_key(stem: &[u8; 31], suffix: u8) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    key[..31].copy_from_slice(stem);
+    key[31] = suffix;
+    key
+}
+
+// Collect the leaves that the range proof will contain: every leaf from `first_key` up to
+// `last_key`, at most `max_leaves` (and at least one) of them. If there is none, the first
+// leaf after `last_key` is served instead so that the proof still shows the gap is empty
+fn collect_leaves<D: ReadOnlyHigherDb>(
+    db: &D,
+    first_key: [u8; 32],
+    last_key: [u8; 32],
+    max_leaves: usize,
+) -> Vec<([u8; 32], [u8; 32])> {
+    let max_leaves = std::cmp::max(max_leaves, 1);
+    let mut iter = LeafIterator::new(first_key);
+    let mut leaves = Vec::new();
+    while let Some((key, value)) = iter.next(db) {
+        if leaves.len() == max_leaves || (key > last_key && !leaves.is_empty()) {
+            break;
+        }
+        leaves.push((key, value));
+        if key > last_key {
+            break;
+        }
+    }
+    leaves
+}
+
+// Walk every slot of the trie intersecting [first_key, last_key] and record a probe key
+// for each empty slot, each slot holding a stem outside of the range and each stem inside
+// the range without a leaf in it, along with the leaves outside of the range of the stems
+// inside of it
+fn collect_probes<D: ReadOnlyHigherDb>(
+    db: &D,
+    branch_id: &[u8],
+    first_key: [u8; 32],
+    last_key: [u8; 32],
+    probes: &mut Vec<[u8; 32]>,
+    stem_leaves: &mut Vec<([u8; 32], [u8; 32])>,
+) {
+    let children: HashMap<u8, BranchChild> =
+        db.get_branch_children(branch_id).into_iter().collect();
+
+    for index in 0..=255u8 {
+        let mut path = branch_id.to_vec();
+        path.push(index);
+        let (low, high) = prefix_bounds(&path);
+        if high < first_key || low > last_key {
+            continue;
+        }
+        let probe = std::cmp::max(low, first_key);
+
+        match children.get(&index) {
+            None => probes.push(probe),
+            Some(BranchChild::Branch(_)) => {
+                collect_probes(db, &path, first_key, last_key, probes, stem_leaves)
+            }
+            Some(BranchChild::Stem(stem)) => {
+                if stem_key(stem, 0xff) < first_key || stem_key(stem, 0) > last_key {
+                    probes.push(probe);
+                    continue;
+                }
+                let mut served = false;
+                for (suffix, value) in db.get_stem_children(*stem) {
+                    let key = stem_key(stem, suffix);
+                    if key < first_key || key > last_key {
+                        stem_leaves.push((key, value));
+                    } else {
+                        served = true;
+                    }
+                }
+                // The stem must be opened for its extension commitment to be in the proof
+                if !served {
+                    probes.push(std::cmp::max(stem_key(stem, 0), first_key));
+                }
+            }
+        }
+    }
+}
+
+// What the proof says sits in the slot where the search for a stem ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SlotKind {
+    Empty,
+    DifferentStem,
+    Present,
+}
+
+impl From<&ExtPresent> for SlotKind {
+    fn from(ext: &ExtPresent) -> Self {
+        match ext {
+            ExtPresent::None => SlotKind::Empty,
+            ExtPresent::DifferentStem => SlotKind::DifferentStem,
+            ExtPresent::Present => SlotKind::Present,
+        }
+    }
+}
+
+fn range_end(first_key: [u8; 32], leaves: &[([u8; 32], [u8; 32])]) -> [u8; 32] {
+    match leaves.last() {
+        Some((key, _)) if *key >= first_key => *key,
+        _ => [0xff; 32],
+    }
+}
+
+pub fn create_range_proof<S, C>(
+    trie: &mut Trie<VerkleTreeDb<S>, C>,
+    first_key: [u8; 32],
+    last_key: [u8; 32],
+    max_leaves: usize,
+) -> (Vec<([u8; 32], [u8; 32])>, RangeProof)
+where
+    VerkleTreeDb<S>: ReadOnlyHigherDb,
+    Trie<VerkleTreeDb<S>, C>: TrieTrait,
+{
+    let leaves = collect_leaves(&trie.storage, first_key, last_key, max_leaves);
+    let end = range_end(first_key, &leaves);
+
+    let mut probes = Vec::new();
+    let mut stem_leaves = Vec::new();
+    collect_probes(
+        &trie.storage,
+        &[],
+        first_key,
+        end,
+        &mut probes,
+        &mut stem_leaves,
+    );
+
+    let mut keys: Vec<[u8; 32]> = leaves.iter().map(|(key, _)| *key).collect();
+    keys.extend(probes.iter().copied());
+    keys.sort();
+    keys.dedup();
+    let proof = trie.create_verkle_proof(keys.into_iter());
+
+    (
+        leaves,
+        RangeProof {
+            probes,
+            stem_leaves,
+            proof,
+        },
+    )
+}
+
+// Check the proof against `root` and that the leaves are all the leaves in the range
+pub fn verify_range_proof(
+    root: EdwardsProjective,
+    first_key: [u8; 32],
+    leaves: &[([u8; 32], [u8; 32])],
+    range_proof: RangeProof,
+) -> bool {
+    let last_key = range_end(first_key, leaves);
+
+    let mut proven: BTreeMap<[u8; 32], Option<[u8; 32]>> = BTreeMap::new();
+    for (key, value) in leaves {
+        if *key < first_key || proven.insert(*key, Some(*value)).is_some() {
+            return false;
+        }
+    }
+    for probe in range_proof.probes.iter() {
+        if *probe < first_key || *probe > last_key {
+            return false;
+        }
+        if proven.insert(*probe, None).is_some() {
+            return false;
+        }
+    }
+
+    // Every leaf of a stem in the range, as (suffix, value), from the served leaves and the
+    // leaves of the proof outside of the range
+    let mut leaves_by_stem: BTreeMap<[u8; 31], Vec<(u8, [u8; 32])>> = BTreeMap::new();
+    for (key, value) in leaves {
+        let stem: [u8; 31] = key[..31].try_into().unwrap();
+        leaves_by_stem
+            .entry(stem)
+            .or_default()
+            .push((key[31], *value));
+    }
+    let mut outside: BTreeSet<[u8; 32]> = BTreeSet::new();
+    for (key, value) in range_proof.stem_leaves.iter() {
+        if (*key >= first_key && *key <= last_key) || !outside.insert(*key) {
+            return false;
+        }
+        let stem: [u8; 31] = key[..31].try_into().unwrap();
+        leaves_by_stem
+            .entry(stem)
+            .or_default()
+            .push((key[31], *value));
+    }
+
+    let stems: Vec<[u8; 31]> = proven
+        .keys()
+        .map(|key| key[..31].try_into().unwrap())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let hint = &range_proof.proof.verification_hint;
+    if hint.depths.len() != stems.len() || hint.extension_present.len() != stems.len() {
+        return false;
+    }
+    if hint.depths.iter().any(|depth| *depth as usize > 31) {
+        return false;
+    }
+    let stem_info: Vec<([u8; 31], usize, SlotKind)> = stems
+        .iter()
+        .zip(hint.depths.iter())
+        .zip(hint.extension_present.iter())
+        .map(|((stem, depth), ext)| (*stem, *depth as usize, SlotKind::from(ext)))
+        .collect();
+    let other_stems = hint.diff_stem_no_proof.clone();
+
+    let keys: Vec<[u8; 32]> = proven.keys().copied().collect();
+    let values: Vec<Option<[u8; 32]>> = proven.values().copied().collect();
+    let comms = match commitments_by_path(&range_proof.proof, &keys, root) {
+        Some(comms) => comms,
+        None => return false,
+    };
+    if !check_proof(range_proof.proof, keys, values, root) {
+        return false;
+    }
+
+    // Every key of the range must fall in a slot that the proof shows to be empty, or in
+    // a stem whose commitment is recomputed from all of its leaves
+    let mut cursor = first_key;
+    while cursor <= last_key {
+        let slot = stem_info
+            .iter()
+            .find(|(stem, depth, _)| stem[..*depth] == cursor[..*depth]);
+        let (stem, depth, ext) = match slot {
+            Some(slot) => *slot,
+            None => return false,
+        };
+        let (_, slot_end) = prefix_bounds(&cursor[..depth]);
+
+        let occupant = match ext {
+            SlotKind::Empty => None,
+            SlotKind::Present => Some(stem),
+            SlotKind::DifferentStem => {
+                let proven_occupant = stem_info
+                    .iter()
+                    .find(|(other, _, ext)| {
+                        other[..depth] == stem[..depth] && *ext == SlotKind::Present
+                    })
+                    .map(|(other, _, _)| *other);
+                let occupant = proven_occupant.or_else(|| {
+                    other_stems
+                        .iter()
+                        .find(|other| other[..depth] == stem[..depth])
+                        .copied()
+                });
+                match occupant {
+                    Some(occupant) => Some(occupant),
+                    None => return false,
+                }
+            }
+        };
+
+        let next = match occupant {
+            None => next_key(slot_end),
+            Some(occupant) => {
+                let cursor_stem: [u8; 31] = cursor[..31].try_into().unwrap();
+                if cursor_stem < occupant {
+                    // The slot holds a single stem, so nothing lives before it
+                    Some(stem_key(&occupant, 0))
+                } else if cursor_stem > occupant {
+                    next_key(slot_end)
+                } else {
+                    // The extension in the slot commits to every leaf of the stem, so the
+                    // leaves of the range it holds are exactly the served ones
+                    let commitment = match comms.get(&occupant[..depth].to_vec()) {
+                        Some(commitment) => *commitment,
+                        None => return false,
+                    };
+                    let stem_leaves = leaves_by_stem.remove(&occupant).unwrap_or_default();
+                    let meta = compute_stem_meta(&TestCommitter, occupant, &stem_leaves);
+                    if meta.stem_commitment != commitment {
+                        return false;
+                    }
+                    next_key(stem_key(&occupant, 0xff))
+                }
+            }
+        };
+
+        match next {
+            Some(next) => cursor = next,
+            None => break,
+        }
+    }
+
+    true
+}
@@ -0,0 +1,411 @@
+use crate::proof::{
+    depth_extension_from_byte, depth_extension_to_byte, point_from_bytes, point_to_bytes,
+    scalar_from_bytes, scalar_to_bytes, ExecutionWitness, StemStateDiff, SuffixStateDiff,
+};
+use ipa_multipoint::ipa::IPAProof;
+use ipa_multipoint::multiproof::MultiPointProof;
+use std::collections::BTreeSet;
+use std::convert::TryInto;
+use verkle_trie::proof::{VerificationHint, VerkleProof};
+
+// SSZ encoding of the proof and witness containers, following the layout used by the
+// consensus-layer specs for stateless verkle:
+//
+// class IPAProof(Container):
+//     cl: Vector[BanderwagonGroupElement, IPA_PROOF_DEPTH]
+//     cr: Vector[BanderwagonGroupElement, IPA_PROOF_DEPTH]
+//     final_evaluation: BanderwagonFieldElement
+//
+// class VerkleProof(Container):
+//     other_stems: List[Bytes31, MAX_STEMS]
+//     depth_extension_present: ByteList[MAX_STEMS]
+//     commitments_by_path: List[BanderwagonGroupElement, MAX_STEMS * MAX_COMMITMENTS_PER_STEM]
+//     d: BanderwagonGroupElement
+//     ipa_proof: IPAProof
+//
+// class SuffixStateDiff(Container):
+//     suffix: Byte
+//     current_value: Optional[Bytes32]
+//     new_value: Optional[Bytes32]
+//
+// class StemStateDiff(Container):
+//     stem: Stem
+//     suffix_diffs: List[SuffixStateDiff, VERKLE_WIDTH]
+//
+// class ExecutionWitness(Container):
+//     state_diff: List[StemStateDiff, MAX_STEMS]
+//     verkle_proof: VerkleProof
+//
+// Not yet checked against another client: the vectors in `tests/proof_ssz_test.rs` were
+// produced by this encoder, cross-client vectors still have to be added
+
+pub const BYTES_PER_OFFSET: usize = 4;
+pub const IPA_PROOF_DEPTH: usize = 8;
+pub const MAX_STEMS: usize = 1 << 16;
+pub const MAX_COMMITMENTS_PER_STEM: usize = 33;
+pub const VERKLE_WIDTH: usize = 256;
+
+const POINT_SIZE: usize = 32;
+const SCALAR_SIZE: usize = 32;
+const STEM_SIZE: usize = 31;
+const IPA_PROOF_SIZE: usize = 2 * IPA_PROOF_DEPTH * POINT_SIZE + SCALAR_SIZE;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SszError {
+    InvalidLength { expected: usize, got: usize },
+    InvalidOffset(usize),
+    InvalidUnionSelector(u8),
+    InvalidExtensionStatus(u8),
+    ListTooLong { max: usize, got: usize },
+    InvalidPoint,
+    InvalidScalar,
+}
+
+enum Field {
+    Fixed(Vec<u8>),
+    Variable(Vec<u8>),
+}
+
+fn encode_container(fields: Vec<Field>) -> Vec<u8> {
+    let fixed_len: usize = fields
+        .iter()
+        .map(|field| match field {
+            Field::Fixed(bytes) => bytes.len(),
+            Field::Variable(_) => BYTES_PER_OFFSET,
+        })
+        .sum();
+
+    let mut fixed = Vec::with_capacity(fixed_len);
+    let mut variable = Vec::new();
+    for field in fields {
+        match field {
+            Field::Fixed(bytes) => fixed.extend_from_slice(&bytes),
+            Field::Variable(bytes) => {
+                let offset = (fixed_len + variable.len()) as u32;
+                fixed.extend_from_slice(&offset.to_le_bytes());
+                variable.extend_from_slice(&bytes);
+            }
+        }
+    }
+    fixed.extend_from_slice(&variable);
+    fixed
+}
+
+// `layout` describes every field of the container: Some(size) for fixed size fields
+// and None for variable size fields. Returns the bytes belonging to each field
+fn decode_container<'a>(
+    bytes: &'a [u8],
+    layout: &[Option<usize>],
+) -> Result<Vec<&'a [u8]>, SszError> {
+    let fixed_len: usize = layout
+        .iter()
+        .map(|size| size.unwrap_or(BYTES_PER_OFFSET))
+        .sum();
+    if bytes.len() < fixed_len {
+        return Err(SszError::InvalidLength {
+            expected: fixed_len,
+            got: bytes.len(),
+        });
+    }
+
+    let mut position = 0;
+    let mut fixed_parts = Vec::with_capacity(layout.len());
+    let mut offsets = Vec::new();
+    for size in layout {
+        match size {
+            Some(size) => {
+                fixed_parts.push(Some(&bytes[position..position + size]));
+                position += size;
+            }
+            None => {
+                let offset = read_offset(&bytes[position..])?;
+                // Offsets must point right after the fixed part and never go backwards
+                let previous = offsets.last().copied().unwrap_or(fixed_len);
+                if (offsets.is_empty() && offset != fixed_len)
+                    || offset < previous
+                    || offset > bytes.len()
+                {
+                    return Err(SszError::InvalidOffset(offset));
+                }
+                offsets.push(offset);
+                fixed_parts.push(None);
+                position += BYTES_PER_OFFSET;
+            }
+        }
+    }
+
+    if offsets.is_empty() && bytes.len() != fixed_len {
+        return Err(SszError::InvalidLength {
+            expected: fixed_len,
+            got: bytes.len(),
+        });
+    }
+
+    let mut variable_parts = offsets.iter().enumerate().map(|(i, start)| {
+        let end = offsets.get(i + 1).copied().unwrap_or(bytes.len());
+        &bytes[*start..end]
+    });
+
+    Ok(fixed_parts
+        .into_iter()
+        .map(|part| match part {
+            Some(part) => part,
+            None => variable_parts.next().unwrap(),
+        })
+        .collect())
+}
+
+fn read_offset(bytes: &[u8]) -> Result<usize, SszError> {
+    if bytes.len() < BYTES_PER_OFFSET {
+        return Err(SszError::InvalidLength {
+            expected: BYTES_PER_OFFSET,
+            got: bytes.len(),
+        });
+    }
+    let offset: [u8; BYTES_PER_OFFSET] = bytes[..BYTES_PER_OFFSET].try_into().unwrap();
+    Ok(u32::from_le_bytes(offset) as usize)
+}
+
+// A list of variable size elements is a table of offsets followed by the elements
+fn encode_variable_list(items: Vec<Vec<u8>>) -> Vec<u8> {
+    encode_container(items.into_iter().map(Field::Variable).collect())
+}
+
+fn decode_variable_list(bytes: &[u8], max: usize) -> Result<Vec<&[u8]>, SszError> {
+    if bytes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let first_offset = read_offset(bytes)?;
+    if first_offset % BYTES_PER_OFFSET != 0 || first_offset == 0 {
+        return Err(SszError::InvalidOffset(first_offset));
+    }
+    let count = first_offset / BYTES_PER_OFFSET;
+    if count > max {
+        return Err(SszError::ListTooLong { max, got: count });
+    }
+    decode_container(bytes, &vec![None; count])
+}
+
+fn decode_fixed_list(bytes: &[u8], size: usize, max: usize) -> Result<Vec<&[u8]>, SszError> {
+    if bytes.len() % size != 0 {
+        return Err(SszError::InvalidLength {
+            expected: bytes.len() - bytes.len() % size,
+            got: bytes.len(),
+        });
+    }
+    let count = bytes.len() / size;
+    if count > max {
+        return Err(SszError::ListTooLong { max, got: count });
+    }
+    Ok(bytes.chunks(size).collect())
+}
+
+fn encode_points<'a>(points: impl Iterator<Item = &'a bandersnatch::EdwardsProjective>) -> Vec<u8> {
+    points.flat_map(|point| point_to_bytes(point)).collect()
+}
+
+fn decode_points(
+    bytes: &[u8],
+    max: usize,
+) -> Result<Vec<bandersnatch::EdwardsProjective>, SszError> {
+    decode_fixed_list(bytes, POINT_SIZE, max)?
+        .into_iter()
+        .map(|point| point_from_bytes(point).ok_or(SszError::InvalidPoint))
+        .collect()
+}
+
+// Optional[T] is encoded as Union[None, T]
+fn encode_optional_value(value: &Option<[u8; 32]>) -> Vec<u8> {
+    match value {
+        None => vec![0],
+        Some(value) => {
+            let mut bytes = vec![1];
+            bytes.extend_from_slice(value);
+            bytes
+        }
+    }
+}
+
+fn decode_optional_value(bytes: &[u8]) -> Result<Option<[u8; 32]>, SszError> {
+    match bytes.first() {
+        Some(0) if bytes.len() == 1 => Ok(None),
+        Some(0) => Err(SszError::InvalidLength {
+            expected: 1,
+            got: bytes.len(),
+        }),
+        Some(1) if bytes.len() == 33 => Ok(Some(bytes[1..].try_into().unwrap())),
+        Some(1) => Err(SszError::InvalidLength {
+            expected: 33,
+            got: bytes.len(),
+        }),
+        Some(selector) => Err(SszError::InvalidUnionSelector(*selector)),
+        None => Err(SszError::InvalidLength {
+            expected: 1,
+            got: 0,
+        }),
+    }
+}
+
+pub fn encode_ipa_proof(proof: &IPAProof) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(IPA_PROOF_SIZE);
+    bytes.extend(encode_points(proof.L_vec.iter()));
+    bytes.extend(encode_points(proof.R_vec.iter()));
+    bytes.extend_from_slice(&scalar_to_bytes(&proof.a));
+    bytes
+}
+
+pub fn decode_ipa_proof(bytes: &[u8]) -> Result<IPAProof, SszError> {
+    if bytes.len() != IPA_PROOF_SIZE {
+        return Err(SszError::InvalidLength {
+            expected: IPA_PROOF_SIZE,
+            got: bytes.len(),
+        });
+    }
+    let vector_size = IPA_PROOF_DEPTH * POINT_SIZE;
+    let cl = decode_points(&bytes[..vector_size], IPA_PROOF_DEPTH)?;
+    let cr = decode_points(&bytes[vector_size..2 * vector_size], IPA_PROOF_DEPTH)?;
+    let final_evaluation =
+        scalar_from_bytes(&bytes[2 * vector_size..]).ok_or(SszError::InvalidScalar)?;
+    Ok(IPAProof {
+        L_vec: cl,
+        R_vec: cr,
+        a: final_evaluation,
+    })
+}
+
+pub fn encode_verkle_proof(proof: &VerkleProof) -> Vec<u8> {
+    let hint = &proof.verification_hint;
+
+    let other_stems: Vec<u8> = hint
+        .diff_stem_no_proof
+        .iter()
+        .flat_map(|stem| stem.to_vec())
+        .collect();
+
+    let depth_extension_present: Vec<u8> = hint
+        .depths
+        .iter()
+        .zip(hint.extension_present.iter())
+        .map(|(depth, ext)| depth_extension_to_byte(*depth, ext))
+        .collect();
+
+    encode_container(vec![
+        Field::Variable(other_stems),
+        Field::Variable(depth_extension_present),
+        Field::Variable(encode_points(proof.comms_sorted.iter())),
+        Field::Fixed(point_to_bytes(&proof.proof.g_x_comm).to_vec()),
+        Field::Fixed(encode_ipa_proof(&proof.proof.open_proof)),
+    ])
+}
+
+pub fn decode_verkle_proof(bytes: &[u8]) -> Result<VerkleProof, SszError> {
+    let fields = decode_container(
+        bytes,
+        &[None, None, None, Some(POINT_SIZE), Some(IPA_PROOF_SIZE)],
+    )?;
+
+    let diff_stem_no_proof: BTreeSet<[u8; 31]> =
+        decode_fixed_list(fields[0], STEM_SIZE, MAX_STEMS)?
+            .into_iter()
+            .map(|stem| stem.try_into().unwrap())
+            .collect();
+
+    if fields[1].len() > MAX_STEMS {
+        return Err(SszError::ListTooLong {
+            max: MAX_STEMS,
+            got: fields[1].len(),
+        });
+    }
+    let mut depths = Vec::with_capacity(fields[1].len());
+    let mut extension_present = Vec::with_capacity(fields[1].len());
+    for byte in fields[1] {
+        let (depth, ext) =
+            depth_extension_from_byte(*byte).ok_or(SszError::InvalidExtensionStatus(*byte))?;
+        depths.push(depth);
+        extension_present.push(ext);
+    }
+
+    let comms_sorted = decode_points(fields[2], MAX_STEMS * MAX_COMMITMENTS_PER_STEM)?;
+    let g_x_comm = point_from_bytes(fields[3]).ok_or(SszError::InvalidPoint)?;
+    let open_proof = decode_ipa_proof(fields[4])?;
+
+    Ok(VerkleProof {
+        verification_hint: VerificationHint {
+            depths,
+            extension_present,
+            diff_stem_no_proof,
+        },
+        comms_sorted,
+        proof: MultiPointProof {
+            open_proof,
+            g_x_comm,
+        },
+    })
+}
+
+pub fn encode_suffix_state_diff(diff: &SuffixStateDiff) -> Vec<u8> {
+    encode_container(vec![
+        Field::Fixed(vec![diff.suffix]),
+        Field::Variable(encode_optional_value(&diff.current_value)),
+        Field::Variable(encode_optional_value(&diff.new_value)),
+    ])
+}
+
+pub fn decode_suffix_state_diff(bytes: &[u8]) -> Result<SuffixStateDiff, SszError> {
+    let fields = decode_container(bytes, &[Some(1), None, None])?;
+    Ok(SuffixStateDiff {
+        suffix: fields[0][0],
+        current_value: decode_optional_value(fields[1])?,
+        new_value: decode_optional_value(fields[2])?,
+    })
+}
+
+pub fn encode_stem_state_diff(diff: &StemStateDiff) -> Vec<u8> {
+    let suffix_diffs = diff
+        .suffix_diffs
+        .iter()
+        .map(encode_suffix_state_diff)
+        .collect();
+    encode_container(vec![
+        Field::Fixed(diff.stem.to_vec()),
+        Field::Variable(encode_variable_list(suffix_diffs)),
+    ])
+}
+
+pub fn decode_stem_state_diff(bytes: &[u8]) -> Result<StemStateDiff, SszError> {
+    let fields = decode_container(bytes, &[Some(STEM_SIZE), None])?;
+    let suffix_diffs = decode_variable_list(fields[1], VERKLE_WIDTH)?
+        .into_iter()
+        .map(decode_suffix_state_diff)
+        .collect::<Result<_, _>>()?;
+    Ok(StemStateDiff {
+        stem: fields[0].try_into().unwrap(),
+        suffix_diffs,
+    })
+}
+
+pub fn encode_state_diff(state_diff: &[StemStateDiff]) -> Vec<u8> {
+    encode_variable_list(state_diff.iter().map(encode_stem_state_diff).collect())
+}
+
+pub fn decode_state_diff(bytes: &[u8]) -> Result<Vec<StemStateDiff>, SszError> {
+    decode_variable_list(bytes, MAX_STEMS)?
+        .into_iter()
+        .map(decode_stem_state_diff)
+        .collect()
+}
+
+pub fn encode_execution_witness(witness: &ExecutionWitness) -> Vec<u8> {
+    encode_container(vec![
+        Field::Variable(encode_state_diff(&witness.state_diff)),
+        Field::Variable(encode_verkle_proof(&witness.verkle_proof)),
+    ])
+}
+
+pub fn decode_execution_witness(bytes: &[u8]) -> Result<ExecutionWitness, SszError> {
+    let fields = decode_container(bytes, &[None, None])?;
+    Ok(ExecutionWitness {
+        state_diff: decode_state_diff(fields[0])?,
+        verkle_proof: decode_verkle_proof(fields[1])?,
+    })
+}
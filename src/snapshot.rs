@@ -0,0 +1,301 @@
+use crate::bulk_load::{BulkLoadError, BulkLoadTarget, BulkLoader};
+use crate::database::verkle_db::VerkleTreeDb;
+use crate::inspect::{stem_meta_to_bytes, STEM_META_SIZE};
+use crate::iterator::LeafIterator;
+use crate::proof::{point_from_bytes, point_to_bytes};
+use bandersnatch::EdwardsProjective;
+use sha2::{Digest, Sha256};
+use std::convert::TryInto;
+use std::io::{Read, Write};
+use verkle_trie::committer::Committer;
+use verkle_trie::database::ReadOnlyHigherDb;
+use verkle_trie::{Trie, TrieTrait};
+
+// Portable dump of the whole state, used to back up a database or move it between machines
+//
+// header:  magic (8) || version (u16) || flags (u8) || root commitment (32)
+// stem:    STEM_RECORD || stem (31) || leaf count (u16) || (suffix (1) || value (32))*
+//          || stem meta (192, only with FLAG_COMMITMENTS)
+// trailer: END_RECORD || stem count (u64) || leaf count (u64) || sha256 of everything before
+//
+// Integers are little endian, stems are written in increasing order and the stem meta uses
+// the layout of `inspect::stem_meta_to_bytes`
+
+pub const SNAPSHOT_MAGIC: [u8; 8] = *b"VKLSNAP\0";
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+pub const FLAG_COMMITMENTS: u8 = 1;
+
+const STEM_RECORD: u8 = 1;
+const END_RECORD: u8 = 0;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(std::io::Error),
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    InvalidRecord(u8),
+    InvalidRoot,
+    UnsortedStems,
+    UnsortedLeaves,
+    EmptyStem([u8; 31]),
+    StemMetaMismatch([u8; 31]),
+    CountMismatch,
+    ChecksumMismatch,
+    RootMismatch,
+    NonEmptyTarget,
+    Storage(String),
+    // Exported stem whose commitment is not in storage, e.g. a partially flushed database
+    MissingStemMeta([u8; 31]),
+}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(err: std::io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
+
+impl From<BulkLoadError> for SnapshotError {
+    fn from(err: BulkLoadError) -> Self {
+        match err {
+            BulkLoadError::UnsortedKeys { .. } => SnapshotError::UnsortedLeaves,
+            BulkLoadError::RootMismatch => SnapshotError::RootMismatch,
+            BulkLoadError::NonEmptyTarget => SnapshotError::NonEmptyTarget,
+            BulkLoadError::EmptyStem(stem) => SnapshotError::EmptyStem(stem),
+            BulkLoadError::WriteFailed(err) => SnapshotError::Storage(err),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotSummary {
+    pub root: [u8; 32],
+    pub stems: u64,
+    pub leaves: u64,
+}
+
+// Writer and reader wrappers that hash every byte going through them
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn write_all(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.hasher.update(bytes);
+        self.inner.write_all(bytes)
+    }
+}
+
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn read_exact(&mut self, bytes: &mut [u8]) -> std::io::Result<()> {
+        self.inner.read_exact(bytes)?;
+        self.hasher.update(&bytes[..]);
+        Ok(())
+    }
+
+    fn read_u8(&mut self) -> std::io::Result<u8> {
+        let mut byte = [0u8; 1];
+        self.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    fn read_u16(&mut self) -> std::io::Result<u16> {
+        let mut bytes = [0u8; 2];
+        self.read_exact(&mut bytes)?;
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    fn read_u64(&mut self) -> std::io::Result<u64> {
+        let mut bytes = [0u8; 8];
+        self.read_exact(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+fn write_stem<W: Write, D: ReadOnlyHigherDb>(
+    writer: &mut HashingWriter<W>,
+    db: &D,
+    stem: [u8; 31],
+    leaves: &[(u8, [u8; 32])],
+    include_commitments: bool,
+) -> Result<(), SnapshotError> {
+    writer.write_all(&[STEM_RECORD])?;
+    writer.write_all(&stem)?;
+    writer.write_all(&(leaves.len() as u16).to_le_bytes())?;
+    for (suffix, value) in leaves {
+        writer.write_all(&[*suffix])?;
+        writer.write_all(value)?;
+    }
+    if include_commitments {
+        let meta = db
+            .get_stem_meta(stem)
+            .ok_or(SnapshotError::MissingStemMeta(stem))?;
+        writer.write_all(&stem_meta_to_bytes(&meta))?;
+    }
+    Ok(())
+}
+
+// Stream every leaf of the trie into `writer`. With `include_commitments` the commitments of
+// every stem are exported too, so that an import can check them stem by stem
+pub fn export_state<S, C, W: Write>(
+    trie: &mut Trie<VerkleTreeDb<S>, C>,
+    include_commitments: bool,
+    writer: W,
+) -> Result<SnapshotSummary, SnapshotError>
+where
+    VerkleTreeDb<S>: ReadOnlyHigherDb,
+    Trie<VerkleTreeDb<S>, C>: TrieTrait,
+{
+    let root = point_to_bytes(&trie.root_commitment());
+    let mut writer = HashingWriter {
+        inner: writer,
+        hasher: Sha256::new(),
+    };
+
+    let flags = if include_commitments {
+        FLAG_COMMITMENTS
+    } else {
+        0
+    };
+    writer.write_all(&SNAPSHOT_MAGIC)?;
+    writer.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+    writer.write_all(&[flags])?;
+    writer.write_all(&root)?;
+
+    let db = &trie.storage;
+    let mut iter = LeafIterator::new([0u8; 32]);
+    let mut stems = 0u64;
+    let mut leaves = 0u64;
+    let mut current: Option<([u8; 31], Vec<(u8, [u8; 32])>)> = None;
+    while let Some((key, value)) = iter.next(db) {
+        let stem: [u8; 31] = key[..31].try_into().unwrap();
+        leaves += 1;
+        match current.as_mut() {
+            Some((current_stem, stem_leaves)) if *current_stem == stem => {
+                stem_leaves.push((key[31], value));
+                continue;
+            }
+            _ => {}
+        }
+        if let Some((previous, stem_leaves)) = current.take() {
+            write_stem(&mut writer, db, previous, &stem_leaves, include_commitments)?;
+            stems += 1;
+        }
+        current = Some((stem, vec![(key[31], value)]));
+    }
+    if let Some((previous, stem_leaves)) = current.take() {
+        write_stem(&mut writer, db, previous, &stem_leaves, include_commitments)?;
+        stems += 1;
+    }
+
+    writer.write_all(&[END_RECORD])?;
+    writer.write_all(&stems.to_le_bytes())?;
+    writer.write_all(&leaves.to_le_bytes())?;
+    let checksum: [u8; 32] = writer.hasher.finalize().into();
+    writer.inner.write_all(&checksum)?;
+    writer.inner.flush()?;
+
+    Ok(SnapshotSummary {
+        root,
+        stems,
+        leaves,
+    })
+}
+
+// Rebuild the state from `reader` into `storage`, which must not hold a trie yet
+// The trie is built with a `BulkLoader`, its root is only written once the checksum and the
+// rebuilt root both match. Like any bulk load, an import larger than one batch that fails
+// leaves nodes no root points to, and the storage should be discarded
+pub fn import_state<S: BulkLoadTarget, C: Committer, R: Read>(
+    storage: &'static mut S,
+    committer: &'static C,
+    reader: R,
+) -> Result<SnapshotSummary, SnapshotError> {
+    let mut loader = BulkLoader::new(storage, committer)?;
+    let mut reader = HashingReader {
+        inner: reader,
+        hasher: Sha256::new(),
+    };
+
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if magic != SNAPSHOT_MAGIC {
+        return Err(SnapshotError::InvalidMagic);
+    }
+    let version = reader.read_u16()?;
+    if version != SNAPSHOT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+    let flags = reader.read_u8()?;
+    let include_commitments = flags & FLAG_COMMITMENTS != 0;
+    let mut root = [0u8; 32];
+    reader.read_exact(&mut root)?;
+    let expected_root: EdwardsProjective =
+        point_from_bytes(&root).ok_or(SnapshotError::InvalidRoot)?;
+
+    let mut stems = 0u64;
+    let mut leaves = 0u64;
+    let mut previous_stem: Option<[u8; 31]> = None;
+    loop {
+        match reader.read_u8()? {
+            STEM_RECORD => {}
+            END_RECORD => break,
+            record => return Err(SnapshotError::InvalidRecord(record)),
+        }
+
+        let mut stem = [0u8; 31];
+        reader.read_exact(&mut stem)?;
+        if previous_stem.map_or(false, |previous| previous >= stem) {
+            return Err(SnapshotError::UnsortedStems);
+        }
+        previous_stem = Some(stem);
+
+        let count = reader.read_u16()? as usize;
+        if count == 0 {
+            return Err(SnapshotError::EmptyStem(stem));
+        }
+        let mut stem_leaves = Vec::with_capacity(count);
+        for _ in 0..count {
+            let suffix = reader.read_u8()?;
+            let mut value = [0u8; 32];
+            reader.read_exact(&mut value)?;
+            stem_leaves.push((suffix, value));
+        }
+        let rebuilt = loader.add_stem(stem, &stem_leaves)?;
+
+        if include_commitments {
+            let mut meta = [0u8; STEM_META_SIZE];
+            reader.read_exact(&mut meta)?;
+            if stem_meta_to_bytes(&rebuilt)[..] != meta[..] {
+                return Err(SnapshotError::StemMetaMismatch(stem));
+            }
+        }
+
+        stems += 1;
+        leaves += count as u64;
+    }
+
+    if reader.read_u64()? != stems || reader.read_u64()? != leaves {
+        return Err(SnapshotError::CountMismatch);
+    }
+    let checksum: [u8; 32] = reader.hasher.finalize().into();
+    let mut expected_checksum = [0u8; 32];
+    reader.inner.read_exact(&mut expected_checksum)?;
+    if checksum != expected_checksum {
+        return Err(SnapshotError::ChecksumMismatch);
+    }
+
+    loader.finish(Some(expected_root))?;
+
+    Ok(SnapshotSummary {
+        root,
+        stems,
+        leaves,
+    })
+}
@@ -1,3 +1,3 @@
-mod precompute;
+pub(crate) mod precompute;
 pub mod traits;
 pub mod trie;
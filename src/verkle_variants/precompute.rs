@@ -1,5 +1,6 @@
 use ark_ec::ProjectiveCurve;
 use bandersnatch::{EdwardsProjective, Fr};
+use once_cell::sync::Lazy;
 use verkle_trie::committer::precompute::{LagrangeTablePoints, PrecomputeLagrange};
 use verkle_trie::committer::Committer;
 use verkle_trie::constants::CRS;
@@ -26,3 +27,8 @@ impl Default for LagrangeCommitter {
         LagrangeCommitter { inner: committer }
     }
 }
+
+// Committer for the code that computes commitments outside of a trie, such as the parallel
+// inserts, bulk loads and integrity checks. Building the tables is expensive, so they are built once and shared
+pub(crate) static SHARED_LAGRANGE_COMMITTER: Lazy<LagrangeCommitter> =
+    Lazy::new(LagrangeCommitter::default);
@@ -1,19 +1,22 @@
 use std::mem::transmute;
 use std::ptr;
 use verkle_trie::from_to_bytes::ToBytes;
-use verkle_trie::proof::VerkleProof;
 use verkle_trie::{Config, Trie, TrieTrait};
 
 use crate::database::verkle_db::VerkleTreeDb;
+use crate::proof::{check_proof, json, point_to_bytes, read_proof, ssz, ExecutionWitness};
 use crate::{
     get_array_from_slice_argument, get_vector_from_slice_argument, proof_ptr_to_proof_vec,
-    CommitScheme, Database, Proof, VerkleTrie,
+    proof_vec_to_proof_ptr, CommitScheme, Database, Proof, VerkleTrie,
 };
 
 pub trait FFI: TrieTrait {
     type DbObject;
 
-    fn verkle_trie_new(path: &str) -> Self;
+    // None if this kind of trie cannot be created on its own
+    fn verkle_trie_new(path: &str) -> Option<Self>
+    where
+        Self: Sized;
 
     fn create_from_db(db: &'static mut Self::DbObject) -> Self;
 
@@ -41,17 +44,17 @@ pub trait FFI: TrieTrait {
         hash_ptr
     }
 
+    fn get_root_commitment(&mut self) -> *mut Proof {
+        let commitment = self.root_commitment();
+        proof_vec_to_proof_ptr(point_to_bytes(&commitment).to_vec())
+    }
+
     fn get_verkle_proof(&mut self, key: *const u8) -> *mut Proof {
         let _key = get_array_from_slice_argument(key);
         let _proof = self.create_verkle_proof(vec![_key].into_iter());
         let mut proof_bytes = Vec::new();
         _proof.write(&mut proof_bytes).expect("Could write proof");
-        let (_ptr, _len, _) = proof_bytes.into_raw_parts();
-        let proof = Proof {
-            ptr: _ptr,
-            len: _len,
-        };
-        unsafe { transmute(Box::new(proof)) }
+        proof_vec_to_proof_ptr(proof_bytes)
     }
 
     fn verify_verkle_proof(
@@ -62,15 +65,15 @@ pub trait FFI: TrieTrait {
         value: *const u8,
     ) -> u8 {
         let proof_bytes = proof_ptr_to_proof_vec(ptr, proof_len);
-        let proof = VerkleProof::read(&proof_bytes[..]).unwrap();
+        let proof = match read_proof(&proof_bytes) {
+            Some(proof) => proof,
+            None => return 0,
+        };
         let _key = get_array_from_slice_argument(key);
         let _value = get_array_from_slice_argument(value);
         let root = self.root_commitment();
         let val_iter = vec![Some(_value)];
-        let vpp = proof.clone();
-        let (res, _) = vpp.check(vec![_key], val_iter, root);
-        let result = res as u8;
-        result
+        check_proof(proof, vec![_key], val_iter, root) as u8
     }
 
     fn get_verkle_proof_multiple(&mut self, keys: *const [u8; 32], len: usize) -> *mut Proof {
@@ -78,12 +81,7 @@ pub trait FFI: TrieTrait {
         let _proof = self.create_verkle_proof(_keys.into_iter());
         let mut proof_bytes = Vec::new();
         _proof.write(&mut proof_bytes).expect("Could write proof");
-        let (_ptr, _len, _) = proof_bytes.into_raw_parts();
-        let proof = Proof {
-            ptr: _ptr,
-            len: _len,
-        };
-        unsafe { transmute(Box::new(proof)) }
+        proof_vec_to_proof_ptr(proof_bytes)
     }
 
     fn verify_verkle_proof_multiple(
@@ -95,15 +93,15 @@ pub trait FFI: TrieTrait {
         len: usize,
     ) -> u8 {
         let proof_bytes = proof_ptr_to_proof_vec(ptr, proof_len);
-        let proof = VerkleProof::read(&proof_bytes[..]).expect("Could write proof");
+        let proof = match read_proof(&proof_bytes) {
+            Some(proof) => proof,
+            None => return 0,
+        };
         let _keys = get_vector_from_slice_argument(keys, len);
         let _vals = get_vector_from_slice_argument(vals, len);
         let root = self.root_commitment();
         let values: Vec<_> = _vals.iter().map(|val| Some(*val)).collect();
-        let vpp = proof.clone();
-        let (res, _) = vpp.check(_keys, values, root);
-        let result = res as u8;
-        result
+        check_proof(proof, _keys, values, root) as u8
     }
 
     fn verkle_trie_insert_multiple(
@@ -114,10 +112,80 @@ pub trait FFI: TrieTrait {
     ) {
         let _keys = get_vector_from_slice_argument(keys, len);
         let _vals = get_vector_from_slice_argument(vals, len);
-        let mut itr = vec![(_keys[0], _vals[0])];
-        for i in 1..=_keys.len() - 1 {
-            itr.push((_keys[i], _vals[i]));
-        }
-        self.insert(itr.into_iter());
+        self.insert(_keys.into_iter().zip(_vals.into_iter()));
+    }
+
+    fn get_verkle_proof_ssz(&mut self, keys: *const [u8; 32], len: usize) -> *mut Proof {
+        let _keys = get_vector_from_slice_argument(keys, len);
+        let _proof = self.create_verkle_proof(_keys.into_iter());
+        proof_vec_to_proof_ptr(ssz::encode_verkle_proof(&_proof))
+    }
+
+    fn verify_verkle_proof_ssz(
+        &mut self,
+        ptr: *const u8,
+        proof_len: usize,
+        keys: *const [u8; 32],
+        vals: *const [u8; 32],
+        len: usize,
+    ) -> u8 {
+        let proof_bytes = proof_ptr_to_proof_vec(ptr, proof_len);
+        let proof = match ssz::decode_verkle_proof(&proof_bytes) {
+            Ok(proof) => proof,
+            Err(_) => return 0,
+        };
+        let _keys = get_vector_from_slice_argument(keys, len);
+        let _vals = get_vector_from_slice_argument(vals, len);
+        let root = self.root_commitment();
+        let values: Vec<_> = _vals.iter().map(|val| Some(*val)).collect();
+        check_proof(proof, _keys, values, root) as u8
+    }
+
+    fn get_execution_witness_ssz(&mut self, keys: *const [u8; 32], len: usize) -> *mut Proof {
+        let _keys = get_vector_from_slice_argument(keys, len);
+        let values: Vec<_> = _keys.iter().map(|key| self.get(*key)).collect();
+        let _proof = self.create_verkle_proof(_keys.clone().into_iter());
+        let witness = ExecutionWitness::new(&_keys, &values, _proof);
+        proof_vec_to_proof_ptr(ssz::encode_execution_witness(&witness))
+    }
+
+    fn verify_execution_witness_ssz(&mut self, ptr: *const u8, witness_len: usize) -> u8 {
+        let witness_bytes = proof_ptr_to_proof_vec(ptr, witness_len);
+        let witness = match ssz::decode_execution_witness(&witness_bytes) {
+            Ok(witness) => witness,
+            Err(_) => return 0,
+        };
+        let (keys, values) = witness.keys_values();
+        let root = self.root_commitment();
+        check_proof(witness.verkle_proof, keys, values, root) as u8
+    }
+
+    fn get_verkle_proof_json(&mut self, keys: *const [u8; 32], len: usize) -> *mut Proof {
+        let _keys = get_vector_from_slice_argument(keys, len);
+        let _proof = self.create_verkle_proof(_keys.into_iter());
+        proof_vec_to_proof_ptr(json::encode_verkle_proof(&_proof).into_bytes())
+    }
+
+    fn verify_verkle_proof_json(
+        &mut self,
+        ptr: *const u8,
+        proof_len: usize,
+        keys: *const [u8; 32],
+        vals: *const [u8; 32],
+        len: usize,
+    ) -> u8 {
+        let proof_bytes = proof_ptr_to_proof_vec(ptr, proof_len);
+        let proof = match std::str::from_utf8(&proof_bytes)
+            .ok()
+            .and_then(|proof_json| json::decode_verkle_proof(proof_json).ok())
+        {
+            Some(proof) => proof,
+            None => return 0,
+        };
+        let _keys = get_vector_from_slice_argument(keys, len);
+        let _vals = get_vector_from_slice_argument(vals, len);
+        let root = self.root_commitment();
+        let values: Vec<_> = _vals.iter().map(|val| Some(*val)).collect();
+        check_proof(proof, _keys, values, root) as u8
     }
 }
@@ -1,15 +1,20 @@
 use crate::verkle_variants::traits::FFI;
-use verkle_db::{BareMetalDiskDb, BareMetalKVDb, BatchDB, BatchWriter, RocksDb};
+use verkle_db::{BareMetalKVDb, BatchDB, BatchWriter};
 use verkle_trie::{
     committer::precompute::PrecomputeLagrange, committer::test::TestCommitter, config::Config,
     constants::CRS, database::VerkleDb, Key, Trie, TrieTrait, Value,
 };
 
+#[cfg(feature = "rocks_db")]
 use crate::database::disk_db::{VerkleReadOnlyRocksDB, VerkleRocksDB};
+use crate::database::external_db::VerkleExternalDB;
 use crate::database::generics::GenericMemoryDb;
 use crate::database::memory_db::{VerkleMemoryDB, VerkleReadOnlyMemoryDB};
+use crate::database::redb_db::{VerkleReadOnlyRedbDB, VerkleRedbDB};
 use crate::database::verkle_db::VerkleTreeDb;
 use crate::verkle_variants::precompute::LagrangeCommitter;
+#[cfg(feature = "rocks_db")]
+use crate::RocksDbOptions;
 use crate::{Database, Proof};
 use ark_ec::ProjectiveCurve;
 use bandersnatch::{EdwardsProjective, Fr};
@@ -17,16 +22,20 @@ use verkle_trie::database::generic::GenericBatchDB;
 use verkle_trie::database::memory_db::MemoryDb;
 use verkle_trie::proof::VerkleProof;
 
+#[cfg(feature = "rocks_db")]
 pub type VerkleTrieRocksDBTest = Trie<VerkleTreeDb<VerkleRocksDB>, TestCommitter>;
+#[cfg(feature = "rocks_db")]
 impl FFI for VerkleTrieRocksDBTest {
     type DbObject = VerkleRocksDB;
 
-    fn verkle_trie_new(path: &str) -> Self {
-        let db = VerkleTreeDb::from_path(path);
+    fn verkle_trie_new(path: &str) -> Option<Self> {
+        let storage =
+            VerkleRocksDB::create_db_with_options(path, &RocksDbOptions::default()).ok()?;
+        let db = VerkleTreeDb::from_db(Box::leak(Box::new(storage)));
         let committer = TestCommitter;
         let config = Config { db, committer };
         let mut _trie = Trie::new(config);
-        _trie
+        Some(_trie)
     }
 
     fn create_from_db(db: &'static mut VerkleRocksDB) -> Self {
@@ -38,12 +47,15 @@ impl FFI for VerkleTrieRocksDBTest {
     }
 }
 
+#[cfg(feature = "rocks_db")]
 pub type VerkleTrieReadOnlyRocksDBTest = Trie<VerkleTreeDb<VerkleReadOnlyRocksDB>, TestCommitter>;
+#[cfg(feature = "rocks_db")]
 impl FFI for VerkleTrieReadOnlyRocksDBTest {
     type DbObject = VerkleReadOnlyRocksDB;
 
-    fn verkle_trie_new(path: &str) -> Self {
-        todo!()
+    fn verkle_trie_new(_path: &str) -> Option<Self> {
+        // Read only tries are created from an overlay, see `create_read_only_verkle_db`
+        None
     }
 
     fn create_from_db(db: &'static mut VerkleReadOnlyRocksDB) -> Self {
@@ -55,16 +67,20 @@ impl FFI for VerkleTrieReadOnlyRocksDBTest {
     }
 }
 
+#[cfg(feature = "rocks_db")]
 pub type VerkleTrieRocksDBLagrange = Trie<VerkleTreeDb<VerkleRocksDB>, LagrangeCommitter>;
+#[cfg(feature = "rocks_db")]
 impl FFI for VerkleTrieRocksDBLagrange {
     type DbObject = VerkleRocksDB;
 
-    fn verkle_trie_new(path: &str) -> Self {
-        let db = VerkleTreeDb::from_path(path);
+    fn verkle_trie_new(path: &str) -> Option<Self> {
+        let storage =
+            VerkleRocksDB::create_db_with_options(path, &RocksDbOptions::default()).ok()?;
+        let db = VerkleTreeDb::from_db(Box::leak(Box::new(storage)));
         let committer = LagrangeCommitter::default();
         let config = Config { db, committer };
         let mut _trie = Trie::new(config);
-        _trie
+        Some(_trie)
     }
 
     fn create_from_db(db: &'static mut VerkleRocksDB) -> Self {
@@ -76,13 +92,16 @@ impl FFI for VerkleTrieRocksDBLagrange {
     }
 }
 
+#[cfg(feature = "rocks_db")]
 pub type VerkleTrieReadOnlyRocksDBLagrange =
     Trie<VerkleTreeDb<VerkleReadOnlyRocksDB>, LagrangeCommitter>;
+#[cfg(feature = "rocks_db")]
 impl FFI for VerkleTrieReadOnlyRocksDBLagrange {
     type DbObject = VerkleReadOnlyRocksDB;
 
-    fn verkle_trie_new(path: &str) -> Self {
-        todo!()
+    fn verkle_trie_new(_path: &str) -> Option<Self> {
+        // Read only tries are created from an overlay, see `create_read_only_verkle_db`
+        None
     }
 
     fn create_from_db(db: &'static mut VerkleReadOnlyRocksDB) -> Self {
@@ -98,12 +117,12 @@ pub type VerkleTrieMemoryTest = Trie<VerkleTreeDb<VerkleMemoryDB>, TestCommitter
 impl FFI for VerkleTrieMemoryTest {
     type DbObject = VerkleMemoryDB;
 
-    fn verkle_trie_new(_path: &str) -> Self {
+    fn verkle_trie_new(_path: &str) -> Option<Self> {
         let db = VerkleTreeDb::new();
         let committer = TestCommitter;
         let config = Config { db, committer };
         let mut _trie = Trie::new(config);
-        _trie
+        Some(_trie)
     }
 
     fn create_from_db(db: &'static mut VerkleMemoryDB) -> Self {
@@ -119,8 +138,9 @@ pub type VerkleTrieReadOnlyMemoryTest = Trie<VerkleTreeDb<VerkleReadOnlyMemoryDB
 impl FFI for VerkleTrieReadOnlyMemoryTest {
     type DbObject = VerkleReadOnlyMemoryDB;
 
-    fn verkle_trie_new(_path: &str) -> Self {
-        todo!()
+    fn verkle_trie_new(_path: &str) -> Option<Self> {
+        // Read only tries are created from an overlay, see `create_read_only_verkle_db`
+        None
     }
 
     fn create_from_db(db: &'static mut VerkleReadOnlyMemoryDB) -> Self {
@@ -136,12 +156,12 @@ pub type VerkleTrieMemoryLagrange = Trie<VerkleTreeDb<VerkleMemoryDB>, LagrangeC
 impl FFI for VerkleTrieMemoryLagrange {
     type DbObject = VerkleMemoryDB;
 
-    fn verkle_trie_new(_path: &str) -> Self {
+    fn verkle_trie_new(_path: &str) -> Option<Self> {
         let db = VerkleTreeDb::new();
         let committer = LagrangeCommitter::default();
         let config = Config { db, committer };
         let mut _trie = Trie::new(config);
-        _trie
+        Some(_trie)
     }
 
     fn create_from_db(db: &'static mut VerkleMemoryDB) -> Self {
@@ -158,8 +178,9 @@ pub type VerkleTrieReadOnlyMemoryLagrange =
 impl FFI for VerkleTrieReadOnlyMemoryLagrange {
     type DbObject = VerkleReadOnlyMemoryDB;
 
-    fn verkle_trie_new(_path: &str) -> Self {
-        todo!()
+    fn verkle_trie_new(_path: &str) -> Option<Self> {
+        // Read only tries are created from an overlay, see `create_read_only_verkle_db`
+        None
     }
 
     fn create_from_db(db: &'static mut VerkleReadOnlyMemoryDB) -> Self {
@@ -170,3 +191,120 @@ impl FFI for VerkleTrieReadOnlyMemoryLagrange {
         _trie
     }
 }
+
+pub type VerkleTrieRedbTest = Trie<VerkleTreeDb<VerkleRedbDB>, TestCommitter>;
+impl FFI for VerkleTrieRedbTest {
+    type DbObject = VerkleRedbDB;
+
+    fn verkle_trie_new(path: &str) -> Option<Self> {
+        let storage = VerkleRedbDB::open_db(path).ok()?;
+        let db = VerkleTreeDb::from_db(Box::leak(Box::new(storage)));
+        let committer = TestCommitter;
+        let config = Config { db, committer };
+        let mut _trie = Trie::new(config);
+        Some(_trie)
+    }
+
+    fn create_from_db(db: &'static mut VerkleRedbDB) -> Self {
+        let _db = VerkleTreeDb::from_db(db);
+        let committer = TestCommitter;
+        let config = Config { db: _db, committer };
+        let mut _trie = Trie::new(config);
+        _trie
+    }
+}
+
+pub type VerkleTrieReadOnlyRedbTest = Trie<VerkleTreeDb<VerkleReadOnlyRedbDB>, TestCommitter>;
+impl FFI for VerkleTrieReadOnlyRedbTest {
+    type DbObject = VerkleReadOnlyRedbDB;
+
+    fn verkle_trie_new(_path: &str) -> Option<Self> {
+        // Read only tries are created from an overlay, see `create_read_only_verkle_db`
+        None
+    }
+
+    fn create_from_db(db: &'static mut VerkleReadOnlyRedbDB) -> Self {
+        let _db = VerkleTreeDb::from_db(db);
+        let committer = TestCommitter;
+        let config = Config { db: _db, committer };
+        let mut _trie = Trie::new(config);
+        _trie
+    }
+}
+
+pub type VerkleTrieRedbLagrange = Trie<VerkleTreeDb<VerkleRedbDB>, LagrangeCommitter>;
+impl FFI for VerkleTrieRedbLagrange {
+    type DbObject = VerkleRedbDB;
+
+    fn verkle_trie_new(path: &str) -> Option<Self> {
+        let storage = VerkleRedbDB::open_db(path).ok()?;
+        let db = VerkleTreeDb::from_db(Box::leak(Box::new(storage)));
+        let committer = LagrangeCommitter::default();
+        let config = Config { db, committer };
+        let mut _trie = Trie::new(config);
+        Some(_trie)
+    }
+
+    fn create_from_db(db: &'static mut VerkleRedbDB) -> Self {
+        let _db = VerkleTreeDb::from_db(db);
+        let committer = LagrangeCommitter::default();
+        let config = Config { db: _db, committer };
+        let mut _trie = Trie::new(config);
+        _trie
+    }
+}
+
+pub type VerkleTrieReadOnlyRedbLagrange =
+    Trie<VerkleTreeDb<VerkleReadOnlyRedbDB>, LagrangeCommitter>;
+impl FFI for VerkleTrieReadOnlyRedbLagrange {
+    type DbObject = VerkleReadOnlyRedbDB;
+
+    fn verkle_trie_new(_path: &str) -> Option<Self> {
+        // Read only tries are created from an overlay, see `create_read_only_verkle_db`
+        None
+    }
+
+    fn create_from_db(db: &'static mut VerkleReadOnlyRedbDB) -> Self {
+        let _db = VerkleTreeDb::from_db(db);
+        let committer = LagrangeCommitter::default();
+        let config = Config { db: _db, committer };
+        let mut _trie = Trie::new(config);
+        _trie
+    }
+}
+
+pub type VerkleTrieExternalTest = Trie<VerkleTreeDb<VerkleExternalDB>, TestCommitter>;
+impl FFI for VerkleTrieExternalTest {
+    type DbObject = VerkleExternalDB;
+
+    fn verkle_trie_new(_path: &str) -> Option<Self> {
+        // External tries are created from a database, see `create_external_verkle_db`
+        None
+    }
+
+    fn create_from_db(db: &'static mut VerkleExternalDB) -> Self {
+        let _db = VerkleTreeDb::from_db(db);
+        let committer = TestCommitter;
+        let config = Config { db: _db, committer };
+        let mut _trie = Trie::new(config);
+        _trie
+    }
+}
+
+pub type VerkleTrieExternalLagrange = Trie<VerkleTreeDb<VerkleExternalDB>, LagrangeCommitter>;
+impl FFI for VerkleTrieExternalLagrange {
+    type DbObject = VerkleExternalDB;
+
+    fn verkle_trie_new(_path: &str) -> Option<Self> {
+        // External tries are created from a database, see `create_external_verkle_db`
+        None
+    }
+
+    fn create_from_db(db: &'static mut VerkleExternalDB) -> Self {
+        let _db = VerkleTreeDb::from_db(db);
+        let committer = LagrangeCommitter::default();
+        let config = Config { db: _db, committer };
+        let mut _trie = Trie::new(config);
+        _trie
+    }
+}
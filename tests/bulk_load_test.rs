@@ -0,0 +1,161 @@
+use rust_verkle::*;
+
+#[cfg(test)]
+mod bulk_load_test_helper {
+    use rust_verkle::utils::{assert_value, get_boxed_value, str_to_cstr};
+    use rust_verkle::*;
+    use std::slice;
+    use tempfile::Builder;
+
+    fn leaves() -> Vec<([u8; 32], [u8; 32])> {
+        let mut leaves = Vec::new();
+        for i in 0..300u32 {
+            let bytes = i.to_le_bytes();
+            let mut key = [0u8; 32];
+            // Spread the stems over the first two bytes so that some of them share a branch
+            key[0] = bytes[0] % 7;
+            key[1] = bytes[0];
+            key[2] = bytes[1];
+            key[31] = (i % 5) as u8 * 60;
+            let mut value = [0u8; 32];
+            value[..4].copy_from_slice(&bytes);
+            value[31] = 1;
+            leaves.push((key, value));
+        }
+        leaves.sort();
+        leaves.dedup_by_key(|(key, _)| *key);
+        leaves
+    }
+
+    fn expected_root(commit_scheme: CommitScheme, leaves: &[([u8; 32], [u8; 32])]) -> Vec<u8> {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let trie = verkle_trie_new(DatabaseScheme::MemoryDb, commit_scheme, str_to_cstr(path));
+        let keys: Vec<_> = leaves.iter().map(|(key, _)| *key).collect();
+        let vals: Vec<_> = leaves.iter().map(|(_, val)| *val).collect();
+        verkle_trie_insert_multiple(trie, keys.as_ptr(), vals.as_ptr(), keys.len());
+        let commitment = unsafe { &*get_root_commitment(trie) };
+        unsafe { slice::from_raw_parts(commitment.ptr, commitment.len) }.to_vec()
+    }
+
+    fn copy_scheme(commit_scheme: &CommitScheme) -> CommitScheme {
+        match commit_scheme {
+            CommitScheme::TestCommitment => CommitScheme::TestCommitment,
+            CommitScheme::PrecomputeLagrange => CommitScheme::PrecomputeLagrange,
+        }
+    }
+
+    fn add_leaves(loader: *mut VerkleBulkLoader, leaves: &[([u8; 32], [u8; 32])]) -> u8 {
+        let keys: Vec<_> = leaves.iter().map(|(key, _)| *key).collect();
+        let vals: Vec<_> = leaves.iter().map(|(_, val)| *val).collect();
+        bulk_loader_add_leaves(loader, keys.as_ptr(), vals.as_ptr(), keys.len())
+    }
+
+    pub fn bulk_load_matches_insert(commit_scheme: CommitScheme) {
+        let all = leaves();
+        let root = expected_root(copy_scheme(&commit_scheme), &all);
+
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = create_verkle_db(DatabaseScheme::RocksDb, str_to_cstr(path));
+
+        let loader = bulk_loader_new(copy_scheme(&commit_scheme), db);
+        assert!(!loader.is_null());
+        for chunk in all.chunks(64) {
+            assert_eq!(add_leaves(loader, chunk), 1);
+        }
+        assert_eq!(bulk_loader_finish(loader, root.as_ptr(), root.len()), 1);
+
+        let trie = create_trie_from_db(commit_scheme, db);
+        for (key, value) in all.iter().step_by(17) {
+            let val = verkle_trie_get(trie, get_boxed_value(*key));
+            assert_value(val, *value);
+        }
+    }
+
+    pub fn bulk_load_rejects_wrong_root(commit_scheme: CommitScheme) {
+        let all = leaves();
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = create_verkle_db(DatabaseScheme::RocksDb, str_to_cstr(path));
+
+        // The root of the trie without its last leaf
+        let root = expected_root(copy_scheme(&commit_scheme), &all[..all.len() - 1]);
+
+        let loader = bulk_loader_new(copy_scheme(&commit_scheme), db);
+        assert_eq!(add_leaves(loader, &all), 1);
+        assert_eq!(bulk_loader_finish(loader, root.as_ptr(), root.len()), 0);
+
+        // Nothing was written, so the database can be loaded again
+        let trie = create_trie_from_db(copy_scheme(&commit_scheme), db);
+        assert!(verkle_trie_get(trie, get_boxed_value(all[0].0)).is_null());
+        assert_eq!(verkle_trie_free(trie), 1);
+        let root = expected_root(copy_scheme(&commit_scheme), &all);
+        let loader = bulk_loader_new(copy_scheme(&commit_scheme), db);
+        assert!(!loader.is_null());
+        assert_eq!(add_leaves(loader, &all), 1);
+        assert_eq!(bulk_loader_finish(loader, root.as_ptr(), root.len()), 1);
+
+        // A database that holds a trie is not loaded into
+        assert!(bulk_loader_new(commit_scheme, db).is_null());
+    }
+
+    pub fn bulk_load_rejects_unsorted_chunks(commit_scheme: CommitScheme) {
+        let all = leaves();
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = create_verkle_db(DatabaseScheme::RocksDb, str_to_cstr(path));
+
+        let loader = bulk_loader_new(copy_scheme(&commit_scheme), db);
+        assert_eq!(add_leaves(loader, &all[10..20]), 1);
+        // A chunk that starts before the end of the previous one
+        assert_eq!(add_leaves(loader, &all[15..30]), 0);
+        // A chunk that is unsorted in the middle is not added in part
+        let mut unsorted = all[20..30].to_vec();
+        unsorted.swap(4, 5);
+        assert_eq!(add_leaves(loader, &unsorted), 0);
+        assert_eq!(add_leaves(loader, &all[20..30]), 1);
+        let root = expected_root(copy_scheme(&commit_scheme), &all[10..30]);
+        assert_eq!(bulk_loader_finish(loader, root.as_ptr(), root.len()), 1);
+
+        let memory_db = create_verkle_db(DatabaseScheme::MemoryDb, str_to_cstr(path));
+        assert!(bulk_loader_new(commit_scheme, memory_db).is_null());
+    }
+}
+
+macro_rules! bulk_load_test {
+    (
+        $module_name: ident;   // Module Name
+        $commit_enum: ident; // Commit enum
+        $($function_name: ident),*  // list of functions to implement
+    ) => {
+        #[cfg(test)]
+        #[allow(non_snake_case)]
+        mod $module_name {
+            use super::*;
+
+            $(
+                #[test]
+                fn $function_name() {
+                    bulk_load_test_helper::$function_name(CommitScheme::$commit_enum);
+                }
+            )*
+        }
+    };
+}
+
+bulk_load_test![
+    RocksdbTest;
+    TestCommitment;
+    bulk_load_matches_insert,
+    bulk_load_rejects_wrong_root,
+    bulk_load_rejects_unsorted_chunks
+];
+
+bulk_load_test![
+    RocksdbPrelagrange;
+    PrecomputeLagrange;
+    bulk_load_matches_insert,
+    bulk_load_rejects_wrong_root,
+    bulk_load_rejects_unsorted_chunks
+];
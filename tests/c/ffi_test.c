@@ -0,0 +1,101 @@
+/*
+ * Exercises the exported API through the generated header, see `make test-c`
+ */
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "rust_verkle.h"
+
+#define LEAVES 3
+
+static void check(int condition, const char *message) {
+    if (!condition) {
+        fprintf(stderr, "FAILED: %s\n", message);
+        exit(1);
+    }
+}
+
+int main(void) {
+    check(rust_verkle_abi_version() == RUST_VERKLE_ABI_VERSION, "ABI version");
+
+    VerkleTrie *trie = verkle_trie_new(DatabaseScheme_MemoryDb, CommitScheme_TestCommitment, "");
+    check(trie != NULL, "create trie");
+
+    uint8_t keys[LEAVES][32];
+    uint8_t values[LEAVES][32];
+    for (int i = 0; i < LEAVES; i++) {
+        memset(keys[i], 0x11 * (i + 1), 32);
+        memset(values[i], 0x20 + i, 32);
+    }
+    /* Two leaves in the same stem */
+    keys[2][31] = 0;
+    memcpy(keys[2], keys[1], 31);
+
+    verkle_trie_insert(trie, keys[0], values[0]);
+    verkle_trie_insert_multiple(trie, (const uint8_t (*)[32])keys[1],
+                                (const uint8_t (*)[32])values[1], LEAVES - 1);
+
+    for (int i = 0; i < LEAVES; i++) {
+        const uint8_t *value = verkle_trie_get(trie, keys[i]);
+        check(value != NULL && memcmp(value, values[i], 32) == 0, "get inserted value");
+    }
+    uint8_t absent[32];
+    memset(absent, 0xee, 32);
+    check(verkle_trie_get(trie, absent) == NULL, "get absent key");
+
+    Proof *proof = get_verkle_proof(trie, keys[0]);
+    check(verify_verkle_proof(trie, proof->ptr, proof->len, keys[0], values[0]) == 1,
+          "verify single proof");
+    check(verify_verkle_proof(trie, proof->ptr, proof->len, keys[0], values[1]) == 0,
+          "reject single proof with a wrong value");
+
+    Proof *multi = get_verkle_proof_multiple(trie, (const uint8_t (*)[32])keys, LEAVES);
+    check(verify_verkle_proof_multiple(trie, multi->ptr, multi->len, (const uint8_t (*)[32])keys,
+                                       (const uint8_t (*)[32])values, LEAVES) == 1,
+          "verify multi proof");
+    values[2][0] ^= 1;
+    check(verify_verkle_proof_multiple(trie, multi->ptr, multi->len, (const uint8_t (*)[32])keys,
+                                       (const uint8_t (*)[32])values, LEAVES) == 0,
+          "reject multi proof with a wrong value");
+
+    /* All leaves of the trie are served in key order, with a proof that none is missing */
+    uint8_t first[32];
+    uint8_t last[32];
+    memset(first, 0, 32);
+    memset(last, 0xff, 32);
+    LeafRange *range = get_range_proof(trie, first, last, LEAVES);
+    check(range != NULL && range->len == LEAVES, "serve a range");
+    check(memcmp(range->keys[0], keys[0], 32) == 0, "serve the smallest key first");
+    Proof *root = get_root_commitment(trie);
+    check(verify_range_proof(root->ptr, root->len, first, range->keys, range->values, range->len,
+                             range->proof.ptr, range->proof.len) == 1,
+          "verify range proof");
+    verkle_leaf_range_free(range);
+    verkle_leaf_range_free(NULL);
+    verkle_proof_free(root);
+    verkle_proof_free(proof);
+    verkle_proof_free(multi);
+
+    /* Handles are validated, a database is not a trie and a freed trie is gone */
+    Database *db = create_verkle_db(DatabaseScheme_MemoryDb, "");
+    check(get_root_hash((VerkleTrie *)db) == NULL, "reject a database as a trie");
+    check(verkle_last_error() == VerkleError_WrongHandleType, "wrong handle type error");
+    VerkleTrie *from_db = create_trie_from_db(CommitScheme_TestCommitment, db);
+    check(verkle_db_free(db) == 0, "keep a database with a live trie");
+    check(verkle_last_error() == VerkleError_HandleInUse, "handle in use error");
+    IntegrityReport *report = check_db_integrity(CommitScheme_TestCommitment, db);
+    check(report != NULL && report->issues.len == 0, "check an empty database");
+    verkle_integrity_report_free(report);
+    check(verkle_trie_free(from_db) == 1 && verkle_db_free(db) == 1, "free trie then database");
+
+    check(verkle_trie_free(trie) == 1, "free trie");
+    check(verkle_last_error() == VerkleError_Ok, "no error after free");
+    check(verkle_trie_get(trie, keys[0]) == NULL, "reject a freed trie");
+    check(verkle_last_error() == VerkleError_InvalidHandle, "use after free error");
+    check(verkle_trie_free(trie) == 0, "reject a double free");
+
+    printf("ok\n");
+    return 0;
+}
@@ -8,8 +8,14 @@ mod db_trie_test_helper {
         verkle_trie_insert, CommitScheme, DatabaseScheme,
     };
     use rust_verkle::utils::{assert_value, get_boxed_value, str_to_cstr};
-    use rust_verkle::{clear_temp_changes_read_only_db, create_read_only_verkle_db};
-    use std::ffi::CStr;
+    use rust_verkle::{
+        clear_temp_changes_read_only_db, commit_temp_changes_read_only_db,
+        create_read_only_verkle_db, diff_read_only_db, get_root_hash, get_verkle_proof,
+        merge_read_only_db, verify_verkle_proof, verkle_db_free, verkle_last_error,
+        verkle_leaf_diff_free, verkle_proof_free, verkle_trie_free, verkle_trie_new, Database,
+        LeafDiff, VerkleError,
+    };
+    use std::ffi::{CStr, CString};
     use std::intrinsics::transmute;
     use std::os::raw::c_char;
     use tempfile::Builder;
@@ -79,6 +85,39 @@ mod db_trie_test_helper {
         assert_value(val, _ONE);
     }
 
+    // Proofs read the children of every node on the path. After a reopen, the cache only
+    // holds what was inserted since, which must not hide the children already on disk
+    pub fn prove_after_reopen(db_scheme: DatabaseScheme) {
+        let one: *const u8 = get_boxed_value(_ONE);
+        let one32: *const u8 = get_boxed_value(_ONE32);
+        let mut _two = _ONE;
+        _two[31] = 2;
+        let two: *const u8 = get_boxed_value(_two);
+
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = create_verkle_db(db_scheme, str_to_cstr(path));
+
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        verkle_trie_insert(trie, one, one);
+        verkle_trie_insert(trie, one32, one);
+        verkle_trie_flush(trie);
+
+        // `two` shares its stem with `one`, so the cache gets a partial stem and root
+        let trie_2 = create_trie_from_db(CommitScheme::TestCommitment, db);
+        verkle_trie_insert(trie_2, two, one32);
+
+        for (key, value) in [(one, one), (one32, one), (two, one32)] {
+            let proof = get_verkle_proof(trie_2, key);
+            let _proof = unsafe { &*proof };
+            assert_eq!(
+                verify_verkle_proof(trie_2, _proof.ptr, _proof.len, key, value),
+                1
+            );
+            verkle_proof_free(proof);
+        }
+    }
+
     pub fn create_trie_from_flushed_db_readonly(db_scheme: DatabaseScheme) {
         let one: *const u8 = get_boxed_value(_ONE);
         let one32: *const u8 = get_boxed_value(_ONE32);
@@ -121,6 +160,222 @@ mod db_trie_test_helper {
         assert_value(val, _ONE);
     }
 
+    // Flushed trie with `one` set to `_ONE`, and a first overlay over it where `one` is `_ONE32`
+    fn pending_overlay(db_scheme: DatabaseScheme, path: &str) -> (*mut Database, *mut Database) {
+        let one: *const u8 = get_boxed_value(_ONE);
+        let one32: *const u8 = get_boxed_value(_ONE32);
+
+        let db = create_verkle_db(db_scheme, str_to_cstr(path));
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        verkle_trie_insert(trie, one, one);
+        verkle_trie_flush(trie);
+        assert_eq!(verkle_trie_free(trie), 1);
+
+        let ro_db = create_read_only_verkle_db(db);
+        let ro_trie = create_trie_from_db(CommitScheme::TestCommitment, ro_db);
+        verkle_trie_insert(ro_trie, one, one32);
+        verkle_trie_flush(ro_trie);
+        (db, ro_db)
+    }
+
+    pub fn nested_read_only_overlays(db_scheme: DatabaseScheme) {
+        let one: *const u8 = get_boxed_value(_ONE);
+        let one32: *const u8 = get_boxed_value(_ONE32);
+
+        let dir = Builder::new().tempdir().unwrap();
+        let (db, ro_db) = pending_overlay(db_scheme, dir.path().to_str().unwrap());
+
+        // Two independent overlays on top of the first one
+        let nested_db = create_read_only_verkle_db(ro_db);
+        assert_ne!(nested_db, ro_db);
+        let sibling_db = create_read_only_verkle_db(ro_db);
+        let nested_trie = create_trie_from_db(CommitScheme::TestCommitment, nested_db);
+        let sibling_trie = create_trie_from_db(CommitScheme::TestCommitment, sibling_db);
+        assert_value(verkle_trie_get(nested_trie, one), _ONE32);
+
+        verkle_trie_insert(nested_trie, one32, one);
+        verkle_trie_flush(nested_trie);
+        assert_value(verkle_trie_get(nested_trie, one32), _ONE);
+        assert!(verkle_trie_get(sibling_trie, one32).is_null());
+        let ro_trie = create_trie_from_db(CommitScheme::TestCommitment, ro_db);
+        assert!(verkle_trie_get(ro_trie, one32).is_null());
+
+        // A third level reads through both overlays
+        let deepest_db = create_read_only_verkle_db(nested_db);
+        let deepest_trie = create_trie_from_db(CommitScheme::TestCommitment, deepest_db);
+        assert_value(verkle_trie_get(deepest_trie, one), _ONE32);
+        assert_value(verkle_trie_get(deepest_trie, one32), _ONE);
+
+        // Clearing an overlay leaves the ones below untouched
+        clear_temp_changes_read_only_db(nested_db);
+        assert!(verkle_trie_get(nested_trie, one32).is_null());
+        assert_value(verkle_trie_get(nested_trie, one), _ONE32);
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        assert_value(verkle_trie_get(trie, one), _ONE);
+
+        // An overlay is not freed while overlays are stacked on it
+        assert_eq!(verkle_db_free(nested_db), 0);
+        assert_eq!(verkle_trie_free(deepest_trie), 1);
+        assert_eq!(verkle_db_free(deepest_db), 1);
+        assert_eq!(verkle_trie_free(nested_trie), 1);
+        assert_eq!(verkle_db_free(nested_db), 1);
+    }
+
+    pub fn merge_read_only_overlay(db_scheme: DatabaseScheme) {
+        let one: *const u8 = get_boxed_value(_ONE);
+        let one32: *const u8 = get_boxed_value(_ONE32);
+
+        let dir = Builder::new().tempdir().unwrap();
+        let (db, ro_db) = pending_overlay(db_scheme, dir.path().to_str().unwrap());
+
+        let nested_db = create_read_only_verkle_db(ro_db);
+        let nested_trie = create_trie_from_db(CommitScheme::TestCommitment, nested_db);
+        verkle_trie_insert(nested_trie, one32, one);
+        verkle_trie_flush(nested_trie);
+        let nested_root = unsafe { *(get_root_hash(nested_trie) as *const [u8; 32]) };
+
+        assert_eq!(merge_read_only_db(nested_db), 1);
+        let ro_trie = create_trie_from_db(CommitScheme::TestCommitment, ro_db);
+        assert_value(verkle_trie_get(ro_trie, one32), _ONE);
+        assert_value(verkle_trie_get(ro_trie, one), _ONE32);
+        assert_value(get_root_hash(ro_trie), nested_root);
+
+        // The merged changes left the nested overlay, clearing it changes nothing
+        clear_temp_changes_read_only_db(nested_db);
+        assert_value(verkle_trie_get(nested_trie, one32), _ONE);
+
+        // Only an overlay stacked on another one has a parent to merge into
+        assert_eq!(merge_read_only_db(ro_db), 0);
+        assert_eq!(merge_read_only_db(db), 0);
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        assert!(verkle_trie_get(trie, one32).is_null());
+    }
+
+    // Key, value below the overlay if any and value in the overlay of every changed leaf
+    fn leaf_diff(db: *mut Database) -> Vec<([u8; 32], Option<[u8; 32]>, [u8; 32])> {
+        let diff_ptr = diff_read_only_db(db);
+        assert!(!diff_ptr.is_null());
+        let diff: &LeafDiff = unsafe { &*diff_ptr };
+        let keys = unsafe { std::slice::from_raw_parts(diff.keys, diff.len) };
+        let old_values = unsafe { std::slice::from_raw_parts(diff.old_values, diff.len) };
+        let new_values = unsafe { std::slice::from_raw_parts(diff.new_values, diff.len) };
+        let existed = unsafe { std::slice::from_raw_parts(diff.existed, diff.len) };
+        let changes = (0..diff.len)
+            .map(|i| {
+                let old_value = if existed[i] == 1 {
+                    Some(old_values[i])
+                } else {
+                    assert_eq!(old_values[i], [0u8; 32]);
+                    None
+                };
+                (keys[i], old_value, new_values[i])
+            })
+            .collect();
+        verkle_leaf_diff_free(diff_ptr);
+        changes
+    }
+
+    pub fn diff_read_only_overlay(db_scheme: DatabaseScheme) {
+        let one: *const u8 = get_boxed_value(_ONE);
+        let one32: *const u8 = get_boxed_value(_ONE32);
+
+        let dir = Builder::new().tempdir().unwrap();
+        let (db, ro_db) = pending_overlay(db_scheme, dir.path().to_str().unwrap());
+        assert!(diff_read_only_db(db).is_null());
+        assert_eq!(leaf_diff(ro_db), vec![(_ONE, Some(_ONE), _ONE32)]);
+
+        // Old values are the ones of the overlay below, a leaf written again with the value it
+        // already had is not a change
+        let nested_db = create_read_only_verkle_db(ro_db);
+        assert!(leaf_diff(nested_db).is_empty());
+        let nested_trie = create_trie_from_db(CommitScheme::TestCommitment, nested_db);
+        verkle_trie_insert(nested_trie, one, one32);
+        verkle_trie_insert(nested_trie, one32, one);
+        // Changes are only seen once flushed into the overlay
+        assert!(leaf_diff(nested_db).is_empty());
+        verkle_trie_flush(nested_trie);
+        assert_eq!(leaf_diff(nested_db), vec![(_ONE32, None, _ONE)]);
+
+        assert_eq!(merge_read_only_db(nested_db), 1);
+        assert!(leaf_diff(nested_db).is_empty());
+        assert_eq!(
+            leaf_diff(ro_db),
+            vec![(_ONE, Some(_ONE), _ONE32), (_ONE32, None, _ONE)]
+        );
+
+        clear_temp_changes_read_only_db(ro_db);
+        assert!(leaf_diff(ro_db).is_empty());
+        verkle_leaf_diff_free(std::ptr::null_mut());
+    }
+
+    // A path that cannot hold a database is reported as a storage error
+    pub fn open_at_file_path(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let file_path = dir.path().join("not_a_directory");
+        std::fs::write(&file_path, b"not a database").unwrap();
+        let path = CString::new(file_path.to_str().unwrap()).unwrap();
+
+        assert!(create_verkle_db(copy_scheme(&db_scheme), path.as_ptr()).is_null());
+        assert_eq!(verkle_last_error(), VerkleError::StorageError);
+        let trie = verkle_trie_new(db_scheme, CommitScheme::TestCommitment, path.as_ptr());
+        assert!(trie.is_null());
+        assert_eq!(verkle_last_error(), VerkleError::StorageError);
+    }
+
+    fn copy_scheme(db_scheme: &DatabaseScheme) -> DatabaseScheme {
+        match db_scheme {
+            DatabaseScheme::MemoryDb => DatabaseScheme::MemoryDb,
+            DatabaseScheme::RocksDb => DatabaseScheme::RocksDb,
+            DatabaseScheme::Redb => DatabaseScheme::Redb,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn commit_read_only_overlay(db_scheme: DatabaseScheme) {
+        let one: *const u8 = get_boxed_value(_ONE);
+        let one32: *const u8 = get_boxed_value(_ONE32);
+
+        let dir = Builder::new().tempdir().unwrap();
+        let (db, ro_db) = pending_overlay(copy_scheme(&db_scheme), dir.path().to_str().unwrap());
+        let ro_trie = create_trie_from_db(CommitScheme::TestCommitment, ro_db);
+        verkle_trie_insert(ro_trie, one32, one);
+        verkle_trie_flush(ro_trie);
+        let ro_root = unsafe { *(get_root_hash(ro_trie) as *const [u8; 32]) };
+
+        // An overlay stacked on another one is not committed
+        let nested_db = create_read_only_verkle_db(ro_db);
+        assert_eq!(commit_temp_changes_read_only_db(nested_db), 0);
+        assert_eq!(commit_temp_changes_read_only_db(db), 0);
+
+        // Nor is an overlay whose database has a trie, which would miss the committed changes
+        let base_trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        assert_eq!(commit_temp_changes_read_only_db(ro_db), 0);
+        assert_eq!(verkle_last_error(), VerkleError::HandleInUse);
+        assert_value(verkle_trie_get(ro_trie, one), _ONE32);
+        assert_value(verkle_trie_get(base_trie, one), _ONE);
+        assert_eq!(verkle_trie_free(base_trie), 1);
+
+        assert_eq!(commit_temp_changes_read_only_db(ro_db), 1);
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        assert_value(verkle_trie_get(trie, one), _ONE32);
+        assert_value(verkle_trie_get(trie, one32), _ONE);
+        assert_value(get_root_hash(trie), ro_root);
+
+        // The overlay is empty, it reads what the database now holds
+        clear_temp_changes_read_only_db(ro_db);
+        assert_value(verkle_trie_get(ro_trie, one), _ONE32);
+
+        // Replaying the changes on the database gives the same root
+        let replay_dir = Builder::new().tempdir().unwrap();
+        let replay_path = replay_dir.path().to_str().unwrap();
+        let replay_db = create_verkle_db(db_scheme, str_to_cstr(replay_path));
+        let replay_trie = create_trie_from_db(CommitScheme::TestCommitment, replay_db);
+        verkle_trie_insert(replay_trie, one, one);
+        verkle_trie_flush(replay_trie);
+        verkle_trie_insert(replay_trie, one, one32);
+        verkle_trie_insert(replay_trie, one32, one);
+        assert_value(get_root_hash(replay_trie), ro_root);
+    }
 }
 
 macro_rules! db_trie_test {
@@ -150,14 +405,41 @@ db_trie_test![
     create_db_trie,
     create_trie_from_empty_db,
     create_trie_from_flushed_db,
-    create_trie_from_flushed_db_readonly
+    prove_after_reopen,
+    create_trie_from_flushed_db_readonly,
+    nested_read_only_overlays,
+    merge_read_only_overlay,
+    commit_read_only_overlay,
+    diff_read_only_overlay
 ];
 
+#[cfg(feature = "rocks_db")]
 db_trie_test![
     RocksDBTrie;
     RocksDb;
     create_db_trie,
     create_trie_from_empty_db,
     create_trie_from_flushed_db,
-    create_trie_from_flushed_db_readonly
+    prove_after_reopen,
+    create_trie_from_flushed_db_readonly,
+    nested_read_only_overlays,
+    merge_read_only_overlay,
+    commit_read_only_overlay,
+    diff_read_only_overlay,
+    open_at_file_path
+];
+
+db_trie_test![
+    RedbTrie;
+    Redb;
+    create_db_trie,
+    create_trie_from_empty_db,
+    create_trie_from_flushed_db,
+    prove_after_reopen,
+    create_trie_from_flushed_db_readonly,
+    nested_read_only_overlays,
+    merge_read_only_overlay,
+    commit_read_only_overlay,
+    diff_read_only_overlay,
+    open_at_file_path
 ];
@@ -0,0 +1,444 @@
+use rust_verkle::*;
+
+#[cfg(test)]
+mod differential_test_helper {
+    use rand_chacha::rand_core::{RngCore, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use rust_verkle::*;
+    use std::collections::BTreeMap;
+    use std::ffi::CString;
+    use std::slice;
+    use tempfile::{Builder, TempDir};
+
+    const SEEDS: [u64; 4] = [1, 2, 3, 0x5eed];
+    const OPS_PER_SEED: usize = 150;
+    // Keys checked and proven by a single `Check`
+    const CHECKED_KEYS: usize = 24;
+    const PROVEN_KEYS: usize = 8;
+
+    type Leaves = Vec<([u8; 32], [u8; 32])>;
+
+    #[derive(Debug, Clone)]
+    pub enum Op {
+        Insert([u8; 32], [u8; 32]),
+        InsertMultiple(Leaves),
+        Flush,
+        // Drop the changes made since the last flush
+        Clear,
+        // Create a new trie from the database, the changes since the last flush are lost
+        Reopen,
+        // Insert into a read-only overlay of the flushed state, flush it and clear it again
+        Overlay(Leaves),
+        // Compare the values of these keys with the model and prove the present ones
+        Check(Vec<[u8; 32]>),
+    }
+
+    // Generates keys that collide in interesting ways: overwrites of known keys, new leaves in
+    // known stems, stems that share all but the last byte with a known stem, and fresh keys
+    struct Generator {
+        rng: ChaCha20Rng,
+        known: Vec<[u8; 32]>,
+    }
+
+    impl Generator {
+        fn below(&mut self, n: u64) -> u64 {
+            self.rng.next_u64() % n
+        }
+
+        fn bytes(&mut self) -> [u8; 32] {
+            let mut bytes = [0u8; 32];
+            self.rng.fill_bytes(&mut bytes);
+            bytes
+        }
+
+        fn key(&mut self) -> [u8; 32] {
+            if self.known.is_empty() {
+                let key = self.bytes();
+                self.known.push(key);
+                return key;
+            }
+            let known = self.known[self.below(self.known.len() as u64) as usize];
+            let key = match self.below(4) {
+                0 => known,
+                1 => {
+                    let mut key = known;
+                    key[31] = self.below(256) as u8;
+                    key
+                }
+                2 => {
+                    let mut key = known;
+                    key[30] ^= 1 + self.below(255) as u8;
+                    key
+                }
+                _ => self.bytes(),
+            };
+            self.known.push(key);
+            key
+        }
+
+        fn leaves(&mut self, len: usize) -> Leaves {
+            (0..len).map(|_| (self.key(), self.bytes())).collect()
+        }
+
+        fn op(&mut self) -> Op {
+            match self.below(100) {
+                0..=29 => Op::Insert(self.key(), self.bytes()),
+                30..=49 => {
+                    // Sometimes above the parallel insert threshold
+                    let len = if self.below(10) == 0 {
+                        1_200
+                    } else {
+                        1 + self.below(50) as usize
+                    };
+                    Op::InsertMultiple(self.leaves(len))
+                }
+                50..=64 => Op::Flush,
+                65..=72 => Op::Clear,
+                73..=77 => Op::Reopen,
+                78..=87 => {
+                    let len = 1 + self.below(20) as usize;
+                    Op::Overlay(self.leaves(len))
+                }
+                _ => {
+                    let mut keys: Vec<_> = (0..CHECKED_KEYS - 4)
+                        .map(|_| self.known[self.below(self.known.len() as u64) as usize])
+                        .collect();
+                    // A few keys that are most likely absent
+                    keys.extend((0..4).map(|_| self.bytes()));
+                    Op::Check(keys)
+                }
+            }
+        }
+    }
+
+    pub fn generate_ops(seed: u64, count: usize) -> Vec<Op> {
+        let mut generator = Generator {
+            rng: ChaCha20Rng::seed_from_u64(seed),
+            known: Vec::new(),
+        };
+        // Start with some state so that the first checks have something to look at
+        let mut ops = vec![Op::InsertMultiple(generator.leaves(100))];
+        ops.extend((0..count).map(|_| generator.op()));
+        ops
+    }
+
+    // Expected content of the trie: the flushed state and the changes made since
+    #[derive(Default)]
+    struct Model {
+        flushed: BTreeMap<[u8; 32], [u8; 32]>,
+        pending: BTreeMap<[u8; 32], [u8; 32]>,
+    }
+
+    impl Model {
+        fn get(&self, key: &[u8; 32]) -> Option<[u8; 32]> {
+            self.pending
+                .get(key)
+                .or_else(|| self.flushed.get(key))
+                .copied()
+        }
+    }
+
+    struct Instance {
+        _dir: TempDir,
+        commit_scheme: CommitScheme,
+        db: *mut Database,
+        trie: *mut VerkleTrie,
+    }
+
+    fn root_hash(trie: *mut VerkleTrie) -> [u8; 32] {
+        let hash = get_root_hash(trie);
+        let mut root = [0u8; 32];
+        root.copy_from_slice(unsafe { slice::from_raw_parts(hash, 32) });
+        root
+    }
+
+    fn get(trie: *mut VerkleTrie, key: &[u8; 32]) -> Option<[u8; 32]> {
+        let value = verkle_trie_get(trie, key.as_ptr());
+        if value.is_null() {
+            None
+        } else {
+            Some(unsafe { *(value as *const [u8; 32]) })
+        }
+    }
+
+    fn insert_multiple(trie: *mut VerkleTrie, leaves: &[([u8; 32], [u8; 32])]) {
+        let keys: Vec<_> = leaves.iter().map(|(key, _)| *key).collect();
+        let vals: Vec<_> = leaves.iter().map(|(_, val)| *val).collect();
+        verkle_trie_insert_multiple(trie, keys.as_ptr(), vals.as_ptr(), keys.len());
+    }
+
+    fn check_keys<'a>(
+        trie: *mut VerkleTrie,
+        keys: impl Iterator<Item = &'a [u8; 32]>,
+        expected: impl Fn(&[u8; 32]) -> Option<[u8; 32]>,
+        context: &str,
+    ) {
+        for key in keys {
+            assert_eq!(
+                get(trie, key),
+                expected(key),
+                "{}: value of 0x{}",
+                context,
+                hex::encode(key)
+            );
+        }
+    }
+
+    fn check_proof(trie: *mut VerkleTrie, leaves: &[([u8; 32], [u8; 32])], context: &str) {
+        if leaves.is_empty() {
+            return;
+        }
+        let keys: Vec<_> = leaves.iter().map(|(key, _)| *key).collect();
+        let vals: Vec<_> = leaves.iter().map(|(_, val)| *val).collect();
+        let proof = unsafe { &*get_verkle_proof_multiple(trie, keys.as_ptr(), keys.len()) };
+        let verified = verify_verkle_proof_multiple(
+            trie,
+            proof.ptr,
+            proof.len,
+            keys.as_ptr(),
+            vals.as_ptr(),
+            keys.len(),
+        );
+        assert_eq!(verified, 1, "{}: proof of {} keys", context, keys.len());
+    }
+
+    // Apply `ops` to a fresh trie, checking values and proofs against the model as it goes
+    // Returns the root hash after every operation, to be compared across databases
+    pub fn run(
+        db_scheme: DatabaseScheme,
+        commit_scheme: CommitScheme,
+        ops: &[Op],
+    ) -> Vec<[u8; 32]> {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let db = create_verkle_db(db_scheme, path.as_ptr());
+        let trie = create_trie_from_db(copy_scheme(&commit_scheme), db);
+        let mut instance = Instance {
+            _dir: dir,
+            commit_scheme,
+            db,
+            trie,
+        };
+
+        // The root branch only exists in the batch until the first flush, a clear before it
+        // would drop it
+        verkle_trie_flush(instance.trie);
+        let mut flushed_root = root_hash(instance.trie);
+
+        let mut model = Model::default();
+        let mut roots = Vec::with_capacity(ops.len());
+        for (i, op) in ops.iter().enumerate() {
+            let context = format!("op {} ({})", i, op_name(op));
+            match op {
+                Op::Insert(key, value) => {
+                    verkle_trie_insert(instance.trie, key.as_ptr(), value.as_ptr());
+                    model.pending.insert(*key, *value);
+                    check_keys(instance.trie, [*key].iter(), |key| model.get(key), &context);
+                }
+                Op::InsertMultiple(leaves) => {
+                    insert_multiple(instance.trie, leaves);
+                    model.pending.extend(leaves.iter().copied());
+                    let keys = leaves.iter().map(|(key, _)| key);
+                    check_keys(instance.trie, keys, |key| model.get(key), &context);
+                }
+                Op::Flush => {
+                    verkle_trie_flush(instance.trie);
+                    let pending = std::mem::take(&mut model.pending);
+                    model.flushed.extend(pending);
+                    flushed_root = root_hash(instance.trie);
+                }
+                Op::Clear => {
+                    verkle_trie_clear(instance.trie);
+                    model.pending.clear();
+                    assert_eq!(root_hash(instance.trie), flushed_root, "{}", context);
+                }
+                Op::Reopen => {
+                    assert_eq!(verkle_trie_free(instance.trie), 1, "{}", context);
+                    instance.trie =
+                        create_trie_from_db(copy_scheme(&instance.commit_scheme), instance.db);
+                    model.pending.clear();
+                    assert_eq!(root_hash(instance.trie), flushed_root, "{}", context);
+                }
+                Op::Overlay(leaves) => {
+                    run_overlay(&instance, &model, flushed_root, leaves, &context);
+                }
+                Op::Check(keys) => {
+                    check_keys(instance.trie, keys.iter(), |key| model.get(key), &context);
+                    let mut present: Vec<_> = keys
+                        .iter()
+                        .filter_map(|key| model.get(key).map(|value| (*key, value)))
+                        .collect();
+                    present.sort();
+                    present.dedup();
+                    present.truncate(PROVEN_KEYS);
+                    check_proof(instance.trie, &present, &context);
+                }
+            }
+            roots.push(root_hash(instance.trie));
+        }
+
+        // Everything written is still readable after a final flush and reopen
+        verkle_trie_flush(instance.trie);
+        model.flushed.extend(std::mem::take(&mut model.pending));
+        let reopened = create_trie_from_db(copy_scheme(&instance.commit_scheme), instance.db);
+        check_keys(
+            reopened,
+            model.flushed.keys(),
+            |key| model.get(key),
+            "final reopen",
+        );
+        let leaves: Vec<_> = model
+            .flushed
+            .iter()
+            .step_by(37)
+            .take(PROVEN_KEYS)
+            .map(|(key, value)| (*key, *value))
+            .collect();
+        check_proof(reopened, &leaves, "final reopen");
+        roots.push(root_hash(reopened));
+        roots
+    }
+
+    fn run_overlay(
+        instance: &Instance,
+        model: &Model,
+        flushed_root: [u8; 32],
+        leaves: &[([u8; 32], [u8; 32])],
+        context: &str,
+    ) {
+        // The overlay sees the flushed state, not the changes pending in the trie
+        let ro_db = create_read_only_verkle_db(instance.db);
+        let ro_trie = create_trie_from_db(copy_scheme(&instance.commit_scheme), ro_db);
+        assert_eq!(
+            root_hash(ro_trie),
+            flushed_root,
+            "{}: overlay root",
+            context
+        );
+
+        let mut overlay = model.flushed.clone();
+        overlay.extend(leaves.iter().copied());
+        insert_multiple(ro_trie, leaves);
+        let keys = leaves.iter().map(|(key, _)| key);
+        check_keys(
+            ro_trie,
+            keys.clone(),
+            |key| overlay.get(key).copied(),
+            context,
+        );
+        let root = root_hash(ro_trie);
+
+        verkle_trie_flush(ro_trie);
+        check_keys(
+            ro_trie,
+            keys.clone(),
+            |key| overlay.get(key).copied(),
+            context,
+        );
+        assert_eq!(
+            root_hash(ro_trie),
+            root,
+            "{}: overlay root after flush",
+            context
+        );
+        let proven: Vec<_> = overlay
+            .iter()
+            .filter(|(key, _)| leaves.iter().any(|(leaf, _)| leaf == *key))
+            .take(PROVEN_KEYS)
+            .map(|(key, value)| (*key, *value))
+            .collect();
+        check_proof(ro_trie, &proven, context);
+
+        clear_temp_changes_read_only_db(ro_db);
+        verkle_trie_clear(ro_trie);
+        check_keys(
+            ro_trie,
+            keys,
+            |key| model.flushed.get(key).copied(),
+            context,
+        );
+        assert_eq!(
+            root_hash(ro_trie),
+            flushed_root,
+            "{}: cleared overlay root",
+            context
+        );
+
+        // The base trie did not see any of it
+        let keys = leaves.iter().map(|(key, _)| key);
+        check_keys(instance.trie, keys, |key| model.get(key), context);
+    }
+
+    fn op_name(op: &Op) -> &'static str {
+        match op {
+            Op::Insert(..) => "insert",
+            Op::InsertMultiple(..) => "insert_multiple",
+            Op::Flush => "flush",
+            Op::Clear => "clear",
+            Op::Reopen => "reopen",
+            Op::Overlay(..) => "overlay",
+            Op::Check(..) => "check",
+        }
+    }
+
+    fn copy_scheme(commit_scheme: &CommitScheme) -> CommitScheme {
+        match commit_scheme {
+            CommitScheme::TestCommitment => CommitScheme::TestCommitment,
+            CommitScheme::PrecomputeLagrange => CommitScheme::PrecomputeLagrange,
+        }
+    }
+
+    // Every database gives the same values, roots and valid proofs for the same operations
+    // Commitments differ between schemes, so roots are only compared within a scheme
+    pub fn random_operations(commit_scheme: CommitScheme) {
+        for seed in SEEDS {
+            let ops = generate_ops(seed, OPS_PER_SEED);
+            let memory_roots = run(DatabaseScheme::MemoryDb, copy_scheme(&commit_scheme), &ops);
+            let rocksdb_roots = run(DatabaseScheme::RocksDb, copy_scheme(&commit_scheme), &ops);
+            for (i, (memory, rocksdb)) in memory_roots.iter().zip(rocksdb_roots.iter()).enumerate()
+            {
+                assert_eq!(
+                    memory, rocksdb,
+                    "seed {}: roots differ after op {}",
+                    seed, i
+                );
+            }
+            assert_eq!(memory_roots.len(), rocksdb_roots.len());
+            let redb_roots = run(DatabaseScheme::Redb, copy_scheme(&commit_scheme), &ops);
+            assert_eq!(memory_roots, redb_roots, "seed {}: redb roots differ", seed);
+        }
+    }
+}
+
+macro_rules! differential_test {
+    (
+        $module_name: ident;   // Module Name
+        $commit_enum: ident;  // Commit enum
+        $($function_name: ident),*  // list of functions to implement
+    ) => {
+        #[cfg(test)]
+        #[allow(non_snake_case)]
+        mod $module_name {
+            use super::*;
+
+            $(
+                #[test]
+                fn $function_name() {
+                    differential_test_helper::$function_name(CommitScheme::$commit_enum);
+                }
+            )*
+        }
+    };
+}
+
+differential_test![
+    TestCommitmentDifferential;
+    TestCommitment;
+    random_operations
+];
+
+differential_test![
+    PrecomputeLagrangeDifferential;
+    PrecomputeLagrange;
+    random_operations
+];
@@ -0,0 +1,287 @@
+use rust_verkle::*;
+
+#[cfg(test)]
+mod external_db_test_helper {
+    use rust_verkle::utils::{assert_value, str_to_cstr};
+    use rust_verkle::*;
+    use std::collections::BTreeMap;
+    use std::os::raw::c_void;
+    use std::slice;
+    use tempfile::Builder;
+
+    // Stand-in for the key value store of the host
+    #[derive(Default)]
+    pub struct HostStore {
+        pub pairs: BTreeMap<Vec<u8>, Vec<u8>>,
+        pub fail_writes: bool,
+        pub flushes: usize,
+        pub released: bool,
+    }
+
+    fn bytes<'a>(ptr: *const u8, len: usize) -> &'a [u8] {
+        if len == 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(ptr, len) }
+    }
+
+    fn host<'a>(context: *mut c_void) -> &'a mut HostStore {
+        unsafe { &mut *(context as *mut HostStore) }
+    }
+
+    extern "C" fn get(
+        context: *mut c_void,
+        key: *const u8,
+        key_len: usize,
+        sink: *mut c_void,
+        emit: ExternalSink,
+    ) {
+        if let Some(value) = host(context).pairs.get(bytes(key, key_len)) {
+            emit(sink, std::ptr::null(), 0, value.as_ptr(), value.len());
+        }
+    }
+
+    extern "C" fn put_batch(
+        context: *mut c_void,
+        keys: *const Proof,
+        values: *const Proof,
+        len: usize,
+    ) -> u8 {
+        let host = host(context);
+        if host.fail_writes {
+            return 0;
+        }
+        let keys = unsafe { slice::from_raw_parts(keys, len) };
+        let values = unsafe { slice::from_raw_parts(values, len) };
+        for (key, value) in keys.iter().zip(values.iter()) {
+            host.pairs.insert(
+                bytes(key.ptr, key.len).to_vec(),
+                bytes(value.ptr, value.len).to_vec(),
+            );
+        }
+        1
+    }
+
+    extern "C" fn iterate_prefix(
+        context: *mut c_void,
+        prefix: *const u8,
+        prefix_len: usize,
+        sink: *mut c_void,
+        emit: ExternalSink,
+    ) {
+        let prefix = bytes(prefix, prefix_len);
+        for (key, value) in host(context).pairs.range(prefix.to_vec()..) {
+            if !key.starts_with(prefix) {
+                break;
+            }
+            emit(sink, key.as_ptr(), key.len(), value.as_ptr(), value.len());
+        }
+    }
+
+    extern "C" fn flush(context: *mut c_void) -> u8 {
+        host(context).flushes += 1;
+        1
+    }
+
+    extern "C" fn release(context: *mut c_void) {
+        host(context).released = true;
+    }
+
+    pub fn new_host() -> &'static mut HostStore {
+        Box::leak(Box::new(HostStore::default()))
+    }
+
+    pub fn open_db(host: &mut HostStore) -> *mut Database {
+        let store = ExternalStore {
+            context: host as *mut HostStore as *mut c_void,
+            get: Some(get),
+            put_batch: Some(put_batch),
+            iterate_prefix: Some(iterate_prefix),
+            flush: Some(flush),
+            release: Some(release),
+        };
+        create_external_verkle_db(&store)
+    }
+
+    fn leaf(i: u8) -> ([u8; 32], [u8; 32]) {
+        let mut key = [i; 32];
+        key[31] = i % 3;
+        (key, [i.wrapping_mul(7); 32])
+    }
+
+    pub fn insert_flush_and_reopen(commit_scheme: fn() -> CommitScheme) {
+        let host = new_host();
+        let db = open_db(host);
+        let trie = create_trie_from_db(commit_scheme(), db);
+        for i in 0..50u8 {
+            let (key, value) = leaf(i);
+            verkle_trie_insert(trie, key.as_ptr(), value.as_ptr());
+        }
+        let root = unsafe { *(get_root_hash(trie) as *const [u8; 32]) };
+        assert!(host.pairs.is_empty());
+
+        assert_eq!(verkle_trie_flush_block(trie, 17), 1);
+        assert!(!host.pairs.is_empty());
+        assert_eq!(host.flushes, 1);
+
+        let mut block = 0u64;
+        let mut recorded_root = [0u8; 32];
+        assert_eq!(
+            get_last_flushed_block(db, &mut block, recorded_root.as_mut_ptr()),
+            1
+        );
+        assert_eq!(block, 17);
+
+        // A second database over the same store sees the flushed trie
+        assert_eq!(verkle_trie_free(trie), 1);
+        assert_eq!(verkle_db_free(db), 1);
+        assert!(host.released);
+        let db = open_db(host);
+        let trie = create_trie_from_db(commit_scheme(), db);
+        assert_value(get_root_hash(trie), root);
+        for i in 0..50u8 {
+            let (key, value) = leaf(i);
+            assert_value(verkle_trie_get(trie, key.as_ptr()), value);
+        }
+
+        let report = unsafe { &*check_db_integrity(commit_scheme(), db) };
+        assert_eq!(report.stems, 50);
+        assert_eq!(report.leaves, 50);
+        assert_eq!(
+            (report.mismatched, report.dangling, report.orphans),
+            (0, 0, 0)
+        );
+    }
+
+    // A batch the host rejects is kept in memory and written by the next flush
+    pub fn rejected_write(commit_scheme: fn() -> CommitScheme) {
+        let host = new_host();
+        let db = open_db(host);
+        let trie = create_trie_from_db(commit_scheme(), db);
+        let (key, value) = leaf(1);
+        verkle_trie_insert(trie, key.as_ptr(), value.as_ptr());
+
+        host.fail_writes = true;
+        assert_eq!(verkle_trie_flush_block(trie, 1), 0);
+        assert!(host.pairs.is_empty());
+        assert_eq!(host.flushes, 0);
+        assert_value(verkle_trie_get(trie, key.as_ptr()), value);
+
+        host.fail_writes = false;
+        assert_eq!(verkle_trie_flush_block(trie, 1), 1);
+        let reopened = create_trie_from_db(commit_scheme(), db);
+        assert_value(verkle_trie_get(reopened, key.as_ptr()), value);
+    }
+
+    // A snapshot import whose writes the host rejects fails with a storage error
+    pub fn import_rejected_write(commit_scheme: fn() -> CommitScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let snapshot = dir.path().join("snapshot");
+        let snapshot = str_to_cstr(snapshot.to_str().unwrap());
+        let source = verkle_trie_new(DatabaseScheme::MemoryDb, commit_scheme(), str_to_cstr(""));
+        for i in 0..20u8 {
+            let (key, value) = leaf(i);
+            verkle_trie_insert(source, key.as_ptr(), value.as_ptr());
+        }
+        assert_eq!(export_state(source, snapshot, 0), 1);
+
+        let host = new_host();
+        let db = open_db(host);
+        host.fail_writes = true;
+        assert_eq!(import_state(commit_scheme(), db, snapshot), 0);
+        assert_eq!(verkle_last_error(), VerkleError::StorageError);
+        assert!(host.pairs.is_empty());
+
+        host.fail_writes = false;
+        assert_eq!(import_state(commit_scheme(), db, snapshot), 1);
+    }
+
+    // Exporting a stem whose commitment is missing from storage fails with a storage error
+    pub fn export_missing_stem_meta(commit_scheme: fn() -> CommitScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let snapshot = dir.path().join("snapshot");
+        let snapshot = str_to_cstr(snapshot.to_str().unwrap());
+        let host = new_host();
+        let db = open_db(host);
+        let trie = create_trie_from_db(commit_scheme(), db);
+        for i in 0..20u8 {
+            let (key, value) = leaf(i);
+            verkle_trie_insert(trie, key.as_ptr(), value.as_ptr());
+        }
+        assert_eq!(verkle_trie_flush(trie), 1);
+        assert_eq!(verkle_trie_free(trie), 1);
+
+        // Stem commitments are stored under 0x01 followed by the stem
+        host.pairs
+            .retain(|key, _| !(key.len() == 32 && key[0] == 0x01));
+        let trie = create_trie_from_db(commit_scheme(), db);
+        assert_eq!(export_state(trie, snapshot, 0), 1);
+        assert_eq!(export_state(trie, snapshot, 1), 0);
+        assert_eq!(verkle_last_error(), VerkleError::StorageError);
+    }
+
+    pub fn missing_callback() {
+        let host = new_host();
+        let store = ExternalStore {
+            context: host as *mut HostStore as *mut c_void,
+            get: Some(get),
+            put_batch: Some(put_batch),
+            iterate_prefix: None,
+            flush: Some(flush),
+            release: None,
+        };
+        assert!(create_external_verkle_db(&store).is_null());
+        assert!(create_external_verkle_db(std::ptr::null()).is_null());
+        assert!(create_verkle_db(DatabaseScheme::External, str_to_cstr("")).is_null());
+        let trie = verkle_trie_new(
+            DatabaseScheme::External,
+            CommitScheme::TestCommitment,
+            str_to_cstr(""),
+        );
+        assert!(trie.is_null());
+
+        // Overlays are not built over the store of the host
+        let db = open_db(host);
+        assert!(create_read_only_verkle_db(db).is_null());
+    }
+}
+
+#[test]
+fn missing_callback() {
+    external_db_test_helper::missing_callback();
+}
+
+macro_rules! external_db_test {
+    ($name:ident, $commit_scheme:expr) => {
+        mod $name {
+            use super::*;
+
+            fn commit_scheme() -> CommitScheme {
+                $commit_scheme
+            }
+
+            #[test]
+            fn insert_flush_and_reopen() {
+                external_db_test_helper::insert_flush_and_reopen(commit_scheme);
+            }
+
+            #[test]
+            fn rejected_write() {
+                external_db_test_helper::rejected_write(commit_scheme);
+            }
+
+            #[test]
+            fn import_rejected_write() {
+                external_db_test_helper::import_rejected_write(commit_scheme);
+            }
+
+            #[test]
+            fn export_missing_stem_meta() {
+                external_db_test_helper::export_missing_stem_meta(commit_scheme);
+            }
+        }
+    };
+}
+
+external_db_test!(test_commitment, CommitScheme::TestCommitment);
+external_db_test!(precompute_lagrange, CommitScheme::PrecomputeLagrange);
@@ -0,0 +1,213 @@
+// Crash tests run the writer in a child process: the test binary runs itself again with
+// only `crash_child` selected and the environment variables below telling it what to do
+const CHILD_DB_PATH: &str = "VERKLE_CRASH_CHILD_DB";
+const CHILD_FAULT: &str = "VERKLE_CRASH_CHILD_FAULT";
+const CHILD_FAULT_BLOCK: &str = "VERKLE_CRASH_CHILD_FAULT_BLOCK";
+
+#[cfg(test)]
+mod flush_test_helper {
+    use super::{CHILD_DB_PATH, CHILD_FAULT, CHILD_FAULT_BLOCK};
+    use rust_verkle::failpoint::{set_flush_fault, FlushFault};
+    use rust_verkle::utils::{assert_value, get_boxed_value, str_to_cstr};
+    use rust_verkle::*;
+    use std::ffi::CString;
+    use std::process::{Child, Command};
+    use std::slice;
+    use tempfile::Builder;
+
+    pub const BLOCKS: u64 = 200;
+
+    // Every block adds new leaves and overwrites some of the leaves of the previous block
+    pub fn block_leaves(block: u64) -> Vec<([u8; 32], [u8; 32])> {
+        let mut leaves = Vec::new();
+        for i in 0..20u64 {
+            let key_block = if i < 5 && block > 0 { block - 1 } else { block };
+            let mut key = [0u8; 32];
+            key[..8].copy_from_slice(&(key_block * 7919 + i).to_le_bytes());
+            key[31] = i as u8;
+            let mut value = [0u8; 32];
+            value[..8].copy_from_slice(&block.to_le_bytes());
+            value[8] = i as u8;
+            leaves.push((key, value));
+        }
+        leaves
+    }
+
+    fn insert_block(trie: *mut VerkleTrie, block: u64) {
+        let leaves = block_leaves(block);
+        let keys: Vec<_> = leaves.iter().map(|(key, _)| *key).collect();
+        let vals: Vec<_> = leaves.iter().map(|(_, val)| *val).collect();
+        verkle_trie_insert_multiple(trie, keys.as_ptr(), vals.as_ptr(), keys.len());
+    }
+
+    fn root_commitment(trie: *mut VerkleTrie) -> Vec<u8> {
+        let commitment = unsafe { &*get_root_commitment(trie) };
+        unsafe { slice::from_raw_parts(commitment.ptr, commitment.len) }.to_vec()
+    }
+
+    // Root of the empty trie followed by the root after each block
+    fn expected_roots(blocks: u64) -> Vec<Vec<u8>> {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let trie = verkle_trie_new(
+            DatabaseScheme::MemoryDb,
+            CommitScheme::TestCommitment,
+            str_to_cstr(path),
+        );
+        let mut roots = vec![root_commitment(trie)];
+        for block in 0..blocks {
+            insert_block(trie, block);
+            roots.push(root_commitment(trie));
+        }
+        roots
+    }
+
+    fn open_db(path: &str) -> *mut Database {
+        let db_path = CString::new(path).unwrap();
+        create_verkle_db(DatabaseScheme::RocksDb, db_path.as_ptr())
+    }
+
+    // Body of the child process: import blocks and flush each of them until the process
+    // crashes, either through the injected fault or by being killed
+    pub fn run_child(path: &str, fault: FlushFault, fault_block: u64) {
+        let db = open_db(path);
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        for block in 0..BLOCKS {
+            insert_block(trie, block);
+            if block == fault_block {
+                set_flush_fault(fault);
+            }
+            assert_eq!(verkle_trie_flush_block(trie, block), 1);
+        }
+    }
+
+    fn spawn_child(path: &str, fault: &str, fault_block: u64) -> Child {
+        Command::new(std::env::current_exe().unwrap())
+            .args(&["crash_child", "--exact", "--nocapture", "--test-threads=1"])
+            .env(CHILD_DB_PATH, path)
+            .env(CHILD_FAULT, fault)
+            .env(CHILD_FAULT_BLOCK, fault_block.to_string())
+            .spawn()
+            .unwrap()
+    }
+
+    // Reopen the database and check that it holds the state of the last recorded block
+    // Returns that block, or None if no block was flushed
+    fn check_reopened(path: &str, roots: &[Vec<u8>]) -> Option<u64> {
+        let db = open_db(path);
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        let root = root_commitment(trie);
+
+        let mut block = 0u64;
+        let mut recorded_root = [0u8; 32];
+        if get_last_flushed_block(db, &mut block, recorded_root.as_mut_ptr()) == 0 {
+            assert_eq!(root, roots[0]);
+            return None;
+        }
+        assert_eq!(root, recorded_root.to_vec());
+        assert_eq!(root, roots[block as usize + 1]);
+
+        let report = unsafe { &*check_db_integrity(CommitScheme::TestCommitment, db) };
+        assert_eq!((report.mismatched, report.dangling), (0, 0));
+        Some(block)
+    }
+
+    pub fn crash_at_fault_point() {
+        let roots = expected_roots(BLOCKS);
+        for (fault, fault_block, last_block) in [
+            ("crash_before_write", 3, Some(2)),
+            ("crash_after_write", 3, Some(3)),
+            ("crash_before_write", 0, None),
+        ] {
+            let dir = Builder::new().tempdir().unwrap();
+            let path = dir.path().to_str().unwrap();
+            let status = spawn_child(path, fault, fault_block).wait().unwrap();
+            assert!(!status.success());
+            assert_eq!(check_reopened(path, &roots), last_block);
+        }
+    }
+
+    pub fn kill_mid_flush() {
+        let roots = expected_roots(BLOCKS);
+        for delay in [30u64, 120, 250, 500, 900] {
+            let dir = Builder::new().tempdir().unwrap();
+            let path = dir.path().to_str().unwrap();
+            let mut child = spawn_child(path, "none", 0);
+            std::thread::sleep(std::time::Duration::from_millis(delay));
+            let _ = child.kill();
+            child.wait().unwrap();
+            check_reopened(path, &roots);
+        }
+    }
+
+    pub fn failed_flush_keeps_changes() {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = open_db(path);
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        let roots = expected_roots(2);
+
+        insert_block(trie, 0);
+        assert_eq!(verkle_trie_flush_block(trie, 0), 1);
+        insert_block(trie, 1);
+
+        set_flush_fault(FlushFault::FailWrite);
+        assert_eq!(verkle_trie_flush_block(trie, 1), 0);
+        assert_eq!(verkle_last_error(), VerkleError::StorageError);
+        assert_eq!(verkle_trie_flush(trie), 0);
+        assert_eq!(verkle_last_error(), VerkleError::StorageError);
+        set_flush_fault(FlushFault::None);
+
+        // Nothing of block 1 reached storage, but the trie still has it
+        let mut block = 0u64;
+        let mut root = [0u8; 32];
+        assert_eq!(get_last_flushed_block(db, &mut block, root.as_mut_ptr()), 1);
+        assert_eq!((block, root.to_vec()), (0, roots[1].clone()));
+        let fresh_trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        assert_eq!(root_commitment(fresh_trie), roots[1]);
+        for (key, value) in block_leaves(1) {
+            assert_value(verkle_trie_get(trie, get_boxed_value(key)), value);
+        }
+
+        // The retry writes the whole block
+        assert_eq!(verkle_trie_flush_block(trie, 1), 1);
+        assert_eq!(get_last_flushed_block(db, &mut block, root.as_mut_ptr()), 1);
+        assert_eq!((block, root.to_vec()), (1, roots[2].clone()));
+        let fresh_trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        assert_eq!(root_commitment(fresh_trie), roots[2]);
+    }
+}
+
+#[test]
+fn crash_child() {
+    use rust_verkle::failpoint::FlushFault;
+
+    let path = match std::env::var(CHILD_DB_PATH) {
+        Ok(path) => path,
+        // Not started by a crash test
+        Err(_) => return,
+    };
+    let fault = match std::env::var(CHILD_FAULT).unwrap().as_str() {
+        "crash_before_write" => FlushFault::CrashBeforeWrite,
+        "crash_after_write" => FlushFault::CrashAfterWrite,
+        _ => FlushFault::None,
+    };
+    let fault_block = std::env::var(CHILD_FAULT_BLOCK).unwrap().parse().unwrap();
+    flush_test_helper::run_child(&path, fault, fault_block);
+}
+
+#[test]
+fn crash_at_fault_point() {
+    flush_test_helper::crash_at_fault_point();
+}
+
+#[test]
+fn kill_mid_flush() {
+    flush_test_helper::kill_mid_flush();
+}
+
+#[test]
+#[serial_test::serial]
+fn failed_flush_keeps_changes() {
+    flush_test_helper::failed_flush_keeps_changes();
+}
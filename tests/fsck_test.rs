@@ -0,0 +1,158 @@
+use rust_verkle::*;
+
+#[cfg(test)]
+mod fsck_test_helper {
+    use rust_verkle::fsck::{check_integrity, Issue};
+    use rust_verkle::utils::str_to_cstr;
+    use rust_verkle::*;
+    use tempfile::Builder;
+    use verkle_trie::committer::test::TestCommitter;
+    use verkle_trie::config::Config;
+    use verkle_trie::database::memory_db::MemoryDb;
+    use verkle_trie::database::{ReadOnlyHigherDb, WriteOnlyHigherDb};
+    use verkle_trie::{Trie, TrieTrait};
+
+    fn leaves() -> Vec<([u8; 32], [u8; 32])> {
+        let mut leaves = Vec::new();
+        for i in 0..40u8 {
+            let mut key = [i % 4; 32];
+            key[1] = i;
+            key[31] = i;
+            leaves.push((key, [i; 32]));
+        }
+        leaves
+    }
+
+    pub fn fsck_clean_db(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = create_verkle_db(db_scheme, str_to_cstr(path));
+
+        // Nothing was written yet
+        let report_ptr = check_db_integrity(CommitScheme::TestCommitment, db);
+        let report = unsafe { &*report_ptr };
+        assert_eq!(
+            (report.branches, report.stems, report.issues.len),
+            (0, 0, 0)
+        );
+        verkle_integrity_report_free(report_ptr);
+
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        let all = leaves();
+        let keys: Vec<_> = all.iter().map(|(key, _)| *key).collect();
+        let vals: Vec<_> = all.iter().map(|(_, val)| *val).collect();
+        verkle_trie_insert_multiple(trie, keys.as_ptr(), vals.as_ptr(), keys.len());
+        verkle_trie_flush(trie);
+
+        let report_ptr = check_db_integrity(CommitScheme::TestCommitment, db);
+        let report = unsafe { &*report_ptr };
+        assert_eq!(report.stems, all.len() as u64);
+        assert_eq!(report.leaves, all.len() as u64);
+        // The root and one branch for each of the 4 first bytes
+        assert_eq!(report.branches, 5);
+        assert_eq!(
+            (report.mismatched, report.dangling, report.orphans),
+            (0, 0, 0)
+        );
+        assert_eq!(report.issues.len, 0);
+        verkle_integrity_report_free(report_ptr);
+        verkle_integrity_report_free(std::ptr::null_mut());
+
+        let ro_db = create_read_only_verkle_db(db);
+        assert!(check_db_integrity(CommitScheme::TestCommitment, ro_db).is_null());
+    }
+
+    pub fn fsck_detects_corruption() {
+        let config = Config {
+            db: MemoryDb::new(),
+            committer: TestCommitter,
+        };
+        let mut trie = Trie::new(config);
+        trie.insert(leaves().into_iter());
+        assert!(check_integrity(&trie.storage, &TestCommitter).is_ok());
+
+        let (key, _) = leaves()[5];
+        let mut stem = [0u8; 31];
+        stem.copy_from_slice(&key[..31]);
+
+        // A leaf value that does not match the stem commitment
+        trie.storage.leaf_table.insert(key, [0xaa; 32]);
+        let report = check_integrity(&trie.storage, &TestCommitter);
+        assert_eq!(report.issues, vec![Issue::StemCommitmentMismatch { stem }]);
+        trie.storage.leaf_table.insert(key, leaves()[5].1);
+
+        // A stem that no branch points to
+        let mut orphan = stem;
+        orphan[30] ^= 1;
+        let meta = trie.storage.get_stem_meta(stem).unwrap();
+        trie.storage.insert_stem(orphan, meta, 0);
+        let report = check_integrity(&trie.storage, &TestCommitter);
+        assert_eq!(report.issues, vec![Issue::OrphanStem { stem: orphan }]);
+
+        // A branch child pointing to a stem that is not stored
+        let mut missing = [0u8; 31];
+        missing[0] = 9;
+        trie.storage.add_stem_as_branch_child(vec![9], missing, 1);
+        let report = check_integrity(&trie.storage, &TestCommitter);
+        assert!(report.issues.contains(&Issue::MissingStem {
+            path: vec![],
+            stem: missing
+        }));
+        assert!(report.issues.contains(&Issue::OrphanStem { stem: orphan }));
+        assert_eq!(
+            report
+                .issues
+                .iter()
+                .filter(|issue| issue.is_dangling())
+                .count(),
+            1
+        );
+        assert_eq!(
+            report
+                .issues
+                .iter()
+                .filter(|issue| issue.is_orphan())
+                .count(),
+            1
+        );
+    }
+}
+
+macro_rules! fsck_test {
+    (
+        $module_name: ident;   // Module Name
+        $database_enum: ident;  // Database enum
+        $($function_name: ident),*  // list of functions to implement
+    ) => {
+        #[cfg(test)]
+        #[allow(non_snake_case)]
+        mod $module_name {
+            use super::*;
+
+            $(
+                #[test]
+                fn $function_name() {
+                    fsck_test_helper::$function_name(DatabaseScheme::$database_enum);
+                }
+            )*
+        }
+    };
+}
+
+fsck_test![
+    MemoryDBFsck;
+    MemoryDb;
+    fsck_clean_db
+];
+
+#[cfg(feature = "rocks_db")]
+fsck_test![
+    RocksDBFsck;
+    RocksDb;
+    fsck_clean_db
+];
+
+#[test]
+fn fsck_detects_corruption() {
+    fsck_test_helper::fsck_detects_corruption();
+}
@@ -0,0 +1,433 @@
+use rust_verkle::*;
+
+#[cfg(test)]
+mod handle_test_helper {
+    use rust_verkle::utils::{assert_value, str_to_cstr};
+    use rust_verkle::*;
+    use std::collections::BTreeMap;
+    use std::os::raw::c_void;
+    use std::slice;
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+    use tempfile::Builder;
+
+    const KEY: [u8; 32] = [7u8; 32];
+    const VALUE: [u8; 32] = [9u8; 32];
+
+    fn copy_scheme(db_scheme: &DatabaseScheme) -> DatabaseScheme {
+        match db_scheme {
+            DatabaseScheme::MemoryDb => DatabaseScheme::MemoryDb,
+            DatabaseScheme::RocksDb => DatabaseScheme::RocksDb,
+            DatabaseScheme::MemoryDbReadOnly => DatabaseScheme::MemoryDbReadOnly,
+            DatabaseScheme::RocksDbReadOnly => DatabaseScheme::RocksDbReadOnly,
+            DatabaseScheme::External => DatabaseScheme::External,
+            DatabaseScheme::Redb => DatabaseScheme::Redb,
+        }
+    }
+
+    // The ABI contract of the handles: which calls return what for an invalid handle, and the
+    // error reported for it
+    pub fn abi_version() {
+        assert_eq!(rust_verkle_abi_version(), RUST_VERKLE_ABI_VERSION);
+        assert_eq!(RUST_VERKLE_ABI_VERSION, 2);
+        assert_eq!(VerkleError::Ok as u32, 0);
+        assert_eq!(VerkleError::NullHandle as u32, 1);
+        assert_eq!(VerkleError::InvalidHandle as u32, 2);
+        assert_eq!(VerkleError::WrongHandleType as u32, 3);
+        assert_eq!(VerkleError::HandleInUse as u32, 4);
+        assert_eq!(VerkleError::UnsupportedSchema as u32, 5);
+        assert_eq!(VerkleError::StorageError as u32, 6);
+        assert_eq!(VerkleError::NullArgument as u32, 7);
+    }
+
+    pub fn null_handle(db_scheme: DatabaseScheme) {
+        let trie: *mut VerkleTrie = std::ptr::null_mut();
+        assert!(verkle_trie_get(trie, KEY.as_ptr()).is_null());
+        assert_eq!(verkle_last_error(), VerkleError::NullHandle);
+        assert!(create_read_only_verkle_db(std::ptr::null_mut()).is_null());
+        assert_eq!(verkle_last_error(), VerkleError::NullHandle);
+
+        // Freeing null is a no-op, as with free()
+        assert_eq!(verkle_trie_free(trie), 1);
+        assert_eq!(verkle_db_free(std::ptr::null_mut()), 1);
+
+        let dir = Builder::new().tempdir().unwrap();
+        let trie = verkle_trie_new(
+            db_scheme,
+            CommitScheme::TestCommitment,
+            str_to_cstr(dir.path().to_str().unwrap()),
+        );
+        assert!(!get_root_hash(trie).is_null());
+        assert_eq!(verkle_last_error(), VerkleError::Ok);
+        assert_eq!(verkle_trie_free(trie), 1);
+    }
+
+    // A null output buffer is reported as an error instead of being written to
+    pub fn null_argument(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let db = create_verkle_db(db_scheme, str_to_cstr(dir.path().to_str().unwrap()));
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        verkle_trie_insert(trie, KEY.as_ptr(), VALUE.as_ptr());
+        verkle_trie_flush(trie);
+
+        let mut block = 0u64;
+        let mut root = [0u8; 32];
+        assert_eq!(
+            get_last_flushed_block(db, std::ptr::null_mut(), root.as_mut_ptr()),
+            0
+        );
+        assert_eq!(verkle_last_error(), VerkleError::NullArgument);
+        assert_eq!(
+            get_last_flushed_block(db, &mut block, std::ptr::null_mut()),
+            0
+        );
+        assert_eq!(verkle_last_error(), VerkleError::NullArgument);
+
+        let iter = verkle_trie_iter_new(trie, std::ptr::null());
+        let mut key = [0u8; 32];
+        let mut value = [0u8; 32];
+        assert_eq!(
+            verkle_trie_iter_next(iter, std::ptr::null_mut(), value.as_mut_ptr()),
+            0
+        );
+        assert_eq!(verkle_last_error(), VerkleError::NullArgument);
+        // The leaf was not consumed
+        assert_eq!(
+            verkle_trie_iter_next(iter, key.as_mut_ptr(), value.as_mut_ptr()),
+            1
+        );
+        assert_eq!((key, value), (KEY, VALUE));
+        verkle_trie_iter_free(iter);
+
+        assert_eq!(verkle_trie_free(trie), 1);
+        assert_eq!(verkle_db_free(db), 1);
+    }
+
+    pub fn use_after_free(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let trie = verkle_trie_new(
+            copy_scheme(&db_scheme),
+            CommitScheme::TestCommitment,
+            str_to_cstr(path),
+        );
+        verkle_trie_insert(trie, KEY.as_ptr(), VALUE.as_ptr());
+        assert_value(verkle_trie_get(trie, KEY.as_ptr()), VALUE);
+
+        let iter = verkle_trie_iter_new(trie, std::ptr::null());
+        assert_eq!(verkle_trie_free(trie), 1);
+        assert_eq!(verkle_last_error(), VerkleError::Ok);
+
+        assert!(verkle_trie_get(trie, KEY.as_ptr()).is_null());
+        assert_eq!(verkle_last_error(), VerkleError::InvalidHandle);
+        verkle_trie_insert(trie, KEY.as_ptr(), VALUE.as_ptr());
+        assert_eq!(verkle_last_error(), VerkleError::InvalidHandle);
+        assert!(get_verkle_proof(trie, KEY.as_ptr()).is_null());
+        assert_eq!(verkle_last_error(), VerkleError::InvalidHandle);
+        assert_eq!(verkle_trie_free(trie), 0);
+        assert_eq!(verkle_last_error(), VerkleError::InvalidHandle);
+
+        // The iterator outlives its trie, it reports the trie as gone
+        let mut key = [0u8; 32];
+        let mut value = [0u8; 32];
+        assert_eq!(
+            verkle_trie_iter_next(iter, key.as_mut_ptr(), value.as_mut_ptr()),
+            0
+        );
+        assert_eq!(verkle_last_error(), VerkleError::InvalidHandle);
+        verkle_trie_iter_free(iter);
+        assert_eq!(verkle_last_error(), VerkleError::Ok);
+        verkle_trie_iter_free(iter);
+        assert_eq!(verkle_last_error(), VerkleError::InvalidHandle);
+
+        // Handles are never reused, a new trie does not revive the freed one
+        let other_dir = Builder::new().tempdir().unwrap();
+        let other = verkle_trie_new(
+            db_scheme,
+            CommitScheme::TestCommitment,
+            str_to_cstr(other_dir.path().to_str().unwrap()),
+        );
+        assert_ne!(other, trie);
+        assert!(verkle_trie_get(trie, KEY.as_ptr()).is_null());
+        assert_eq!(verkle_last_error(), VerkleError::InvalidHandle);
+        assert_eq!(verkle_trie_free(other), 1);
+    }
+
+    pub fn wrong_handle_type(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let db = create_verkle_db(db_scheme, str_to_cstr(dir.path().to_str().unwrap()));
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        let iter = verkle_trie_iter_new(trie, std::ptr::null());
+
+        assert!(get_root_hash(db as *mut VerkleTrie).is_null());
+        assert_eq!(verkle_last_error(), VerkleError::WrongHandleType);
+        assert!(get_root_hash(iter as *mut VerkleTrie).is_null());
+        assert_eq!(verkle_last_error(), VerkleError::WrongHandleType);
+        assert!(create_trie_from_db(CommitScheme::TestCommitment, trie as *mut Database).is_null());
+        assert_eq!(verkle_last_error(), VerkleError::WrongHandleType);
+
+        // A handle of the wrong type is not freed
+        assert_eq!(verkle_trie_free(db as *mut VerkleTrie), 0);
+        assert_eq!(verkle_last_error(), VerkleError::WrongHandleType);
+        assert_eq!(verkle_db_free(trie as *mut Database), 0);
+        assert_eq!(verkle_last_error(), VerkleError::WrongHandleType);
+        assert!(!get_root_hash(trie).is_null());
+
+        verkle_trie_iter_free(iter);
+        assert_eq!(verkle_trie_free(trie), 1);
+        assert_eq!(verkle_db_free(db), 1);
+    }
+
+    // A database is freed only once nothing created from it is alive
+    pub fn db_in_use(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = create_verkle_db(copy_scheme(&db_scheme), str_to_cstr(path));
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        verkle_trie_insert(trie, KEY.as_ptr(), VALUE.as_ptr());
+        verkle_trie_flush(trie);
+
+        let read_only_db = create_read_only_verkle_db(db);
+        let read_only_trie = create_trie_from_db(CommitScheme::TestCommitment, read_only_db);
+        assert_value(verkle_trie_get(read_only_trie, KEY.as_ptr()), VALUE);
+
+        assert_eq!(verkle_db_free(db), 0);
+        assert_eq!(verkle_last_error(), VerkleError::HandleInUse);
+        assert_eq!(verkle_trie_free(trie), 1);
+        assert_eq!(verkle_db_free(db), 0);
+        assert_eq!(verkle_last_error(), VerkleError::HandleInUse);
+        assert_eq!(verkle_db_free(read_only_db), 0);
+        assert_eq!(verkle_last_error(), VerkleError::HandleInUse);
+
+        assert_eq!(verkle_trie_free(read_only_trie), 1);
+        assert_eq!(verkle_db_free(read_only_db), 1);
+        assert_eq!(verkle_db_free(db), 1);
+        assert!(verkle_trie_get(read_only_trie, KEY.as_ptr()).is_null());
+        assert_eq!(verkle_last_error(), VerkleError::InvalidHandle);
+
+        if let DatabaseScheme::RocksDb | DatabaseScheme::Redb = db_scheme {
+            // The database was closed, so it can be opened again
+            let db = create_verkle_db(copy_scheme(&db_scheme), str_to_cstr(path));
+            let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+            assert_value(verkle_trie_get(trie, KEY.as_ptr()), VALUE);
+            assert_eq!(verkle_trie_free(trie), 1);
+            assert_eq!(verkle_db_free(db), 1);
+        }
+    }
+
+    // Host store whose first write signals that it started and then waits to be resumed
+    struct BlockingHost {
+        pairs: Mutex<BTreeMap<Vec<u8>, Vec<u8>>>,
+        first_write: Mutex<Option<(Sender<()>, Receiver<()>)>>,
+    }
+
+    fn host<'a>(context: *mut c_void) -> &'a BlockingHost {
+        unsafe { &*(context as *const BlockingHost) }
+    }
+
+    fn bytes<'a>(ptr: *const u8, len: usize) -> &'a [u8] {
+        if len == 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(ptr, len) }
+    }
+
+    extern "C" fn get(
+        context: *mut c_void,
+        key: *const u8,
+        key_len: usize,
+        sink: *mut c_void,
+        emit: ExternalSink,
+    ) {
+        let pairs = host(context).pairs.lock().unwrap();
+        if let Some(value) = pairs.get(bytes(key, key_len)) {
+            emit(sink, std::ptr::null(), 0, value.as_ptr(), value.len());
+        }
+    }
+
+    extern "C" fn put_batch(
+        context: *mut c_void,
+        keys: *const Proof,
+        values: *const Proof,
+        len: usize,
+    ) -> u8 {
+        let host = host(context);
+        if let Some((started, resume)) = host.first_write.lock().unwrap().take() {
+            started.send(()).unwrap();
+            resume.recv().unwrap();
+        }
+        let keys = unsafe { slice::from_raw_parts(keys, len) };
+        let values = unsafe { slice::from_raw_parts(values, len) };
+        let mut pairs = host.pairs.lock().unwrap();
+        for (key, value) in keys.iter().zip(values.iter()) {
+            pairs.insert(
+                bytes(key.ptr, key.len).to_vec(),
+                bytes(value.ptr, value.len).to_vec(),
+            );
+        }
+        1
+    }
+
+    extern "C" fn iterate_prefix(
+        context: *mut c_void,
+        prefix: *const u8,
+        prefix_len: usize,
+        sink: *mut c_void,
+        emit: ExternalSink,
+    ) {
+        let prefix = bytes(prefix, prefix_len);
+        let pairs = host(context).pairs.lock().unwrap();
+        for (key, value) in pairs.range(prefix.to_vec()..) {
+            if !key.starts_with(prefix) {
+                break;
+            }
+            emit(sink, key.as_ptr(), key.len(), value.as_ptr(), value.len());
+        }
+    }
+
+    extern "C" fn flush(_context: *mut c_void) -> u8 {
+        1
+    }
+
+    // A trie freed by one thread while another thread is inside a call on it stays alive until
+    // the call returns. The flush is held inside the host store until the free was tried
+    pub fn free_while_in_use() {
+        let (started_tx, started_rx) = mpsc::channel();
+        let (resume_tx, resume_rx) = mpsc::channel();
+        let host = Box::leak(Box::new(BlockingHost {
+            pairs: Mutex::new(BTreeMap::new()),
+            first_write: Mutex::new(Some((started_tx, resume_rx))),
+        }));
+        let store = ExternalStore {
+            context: host as *mut BlockingHost as *mut c_void,
+            get: Some(get),
+            put_batch: Some(put_batch),
+            iterate_prefix: Some(iterate_prefix),
+            flush: Some(flush),
+            release: None,
+        };
+        let db = create_external_verkle_db(&store);
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        verkle_trie_insert(trie, KEY.as_ptr(), VALUE.as_ptr());
+
+        // Handles are plain ids, they are sent to the other thread as such
+        let trie_id = trie as usize;
+        let worker = thread::spawn(move || verkle_trie_flush(trie_id as *mut VerkleTrie));
+        started_rx.recv().unwrap();
+
+        assert_eq!(verkle_trie_free(trie), 0);
+        assert_eq!(verkle_last_error(), VerkleError::HandleInUse);
+        assert_eq!(verkle_db_free(db), 0);
+        assert_eq!(verkle_last_error(), VerkleError::HandleInUse);
+
+        resume_tx.send(()).unwrap();
+        assert_eq!(worker.join().unwrap(), 1);
+        assert_value(verkle_trie_get(trie, KEY.as_ptr()), VALUE);
+
+        assert_eq!(verkle_trie_free(trie), 1);
+        assert!(verkle_trie_get(trie, KEY.as_ptr()).is_null());
+        assert_eq!(verkle_last_error(), VerkleError::InvalidHandle);
+        assert_eq!(verkle_db_free(db), 1);
+        assert!(!host.pairs.lock().unwrap().is_empty());
+    }
+
+    // A call on a database waits for a call on a trie created from it. The flush of the trie
+    // is held inside the host store while the database is used by another thread
+    pub fn db_waits_for_trie() {
+        let (started_tx, started_rx) = mpsc::channel();
+        let (resume_tx, resume_rx) = mpsc::channel();
+        let host = Box::leak(Box::new(BlockingHost {
+            pairs: Mutex::new(BTreeMap::new()),
+            first_write: Mutex::new(Some((started_tx, resume_rx))),
+        }));
+        let store = ExternalStore {
+            context: host as *mut BlockingHost as *mut c_void,
+            get: Some(get),
+            put_batch: Some(put_batch),
+            iterate_prefix: Some(iterate_prefix),
+            flush: Some(flush),
+            release: None,
+        };
+        let db = create_external_verkle_db(&store);
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        verkle_trie_insert(trie, KEY.as_ptr(), VALUE.as_ptr());
+
+        let trie_id = trie as usize;
+        let flusher = thread::spawn(move || verkle_trie_flush(trie_id as *mut VerkleTrie));
+        started_rx.recv().unwrap();
+
+        let db_id = db as usize;
+        let (done_tx, done_rx) = mpsc::channel();
+        let reader = thread::spawn(move || {
+            let mut block = 0u64;
+            let mut root = [0u8; 32];
+            get_last_flushed_block(db_id as *mut Database, &mut block, root.as_mut_ptr());
+            done_tx.send(()).unwrap();
+        });
+        thread::sleep(Duration::from_millis(100));
+        assert!(done_rx.try_recv().is_err());
+
+        resume_tx.send(()).unwrap();
+        assert_eq!(flusher.join().unwrap(), 1);
+        reader.join().unwrap();
+        done_rx.recv().unwrap();
+
+        assert_eq!(verkle_trie_free(trie), 1);
+        assert_eq!(verkle_db_free(db), 1);
+    }
+}
+
+#[test]
+fn abi_version() {
+    handle_test_helper::abi_version();
+}
+
+#[test]
+fn free_while_in_use() {
+    handle_test_helper::free_while_in_use();
+}
+
+#[test]
+fn db_waits_for_trie() {
+    handle_test_helper::db_waits_for_trie();
+}
+
+macro_rules! handle_test {
+    ($name:ident, $db_scheme:expr) => {
+        mod $name {
+            use super::*;
+
+            #[test]
+            fn null_handle() {
+                handle_test_helper::null_handle($db_scheme);
+            }
+
+            #[test]
+            fn null_argument() {
+                handle_test_helper::null_argument($db_scheme);
+            }
+
+            #[test]
+            fn use_after_free() {
+                handle_test_helper::use_after_free($db_scheme);
+            }
+
+            #[test]
+            fn wrong_handle_type() {
+                handle_test_helper::wrong_handle_type($db_scheme);
+            }
+
+            #[test]
+            fn db_in_use() {
+                handle_test_helper::db_in_use($db_scheme);
+            }
+        }
+    };
+}
+
+handle_test!(memory_db, DatabaseScheme::MemoryDb);
+#[cfg(feature = "rocks_db")]
+handle_test!(rocks_db, DatabaseScheme::RocksDb);
+handle_test!(redb, DatabaseScheme::Redb);
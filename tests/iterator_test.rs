@@ -0,0 +1,158 @@
+use rust_verkle::*;
+
+#[cfg(test)]
+mod iterator_test_helper {
+    use rust_verkle::utils::str_to_cstr;
+    use rust_verkle::*;
+    use tempfile::Builder;
+
+    fn leaves() -> Vec<([u8; 32], [u8; 32])> {
+        let mut leaves = Vec::new();
+        for i in [200u8, 3, 77, 3, 150] {
+            for suffix in [9u8, 0, 255] {
+                let mut key = [i; 32];
+                key[1] = suffix;
+                key[31] = suffix;
+                leaves.push((key, [suffix.wrapping_add(i); 32]));
+            }
+        }
+        leaves.sort();
+        leaves.dedup();
+        leaves
+    }
+
+    fn collect(iter: *mut TrieIterator) -> Vec<([u8; 32], [u8; 32])> {
+        let mut items = Vec::new();
+        let mut key = [0u8; 32];
+        let mut value = [0u8; 32];
+        while verkle_trie_iter_next(iter, key.as_mut_ptr(), value.as_mut_ptr()) == 1 {
+            items.push((key, value));
+        }
+        verkle_trie_iter_free(iter);
+        items
+    }
+
+    fn insert_leaves(trie: *mut VerkleTrie, leaves: &[([u8; 32], [u8; 32])]) {
+        let keys: Vec<_> = leaves.iter().map(|(key, _)| *key).collect();
+        let vals: Vec<_> = leaves.iter().map(|(_, val)| *val).collect();
+        verkle_trie_insert_multiple(trie, keys.as_ptr(), vals.as_ptr(), keys.len());
+    }
+
+    pub fn iterate_in_key_order(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = create_verkle_db(db_scheme, str_to_cstr(path));
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+
+        let all = leaves();
+        // Half of the leaves end up in storage, the other half stays in the batch
+        let (flushed, pending) = all.split_at(all.len() / 2);
+        insert_leaves(trie, flushed);
+        verkle_trie_flush(trie);
+        insert_leaves(trie, pending);
+
+        assert_eq!(collect(verkle_trie_iter_new(trie, std::ptr::null())), all);
+
+        let start = all[4].0;
+        let from_start = collect(verkle_trie_iter_new(trie, start.as_ptr()));
+        assert_eq!(from_start, all[4..].to_vec());
+
+        // A start key that is not in the trie starts at the next key
+        let mut between = all[4].0;
+        between[31] = between[31].wrapping_add(1);
+        let from_between = collect(verkle_trie_iter_new(trie, between.as_ptr()));
+        assert_eq!(from_between, all[5..].to_vec());
+
+        let end = [0xffu8; 32];
+        assert!(collect(verkle_trie_iter_new(trie, end.as_ptr())).is_empty());
+    }
+
+    pub fn iterate_flushed_db(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = create_verkle_db(db_scheme, str_to_cstr(path));
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+
+        let all = leaves();
+        insert_leaves(trie, &all);
+        verkle_trie_flush(trie);
+
+        // The cache of a new trie is empty, everything has to come from storage
+        let trie_2 = create_trie_from_db(CommitScheme::TestCommitment, db);
+        assert_eq!(collect(verkle_trie_iter_new(trie_2, std::ptr::null())), all);
+    }
+
+    pub fn iterate_read_only_overlay(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = create_verkle_db(db_scheme, str_to_cstr(path));
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+
+        let all = leaves();
+        insert_leaves(trie, &all);
+        verkle_trie_flush(trie);
+
+        let ro_db = create_read_only_verkle_db(db);
+        let ro_trie = create_trie_from_db(CommitScheme::TestCommitment, ro_db);
+
+        // Overwrite an existing leaf and add a new one in the overlay
+        let new_leaf = ([100u8; 32], [1u8; 32]);
+        let updated_leaf = (all[0].0, [42u8; 32]);
+        insert_leaves(ro_trie, &[new_leaf, updated_leaf]);
+        verkle_trie_flush(ro_trie);
+
+        let mut expected = all.clone();
+        expected[0] = updated_leaf;
+        expected.push(new_leaf);
+        expected.sort();
+        assert_eq!(
+            collect(verkle_trie_iter_new(ro_trie, std::ptr::null())),
+            expected
+        );
+
+        // The base trie does not see the overlay
+        assert_eq!(collect(verkle_trie_iter_new(trie, std::ptr::null())), all);
+
+        clear_temp_changes_read_only_db(ro_db);
+        verkle_trie_clear(ro_trie);
+        assert_eq!(collect(verkle_trie_iter_new(ro_trie, std::ptr::null())), all);
+    }
+}
+
+macro_rules! iterator_test {
+    (
+        $module_name: ident;   // Module Name
+        $database_enum: ident;  // Database enum
+        $($function_name: ident),*  // list of functions to implement
+    ) => {
+        #[cfg(test)]
+        #[allow(non_snake_case)]
+        mod $module_name {
+            use super::*;
+
+            $(
+                #[test]
+                fn $function_name() {
+                    iterator_test_helper::$function_name(DatabaseScheme::$database_enum);
+                }
+            )*
+        }
+    };
+}
+
+iterator_test![
+    MemoryDBIterator;
+    MemoryDb;
+    iterate_in_key_order,
+    iterate_flushed_db,
+    iterate_read_only_overlay
+];
+
+#[cfg(feature = "rocks_db")]
+iterator_test![
+    RocksDBIterator;
+    RocksDb;
+    iterate_in_key_order,
+    iterate_flushed_db,
+    iterate_read_only_overlay
+];
@@ -0,0 +1,192 @@
+use rust_verkle::*;
+
+#[cfg(test)]
+mod namespace_test_helper {
+    use rust_verkle::utils::{assert_value, str_to_cstr};
+    use rust_verkle::*;
+    use tempfile::Builder;
+
+    const NAMES: [&[u8]; 2] = [b"shard_a", b"shard_b"];
+
+    fn leaf(i: u8, namespace: u8) -> ([u8; 32], [u8; 32]) {
+        let mut key = [i; 32];
+        key[31] = i % 3;
+        (key, [i.wrapping_add(namespace); 32])
+    }
+
+    fn namespace(db: *mut Database, name: &[u8]) -> *mut Database {
+        create_namespaced_verkle_db(db, name.as_ptr(), name.len())
+    }
+
+    fn root_hash(trie: *mut VerkleTrie) -> [u8; 32] {
+        unsafe { *(get_root_hash(trie) as *const [u8; 32]) }
+    }
+
+    // Root of the trie of the leaves of `namespace`, flushed as block `namespace`
+    fn write_leaves(db: *mut Database, namespace: u8) -> [u8; 32] {
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        for i in 0..30u8 {
+            let (key, value) = leaf(i, namespace);
+            verkle_trie_insert(trie, key.as_ptr(), value.as_ptr());
+        }
+        let root = root_hash(trie);
+        assert_eq!(verkle_trie_flush_block(trie, namespace as u64), 1);
+        assert_eq!(verkle_trie_free(trie), 1);
+        root
+    }
+
+    fn assert_leaves(db: *mut Database, namespace: u8, root: [u8; 32]) {
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        assert_value(get_root_hash(trie), root);
+        for i in 0..30u8 {
+            let (key, value) = leaf(i, namespace);
+            assert_value(verkle_trie_get(trie, key.as_ptr()), value);
+        }
+        assert_eq!(verkle_trie_free(trie), 1);
+
+        let mut block = 0u64;
+        let mut recorded_root = [0u8; 32];
+        assert_eq!(
+            get_last_flushed_block(db, &mut block, recorded_root.as_mut_ptr()),
+            1
+        );
+        assert_eq!(block, namespace as u64);
+
+        let report = unsafe { &*check_db_integrity(CommitScheme::TestCommitment, db) };
+        assert_eq!(report.leaves, 30);
+        assert_eq!(
+            (report.mismatched, report.dangling, report.orphans),
+            (0, 0, 0)
+        );
+    }
+
+    fn copy_scheme(db_scheme: &DatabaseScheme) -> DatabaseScheme {
+        match db_scheme {
+            DatabaseScheme::RocksDb => DatabaseScheme::RocksDb,
+            DatabaseScheme::Redb => DatabaseScheme::Redb,
+            _ => unreachable!(),
+        }
+    }
+
+    // Tries on the same keys in the database and in two of its namespaces do not see each
+    // other, before and after the database is reopened
+    pub fn isolated_tries(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = create_verkle_db(copy_scheme(&db_scheme), str_to_cstr(path));
+        let mut roots = vec![write_leaves(db, 0)];
+        for (i, name) in NAMES.iter().enumerate() {
+            let namespace_db = namespace(db, name);
+            assert!(!namespace_db.is_null());
+            roots.push(write_leaves(namespace_db, i as u8 + 1));
+            assert_eq!(verkle_db_free(namespace_db), 1);
+        }
+        assert_ne!(roots[1], roots[0]);
+        assert_ne!(roots[2], roots[1]);
+        assert_eq!(verkle_db_free(db), 1);
+
+        let db = create_verkle_db(db_scheme, str_to_cstr(path));
+        assert_leaves(db, 0, roots[0]);
+        for (i, name) in NAMES.iter().enumerate() {
+            let namespace_db = namespace(db, name);
+            assert_leaves(namespace_db, i as u8 + 1, roots[i + 1]);
+            assert_eq!(verkle_db_free(namespace_db), 1);
+        }
+        assert_eq!(verkle_db_free(db), 1);
+    }
+
+    // A namespace nested in another one, or whose name starts with the name of another one,
+    // is a namespace of its own
+    pub fn nested_namespaces(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let db = create_verkle_db(db_scheme, str_to_cstr(dir.path().to_str().unwrap()));
+        let outer_db = namespace(db, b"shard");
+        let nested_db = namespace(outer_db, b"shard");
+        let longer_db = namespace(db, b"shard_a");
+        let outer_root = write_leaves(outer_db, 1);
+
+        for db in [db, nested_db, longer_db] {
+            let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+            let (key, _) = leaf(0, 1);
+            assert!(verkle_trie_get(trie, key.as_ptr()).is_null());
+            assert_eq!(verkle_trie_free(trie), 1);
+        }
+        assert_leaves(outer_db, 1, outer_root);
+
+        // A namespace borrows the database it was opened in
+        assert_eq!(verkle_db_free(outer_db), 0);
+        assert_eq!(verkle_last_error(), VerkleError::HandleInUse);
+        assert_eq!(verkle_db_free(nested_db), 1);
+        assert_eq!(verkle_db_free(outer_db), 1);
+        assert_eq!(verkle_db_free(longer_db), 1);
+        assert_eq!(verkle_db_free(db), 1);
+    }
+
+    // The changes of an overlay over a namespace are committed into that namespace only
+    pub fn read_only_overlay(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let db = create_verkle_db(db_scheme, str_to_cstr(dir.path().to_str().unwrap()));
+        let namespace_db = namespace(db, NAMES[0]);
+        let ro_db = create_read_only_verkle_db(namespace_db);
+        let ro_root = write_leaves(ro_db, 1);
+
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, namespace_db);
+        let (key, value) = leaf(0, 1);
+        assert!(verkle_trie_get(trie, key.as_ptr()).is_null());
+        assert_eq!(verkle_trie_free(trie), 1);
+        assert_eq!(commit_temp_changes_read_only_db(ro_db), 1);
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, namespace_db);
+        assert_value(verkle_trie_get(trie, key.as_ptr()), value);
+        assert_value(get_root_hash(trie), ro_root);
+
+        let db_trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        assert!(verkle_trie_get(db_trie, key.as_ptr()).is_null());
+    }
+
+    pub fn invalid_namespaces(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let db = create_verkle_db(db_scheme, str_to_cstr(dir.path().to_str().unwrap()));
+        assert!(create_namespaced_verkle_db(db, std::ptr::null(), 0).is_null());
+        assert!(namespace(db, &[7u8; 256]).is_null());
+        assert!(!namespace(db, &[7u8; 255]).is_null());
+
+        // Namespaces are only opened in RocksDB and redb databases
+        let ro_db = create_read_only_verkle_db(db);
+        assert!(namespace(ro_db, NAMES[0]).is_null());
+        let memory_db = create_verkle_db(DatabaseScheme::MemoryDb, str_to_cstr(""));
+        assert!(namespace(memory_db, NAMES[0]).is_null());
+    }
+}
+
+macro_rules! namespace_test {
+    ($module_name:ident, $db_scheme:ident) => {
+        #[cfg(test)]
+        mod $module_name {
+            use super::*;
+
+            #[test]
+            fn isolated_tries() {
+                namespace_test_helper::isolated_tries(DatabaseScheme::$db_scheme);
+            }
+
+            #[test]
+            fn nested_namespaces() {
+                namespace_test_helper::nested_namespaces(DatabaseScheme::$db_scheme);
+            }
+
+            #[test]
+            fn read_only_overlay() {
+                namespace_test_helper::read_only_overlay(DatabaseScheme::$db_scheme);
+            }
+
+            #[test]
+            fn invalid_namespaces() {
+                namespace_test_helper::invalid_namespaces(DatabaseScheme::$db_scheme);
+            }
+        }
+    };
+}
+
+#[cfg(feature = "rocks_db")]
+namespace_test!(rocksdb, RocksDb);
+namespace_test!(redb, Redb);
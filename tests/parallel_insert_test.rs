@@ -0,0 +1,168 @@
+use rust_verkle::*;
+
+#[cfg(test)]
+mod parallel_insert_test_helper {
+    use rust_verkle::utils::{assert_value, get_boxed_value};
+    use rust_verkle::*;
+    use std::slice;
+
+    // Keys spread over the trie with a few leaves per stem, plus groups of stems that share
+    // long prefixes so that inserting them splits existing slots into new branches
+    fn first_batch() -> Vec<([u8; 32], [u8; 32])> {
+        let mut leaves = Vec::new();
+        for i in 0..1500u64 {
+            let mut key = [0u8; 32];
+            key[..8].copy_from_slice(&(i / 3).wrapping_mul(0x9e37_79b9_7f4a_7c15).to_be_bytes());
+            key[31] = (i % 3) as u8;
+            leaves.push((key, [(i % 251) as u8; 32]));
+        }
+        for i in 0..20u8 {
+            let mut key = [7u8; 32];
+            key[10] = i;
+            leaves.push((key, [i; 32]));
+        }
+        leaves
+    }
+
+    // Overwrites part of the first batch and adds stems next to existing ones
+    fn second_batch() -> Vec<([u8; 32], [u8; 32])> {
+        let mut leaves: Vec<_> = first_batch()
+            .into_iter()
+            .step_by(5)
+            .map(|(key, value)| (key, [value[0].wrapping_add(1); 32]))
+            .collect();
+        for (key, _) in first_batch().into_iter().step_by(50) {
+            let mut sibling = key;
+            sibling[30] ^= 0x80;
+            leaves.push((sibling, [3u8; 32]));
+        }
+        // The same key twice, the last value wins
+        leaves.push(([9u8; 32], [1u8; 32]));
+        leaves.push(([9u8; 32], [2u8; 32]));
+        leaves
+    }
+
+    fn insert(trie: *mut VerkleTrie, leaves: &[([u8; 32], [u8; 32])], threshold: usize) {
+        let keys: Vec<_> = leaves.iter().map(|(key, _)| *key).collect();
+        let vals: Vec<_> = leaves.iter().map(|(_, val)| *val).collect();
+        verkle_trie_insert_multiple_with_threshold(
+            trie,
+            keys.as_ptr(),
+            vals.as_ptr(),
+            keys.len(),
+            threshold,
+        );
+    }
+
+    fn root_commitment(trie: *mut VerkleTrie) -> Vec<u8> {
+        let commitment = unsafe { &*get_root_commitment(trie) };
+        unsafe { slice::from_raw_parts(commitment.ptr, commitment.len) }.to_vec()
+    }
+
+    pub fn parallel_matches_sequential(sequential: *mut VerkleTrie, parallel: *mut VerkleTrie) {
+        insert(sequential, &first_batch(), usize::MAX);
+        insert(parallel, &first_batch(), 0);
+        assert_eq!(root_commitment(sequential), root_commitment(parallel));
+
+        insert(sequential, &second_batch(), usize::MAX);
+        insert(parallel, &second_batch(), 0);
+        assert_eq!(root_commitment(sequential), root_commitment(parallel));
+
+        let value = verkle_trie_get(parallel, get_boxed_value([9u8; 32]));
+        assert_value(value, [2u8; 32]);
+    }
+
+    pub fn parallel_then_sequential(sequential: *mut VerkleTrie, mixed: *mut VerkleTrie) {
+        insert(sequential, &first_batch(), usize::MAX);
+        insert(sequential, &second_batch(), usize::MAX);
+
+        // Sequential inserts continue from the nodes written by a parallel insert
+        insert(mixed, &first_batch(), 0);
+        verkle_trie_flush(mixed);
+        insert(mixed, &second_batch(), usize::MAX);
+        assert_eq!(root_commitment(sequential), root_commitment(mixed));
+
+        let keys: Vec<_> = second_batch().iter().map(|(key, _)| *key).take(8).collect();
+        let vals: Vec<_> = second_batch().iter().map(|(_, val)| *val).take(8).collect();
+        let _proof = get_verkle_proof_multiple(mixed, keys.as_ptr(), keys.len());
+        let proof = unsafe { &*_proof };
+        let verification = verify_verkle_proof_multiple(
+            mixed,
+            proof.ptr,
+            proof.len,
+            keys.as_ptr(),
+            vals.as_ptr(),
+            keys.len(),
+        );
+        assert_eq!(verification, 1);
+    }
+}
+
+macro_rules! parallel_insert_test {
+    (
+        $module_name: ident;   // Module Name
+        $database_enum: ident;  // Database enum
+        $commit_enum: ident; // Commit enum
+        $($function_name: ident),*  // list of functions to implement
+    ) => {
+        #[cfg(test)]
+        #[allow(non_snake_case)]
+        mod $module_name {
+            use super::*;
+            use tempfile::Builder;
+
+            $(
+                #[test]
+                fn $function_name() {
+                    let dir_1 = Builder::new().tempdir().unwrap();
+                    let dir_2 = Builder::new().tempdir().unwrap();
+                    let trie_1 = verkle_trie_new(
+                        DatabaseScheme::$database_enum,
+                        CommitScheme::$commit_enum,
+                        utils::str_to_cstr(dir_1.path().to_str().unwrap()),
+                    );
+                    let trie_2 = verkle_trie_new(
+                        DatabaseScheme::$database_enum,
+                        CommitScheme::$commit_enum,
+                        utils::str_to_cstr(dir_2.path().to_str().unwrap()),
+                    );
+                    parallel_insert_test_helper::$function_name(trie_1, trie_2);
+                }
+            )*
+        }
+    };
+}
+
+parallel_insert_test![
+    MemoryTest;
+    MemoryDb;
+    TestCommitment;
+    parallel_matches_sequential,
+    parallel_then_sequential
+];
+
+parallel_insert_test![
+    MemoryPrelagrange;
+    MemoryDb;
+    PrecomputeLagrange;
+    parallel_matches_sequential,
+    parallel_then_sequential
+];
+
+#[cfg(feature = "rocks_db")]
+parallel_insert_test![
+    RocksdbTest;
+    RocksDb;
+    TestCommitment;
+    parallel_matches_sequential,
+    parallel_then_sequential
+];
+
+#[cfg(feature = "rocks_db")]
+parallel_insert_test![
+    RocksdbPrelagrange;
+    RocksDb;
+    PrecomputeLagrange;
+    parallel_matches_sequential,
+    parallel_then_sequential
+];
@@ -0,0 +1,189 @@
+use rust_verkle::*;
+
+#[cfg(test)]
+mod proof_json_test_helper {
+    use rust_verkle::proof::json::{decode_verkle_proof, encode_verkle_proof, VerkleProofJson};
+    use rust_verkle::*;
+    use std::slice;
+
+    const _ONE: [u8; 32] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 1,
+    ];
+    const _ONE32: [u8; 32] = [1; 32];
+
+    const TREE_KEY_VERSION: [u8; 32] = [
+        121, 85, 7, 198, 131, 230, 143, 90, 165, 129, 173, 81, 186, 89, 19, 191, 13, 107, 197, 120,
+        243, 229, 224, 183, 72, 25, 6, 8, 210, 159, 31, 0,
+    ];
+
+    const VALUE_2: [u8; 32] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 2,
+    ];
+
+    fn proof_to_vec(proof: *mut Proof) -> Vec<u8> {
+        let proof = unsafe { &*proof };
+        unsafe { slice::from_raw_parts(proof.ptr, proof.len) }.to_vec()
+    }
+
+    pub fn json_proof_round_trip(trie: *mut VerkleTrie) {
+        let all_keys = vec![_ONE, _ONE32, TREE_KEY_VERSION];
+        let all_vals = vec![_ONE32, _ONE, VALUE_2];
+        verkle_trie_insert_multiple(trie, all_keys.as_ptr(), all_vals.as_ptr(), all_keys.len());
+
+        let _proof = get_verkle_proof_json(trie, all_keys.as_ptr(), all_keys.len());
+        let json_bytes = proof_to_vec(_proof);
+        let json = String::from_utf8(json_bytes.clone()).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        for field in [
+            "otherStems",
+            "depthExtensionPresent",
+            "commitmentsByPath",
+            "d",
+            "ipaProof",
+        ] {
+            assert!(value.get(field).is_some(), "missing field {}", field);
+        }
+        let ipa_proof = value.get("ipaProof").unwrap();
+        assert_eq!(ipa_proof["cl"].as_array().unwrap().len(), 8);
+        assert_eq!(ipa_proof["cr"].as_array().unwrap().len(), 8);
+        assert!(ipa_proof["finalEvaluation"]
+            .as_str()
+            .unwrap()
+            .starts_with("0x"));
+
+        let proof = decode_verkle_proof(&json).unwrap();
+        assert_eq!(encode_verkle_proof(&proof), json);
+
+        let verification = verify_verkle_proof_json(
+            trie,
+            json_bytes.as_ptr(),
+            json_bytes.len(),
+            all_keys.as_ptr(),
+            all_vals.as_ptr(),
+            all_keys.len(),
+        );
+        assert_eq!(verification, 1);
+    }
+
+    pub fn json_proof_to_multiple_proof(trie: *mut VerkleTrie) {
+        let all_keys = vec![_ONE, _ONE32];
+        let all_vals = vec![_ONE32, _ONE];
+        verkle_trie_insert_multiple(trie, all_keys.as_ptr(), all_vals.as_ptr(), all_keys.len());
+
+        let _proof = get_verkle_proof_multiple(trie, all_keys.as_ptr(), all_keys.len());
+        let raw_bytes = proof_to_vec(_proof);
+
+        let _json = verkle_proof_to_json(raw_bytes.as_ptr(), raw_bytes.len());
+        assert!(!_json.is_null());
+        let json_bytes = proof_to_vec(_json);
+
+        let _parsed = verkle_proof_from_json(json_bytes.as_ptr(), json_bytes.len());
+        assert!(!_parsed.is_null());
+        let parsed = unsafe { &*_parsed };
+        let verification = verify_verkle_proof_multiple(
+            trie,
+            parsed.ptr,
+            parsed.len,
+            all_keys.as_ptr(),
+            all_vals.as_ptr(),
+            all_keys.len(),
+        );
+        assert_eq!(verification, 1);
+    }
+
+    pub fn json_proof_rejects_bad_input(trie: *mut VerkleTrie) {
+        let all_keys = vec![_ONE];
+        let all_vals = vec![_ONE32];
+        verkle_trie_insert_multiple(trie, all_keys.as_ptr(), all_vals.as_ptr(), all_keys.len());
+
+        let not_json = b"{\"d\": ";
+        assert!(verkle_proof_from_json(not_json.as_ptr(), not_json.len()).is_null());
+
+        let _proof = get_verkle_proof_json(trie, all_keys.as_ptr(), all_keys.len());
+        let json = String::from_utf8(proof_to_vec(_proof)).unwrap();
+        let mut proof: VerkleProofJson = serde_json::from_str(&json).unwrap();
+        proof.d = "0x1234".to_string();
+        let bad_json = serde_json::to_string(&proof).unwrap();
+        assert!(decode_verkle_proof(&bad_json).is_err());
+
+        // Malformed shapes are rejected when decoding, before any verification
+        let proof: VerkleProofJson = serde_json::from_str(&json).unwrap();
+        let mut short_ipa_proof = proof.clone();
+        short_ipa_proof.ipa_proof.cl.pop();
+        let mut long_ipa_proof = proof.clone();
+        long_ipa_proof.ipa_proof.cr.push(proof.d.clone());
+        // The only key is present, so no other stem can be part of the proof
+        let mut other_stem = proof.clone();
+        other_stem
+            .other_stems
+            .push(format!("0x{}", "22".repeat(31)));
+        let mut no_stems = proof;
+        no_stems.depth_extension_present = "0x".to_string();
+        for bad_proof in [short_ipa_proof, long_ipa_proof, other_stem, no_stems] {
+            let bad_json = serde_json::to_string(&bad_proof).unwrap();
+            assert!(decode_verkle_proof(&bad_json).is_err());
+        }
+
+        let verification = verify_verkle_proof_json(
+            trie,
+            bad_json.as_ptr(),
+            bad_json.len(),
+            all_keys.as_ptr(),
+            all_vals.as_ptr(),
+            all_keys.len(),
+        );
+        assert_eq!(verification, 0);
+    }
+}
+
+macro_rules! proof_json_test {
+    (
+        $module_name: ident;   // Module Name
+        $database_enum: ident;  // Database enum
+        $commit_enum: ident; // Commit enum
+        $($function_name: ident),*  // list of functions to implement
+    ) => {
+        #[cfg(test)]
+        #[allow(non_snake_case)]
+        mod $module_name {
+            use super::*;
+            use tempfile::Builder;
+
+            $(
+                #[test]
+                fn $function_name() {
+                    let dir = Builder::new().tempdir().unwrap();
+                    let path = dir.path().to_str().unwrap();
+                    let trie = verkle_trie_new(
+                        DatabaseScheme::$database_enum,
+                        CommitScheme::$commit_enum,
+                        utils::str_to_cstr(path),
+                    );
+                    proof_json_test_helper::$function_name(trie);
+                }
+            )*
+        }
+    };
+}
+
+proof_json_test![
+    MemoryTest;
+    MemoryDb;
+    TestCommitment;
+    json_proof_round_trip,
+    json_proof_to_multiple_proof,
+    json_proof_rejects_bad_input
+];
+
+#[cfg(feature = "rocks_db")]
+proof_json_test![
+    RocksdbPrelagrange;
+    RocksDb;
+    PrecomputeLagrange;
+    json_proof_round_trip,
+    json_proof_to_multiple_proof,
+    json_proof_rejects_bad_input
+];
@@ -0,0 +1,262 @@
+use rust_verkle::*;
+
+#[cfg(test)]
+mod proof_ssz_test_helper {
+    use rust_verkle::proof::ssz::*;
+    use rust_verkle::proof::{StemStateDiff, SuffixStateDiff};
+    use rust_verkle::utils::get_boxed_value;
+    use rust_verkle::*;
+    use std::slice;
+
+    const _ONE: [u8; 32] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 1,
+    ];
+    const _ONE32: [u8; 32] = [1; 32];
+
+    const TREE_KEY_VERSION: [u8; 32] = [
+        121, 85, 7, 198, 131, 230, 143, 90, 165, 129, 173, 81, 186, 89, 19, 191, 13, 107, 197, 120,
+        243, 229, 224, 183, 72, 25, 6, 8, 210, 159, 31, 0,
+    ];
+
+    const TREE_KEY_BALANCE: [u8; 32] = [
+        121, 85, 7, 198, 131, 230, 143, 90, 165, 129, 173, 81, 186, 89, 19, 191, 13, 107, 197, 120,
+        243, 229, 224, 183, 72, 25, 6, 8, 210, 159, 31, 1,
+    ];
+
+    const VALUE_2: [u8; 32] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 2,
+    ];
+
+    // Encoding of a StateDiff with two stems: the first one with a present and an absent
+    // suffix, the second one with only a new value. The bytes were checked by hand against the
+    // SSZ layout of the execution witness, they do not come from another client, so they only
+    // catch changes of this encoder
+    // TODO: add the execution witness vectors of go-verkle or geth, with their source, to check
+    // that other clients read what this encoder writes
+    const STATE_DIFF_GOLDEN: &str = "08000000690000001111111111111111111111111111111111111111111111111111111111111123000000080000003300000000090000002a0000000122222222222222222222222222222222222222222222222222222222222222220005090000000a0000000000aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa2300000004000000ff090000000a00000000010101010101010101010101010101010101010101010101010101010101010101";
+
+    fn proof_to_vec(proof: *mut Proof) -> Vec<u8> {
+        let proof = unsafe { &*proof };
+        unsafe { slice::from_raw_parts(proof.ptr, proof.len) }.to_vec()
+    }
+
+    fn golden_state_diff() -> Vec<StemStateDiff> {
+        vec![
+            StemStateDiff {
+                stem: [0x11; 31],
+                suffix_diffs: vec![
+                    SuffixStateDiff {
+                        suffix: 0,
+                        current_value: Some([0x22; 32]),
+                        new_value: None,
+                    },
+                    SuffixStateDiff {
+                        suffix: 5,
+                        current_value: None,
+                        new_value: None,
+                    },
+                ],
+            },
+            StemStateDiff {
+                stem: [0xaa; 31],
+                suffix_diffs: vec![SuffixStateDiff {
+                    suffix: 255,
+                    current_value: None,
+                    new_value: Some([0x01; 32]),
+                }],
+            },
+        ]
+    }
+
+    pub fn state_diff_golden_bytes() {
+        let encoded = encode_state_diff(&golden_state_diff());
+        assert_eq!(hex::encode(&encoded), STATE_DIFF_GOLDEN);
+
+        let decoded = decode_state_diff(&hex::decode(STATE_DIFF_GOLDEN).unwrap()).unwrap();
+        assert_eq!(decoded, golden_state_diff());
+    }
+
+    pub fn state_diff_rejects_bad_input() {
+        let mut bytes = hex::decode(STATE_DIFF_GOLDEN).unwrap();
+        assert!(decode_state_diff(&bytes[..bytes.len() - 1]).is_err());
+
+        // Break the first offset so that it no longer points after the offset table
+        bytes[0] = 0x07;
+        assert!(decode_state_diff(&bytes).is_err());
+
+        assert!(decode_verkle_proof(&[0u8; 16]).is_err());
+        assert!(decode_ipa_proof(&[0u8; 543]).is_err());
+    }
+
+    pub fn ssz_proof_round_trip(trie: *mut VerkleTrie) {
+        let all_keys = vec![_ONE, _ONE32, TREE_KEY_VERSION, TREE_KEY_BALANCE];
+        let all_vals = vec![_ONE32, _ONE, VALUE_2, VALUE_2];
+        verkle_trie_insert_multiple(trie, all_keys.as_ptr(), all_vals.as_ptr(), all_keys.len());
+
+        let _proof = get_verkle_proof_ssz(trie, all_keys.as_ptr(), all_keys.len());
+        let ssz_bytes = proof_to_vec(_proof);
+
+        let proof = decode_verkle_proof(&ssz_bytes).unwrap();
+        assert_eq!(encode_verkle_proof(&proof), ssz_bytes);
+        // The IPA proof is the last field of the fixed part, after three offsets and `d`
+        assert_eq!(
+            encode_ipa_proof(&proof.proof.open_proof),
+            ssz_bytes[44..588].to_vec()
+        );
+
+        let verification = verify_verkle_proof_ssz(
+            trie,
+            ssz_bytes.as_ptr(),
+            ssz_bytes.len(),
+            all_keys.as_ptr(),
+            all_vals.as_ptr(),
+            all_keys.len(),
+        );
+        assert_eq!(verification, 1);
+
+        let wrong_vals = vec![_ONE, _ONE, VALUE_2, VALUE_2];
+        let verification = verify_verkle_proof_ssz(
+            trie,
+            ssz_bytes.as_ptr(),
+            ssz_bytes.len(),
+            all_keys.as_ptr(),
+            wrong_vals.as_ptr(),
+            all_keys.len(),
+        );
+        assert_eq!(verification, 0);
+    }
+
+    pub fn ssz_proof_format_conversion(trie: *mut VerkleTrie) {
+        let one: *const u8 = get_boxed_value(_ONE);
+        let one32: *const u8 = get_boxed_value(_ONE32);
+        verkle_trie_insert(trie, one, one32);
+        verkle_trie_insert(trie, one32, one);
+
+        let keys = vec![_ONE, _ONE32];
+        let vals = vec![_ONE32, _ONE];
+        let _proof = get_verkle_proof_multiple(trie, keys.as_ptr(), keys.len());
+        let raw_bytes = proof_to_vec(_proof);
+
+        let _ssz_proof = verkle_proof_to_ssz(raw_bytes.as_ptr(), raw_bytes.len());
+        assert!(!_ssz_proof.is_null());
+        let ssz_bytes = proof_to_vec(_ssz_proof);
+        let verification = verify_verkle_proof_ssz(
+            trie,
+            ssz_bytes.as_ptr(),
+            ssz_bytes.len(),
+            keys.as_ptr(),
+            vals.as_ptr(),
+            keys.len(),
+        );
+        assert_eq!(verification, 1);
+
+        let _raw_proof = verkle_proof_from_ssz(ssz_bytes.as_ptr(), ssz_bytes.len());
+        assert!(!_raw_proof.is_null());
+        assert_eq!(proof_to_vec(_raw_proof), raw_bytes);
+
+        let garbage = [0xffu8; 64];
+        assert!(verkle_proof_from_ssz(garbage.as_ptr(), garbage.len()).is_null());
+    }
+
+    pub fn ssz_execution_witness(trie: *mut VerkleTrie) {
+        let all_keys = vec![TREE_KEY_VERSION, TREE_KEY_BALANCE];
+        let all_vals = vec![VALUE_2, _ONE32];
+        verkle_trie_insert_multiple(trie, all_keys.as_ptr(), all_vals.as_ptr(), all_keys.len());
+
+        // _ONE was never inserted so the witness has to prove its absence
+        let proven_keys = vec![TREE_KEY_VERSION, TREE_KEY_BALANCE, _ONE];
+        let _witness = get_execution_witness_ssz(trie, proven_keys.as_ptr(), proven_keys.len());
+        let witness_bytes = proof_to_vec(_witness);
+
+        let witness = decode_execution_witness(&witness_bytes).unwrap();
+        assert_eq!(encode_execution_witness(&witness), witness_bytes);
+        assert_eq!(witness.state_diff.len(), 2);
+        let (keys, values) = witness.keys_values();
+        assert_eq!(keys, vec![_ONE, TREE_KEY_VERSION, TREE_KEY_BALANCE]);
+        assert_eq!(values, vec![None, Some(VALUE_2), Some(_ONE32)]);
+
+        let verification =
+            verify_execution_witness_ssz(trie, witness_bytes.as_ptr(), witness_bytes.len());
+        assert_eq!(verification, 1);
+    }
+}
+
+#[test]
+fn state_diff_golden_bytes() {
+    proof_ssz_test_helper::state_diff_golden_bytes();
+}
+
+#[test]
+fn state_diff_rejects_bad_input() {
+    proof_ssz_test_helper::state_diff_rejects_bad_input();
+}
+
+macro_rules! proof_ssz_test {
+    (
+        $module_name: ident;   // Module Name
+        $database_enum: ident;  // Database enum
+        $commit_enum: ident; // Commit enum
+        $($function_name: ident),*  // list of functions to implement
+    ) => {
+        #[cfg(test)]
+        #[allow(non_snake_case)]
+        mod $module_name {
+            use super::*;
+            use tempfile::Builder;
+
+            $(
+                #[test]
+                fn $function_name() {
+                    let dir = Builder::new().tempdir().unwrap();
+                    let path = dir.path().to_str().unwrap();
+                    let trie = verkle_trie_new(
+                        DatabaseScheme::$database_enum,
+                        CommitScheme::$commit_enum,
+                        utils::str_to_cstr(path),
+                    );
+                    proof_ssz_test_helper::$function_name(trie);
+                }
+            )*
+        }
+    };
+}
+
+proof_ssz_test![
+    MemoryTest;
+    MemoryDb;
+    TestCommitment;
+    ssz_proof_round_trip,
+    ssz_proof_format_conversion,
+    ssz_execution_witness
+];
+
+#[cfg(feature = "rocks_db")]
+proof_ssz_test![
+    RocksdbTest;
+    RocksDb;
+    TestCommitment;
+    ssz_proof_round_trip,
+    ssz_proof_format_conversion,
+    ssz_execution_witness
+];
+
+proof_ssz_test![
+    MemoryPrelagrange;
+    MemoryDb;
+    PrecomputeLagrange;
+    ssz_proof_round_trip,
+    ssz_proof_format_conversion,
+    ssz_execution_witness
+];
+
+#[cfg(feature = "rocks_db")]
+proof_ssz_test![
+    RocksdbPrelagrange;
+    RocksDb;
+    PrecomputeLagrange;
+    ssz_proof_round_trip,
+    ssz_proof_format_conversion,
+    ssz_execution_witness
+];
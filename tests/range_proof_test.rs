@@ -0,0 +1,266 @@
+use rust_verkle::*;
+
+#[cfg(test)]
+mod range_proof_test_helper {
+    use rust_verkle::*;
+    use std::slice;
+
+    fn leaves() -> Vec<([u8; 32], [u8; 32])> {
+        let mut leaves = Vec::new();
+        for i in [10u8, 20, 30, 40, 50, 60] {
+            for suffix in [0u8, 1, 128] {
+                let mut key = [i; 32];
+                key[31] = suffix;
+                leaves.push((key, [i ^ suffix; 32]));
+            }
+        }
+        // Two stems sharing their first byte, so that the trie has a deeper branch
+        let mut key = [20u8; 32];
+        key[1] = 21;
+        leaves.push((key, [7u8; 32]));
+        leaves.sort();
+        leaves
+    }
+
+    fn insert_leaves(trie: *mut VerkleTrie, leaves: &[([u8; 32], [u8; 32])]) {
+        let keys: Vec<_> = leaves.iter().map(|(key, _)| *key).collect();
+        let vals: Vec<_> = leaves.iter().map(|(_, val)| *val).collect();
+        verkle_trie_insert_multiple(trie, keys.as_ptr(), vals.as_ptr(), keys.len());
+    }
+
+    fn root_commitment(trie: *mut VerkleTrie) -> Vec<u8> {
+        let commitment = unsafe { &*get_root_commitment(trie) };
+        unsafe { slice::from_raw_parts(commitment.ptr, commitment.len) }.to_vec()
+    }
+
+    // Copy the served leaves and the proof out of the range and free it
+    fn take_range(range: *mut LeafRange) -> (Vec<([u8; 32], [u8; 32])>, Vec<u8>) {
+        let taken = {
+            let range = unsafe { &*range };
+            let keys = unsafe { slice::from_raw_parts(range.keys, range.len) };
+            let values = unsafe { slice::from_raw_parts(range.values, range.len) };
+            let proof = unsafe { slice::from_raw_parts(range.proof.ptr, range.proof.len) };
+            let leaves = keys.iter().copied().zip(values.iter().copied()).collect();
+            (leaves, proof.to_vec())
+        };
+        verkle_leaf_range_free(range);
+        taken
+    }
+
+    fn verify(
+        root: &[u8],
+        first_key: [u8; 32],
+        leaves: &[([u8; 32], [u8; 32])],
+        proof: &[u8],
+    ) -> u8 {
+        let keys: Vec<_> = leaves.iter().map(|(key, _)| *key).collect();
+        let vals: Vec<_> = leaves.iter().map(|(_, val)| *val).collect();
+        verify_range_proof(
+            root.as_ptr(),
+            root.len(),
+            first_key.as_ptr(),
+            keys.as_ptr(),
+            vals.as_ptr(),
+            keys.len(),
+            proof.as_ptr(),
+            proof.len(),
+        )
+    }
+
+    pub fn prove_middle_range(trie: *mut VerkleTrie) {
+        let all = leaves();
+        insert_leaves(trie, &all);
+        let root = root_commitment(trie);
+
+        let first_key = [15u8; 32];
+        let last_key = [45u8; 32];
+        let (served, proof) = take_range(get_range_proof(
+            trie,
+            first_key.as_ptr(),
+            last_key.as_ptr(),
+            100,
+        ));
+
+        let expected: Vec<_> = all
+            .iter()
+            .filter(|(key, _)| *key >= first_key && *key <= last_key)
+            .copied()
+            .collect();
+        assert_eq!(served, expected);
+        assert_eq!(verify(&root, first_key, &served, &proof), 1);
+
+        // Hiding a leaf in the middle of the range must be detected
+        let mut missing = served.clone();
+        missing.remove(3);
+        assert_eq!(verify(&root, first_key, &missing, &proof), 0);
+
+        // So must a modified value
+        let mut modified = served.clone();
+        modified[0].1 = [0xee; 32];
+        assert_eq!(verify(&root, first_key, &modified, &proof), 0);
+
+        // And a proof checked against another root
+        insert_leaves(trie, &[([33u8; 32], [1u8; 32])]);
+        let new_root = root_commitment(trie);
+        assert_eq!(verify(&new_root, first_key, &served, &proof), 0);
+    }
+
+    pub fn prove_limited_range(trie: *mut VerkleTrie) {
+        let all = leaves();
+        insert_leaves(trie, &all);
+        let root = root_commitment(trie);
+
+        let first_key = [0u8; 32];
+        let last_key = [0xffu8; 32];
+        let (served, proof) = take_range(get_range_proof(
+            trie,
+            first_key.as_ptr(),
+            last_key.as_ptr(),
+            5,
+        ));
+        assert_eq!(served, all[..5].to_vec());
+        assert_eq!(verify(&root, first_key, &served, &proof), 1);
+
+        // Continue from the key after the last served leaf
+        let mut next_key = served[4].0;
+        next_key[31] += 1;
+        let (served, proof) = take_range(get_range_proof(
+            trie,
+            next_key.as_ptr(),
+            last_key.as_ptr(),
+            100,
+        ));
+        assert_eq!(served, all[5..].to_vec());
+        assert_eq!(verify(&root, next_key, &served, &proof), 1);
+    }
+
+    pub fn prove_empty_range(trie: *mut VerkleTrie) {
+        let all = leaves();
+        insert_leaves(trie, &all);
+        let root = root_commitment(trie);
+
+        // Nothing lives between 41.. and 49.., the next leaf is served to close the gap
+        let first_key = [41u8; 32];
+        let last_key = [49u8; 32];
+        let (served, proof) = take_range(get_range_proof(
+            trie,
+            first_key.as_ptr(),
+            last_key.as_ptr(),
+            100,
+        ));
+        assert_eq!(served.len(), 1);
+        assert_eq!(
+            served[0],
+            all.iter().find(|(key, _)| key[0] == 50).copied().unwrap()
+        );
+        assert_eq!(verify(&root, first_key, &served, &proof), 1);
+
+        // Past the last leaf the proof shows that there is nothing left
+        let first_key = [61u8; 32];
+        let (served, proof) = take_range(get_range_proof(
+            trie,
+            first_key.as_ptr(),
+            first_key.as_ptr(),
+            100,
+        ));
+        assert!(served.is_empty());
+        assert_eq!(verify(&root, first_key, &[], &proof), 1);
+
+        // Freeing null is a no-op, as with free()
+        verkle_leaf_range_free(std::ptr::null_mut());
+    }
+
+    pub fn prove_sparse_stem(trie: *mut VerkleTrie) {
+        let mut all = leaves();
+        // A stem with 254 missing suffixes between its two leaves
+        let mut low_key = [70u8; 32];
+        low_key[31] = 0;
+        let mut high_key = [70u8; 32];
+        high_key[31] = 255;
+        all.push((low_key, [1u8; 32]));
+        all.push((high_key, [2u8; 32]));
+        insert_leaves(trie, &all);
+        let root = root_commitment(trie);
+
+        // The range starts in the middle of the stem, the leaf before it is carried along
+        // with the proof so that the missing suffixes are proven by the stem commitment
+        let mut first_key = [70u8; 32];
+        first_key[31] = 1;
+        let (served, proof) = take_range(get_range_proof(
+            trie,
+            first_key.as_ptr(),
+            high_key.as_ptr(),
+            100,
+        ));
+        assert_eq!(served, vec![(high_key, [2u8; 32])]);
+        assert_eq!(verify(&root, first_key, &served, &proof), 1);
+        assert!(proof.len() < 254 * 32);
+
+        // A leaf in the middle of the stem cannot be hidden
+        let mut middle_key = [70u8; 32];
+        middle_key[31] = 100;
+        insert_leaves(trie, &[(middle_key, [3u8; 32])]);
+        let root = root_commitment(trie);
+        assert_eq!(verify(&root, first_key, &served, &proof), 0);
+        let (served, proof) = take_range(get_range_proof(
+            trie,
+            first_key.as_ptr(),
+            high_key.as_ptr(),
+            100,
+        ));
+        assert_eq!(served.len(), 2);
+        assert_eq!(verify(&root, first_key, &served, &proof), 1);
+        assert_eq!(verify(&root, first_key, &served[1..], &proof), 0);
+    }
+}
+
+macro_rules! range_proof_test {
+    (
+        $module_name: ident;   // Module Name
+        $database_enum: ident;  // Database enum
+        $commit_enum: ident; // Commit enum
+        $($function_name: ident),*  // list of functions to implement
+    ) => {
+        #[cfg(test)]
+        #[allow(non_snake_case)]
+        mod $module_name {
+            use super::*;
+            use tempfile::Builder;
+
+            $(
+                #[test]
+                fn $function_name() {
+                    let dir = Builder::new().tempdir().unwrap();
+                    let path = dir.path().to_str().unwrap();
+                    let trie = verkle_trie_new(
+                        DatabaseScheme::$database_enum,
+                        CommitScheme::$commit_enum,
+                        utils::str_to_cstr(path),
+                    );
+                    range_proof_test_helper::$function_name(trie);
+                }
+            )*
+        }
+    };
+}
+
+range_proof_test![
+    MemoryTest;
+    MemoryDb;
+    TestCommitment;
+    prove_middle_range,
+    prove_limited_range,
+    prove_empty_range,
+    prove_sparse_stem
+];
+
+#[cfg(feature = "rocks_db")]
+range_proof_test![
+    RocksdbPrelagrange;
+    RocksDb;
+    PrecomputeLagrange;
+    prove_middle_range,
+    prove_limited_range,
+    prove_empty_range,
+    prove_sparse_stem
+];
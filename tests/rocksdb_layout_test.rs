@@ -0,0 +1,259 @@
+#[cfg(test)]
+mod rocksdb_layout_test_helper {
+    use rocksdb::{IteratorMode, Options, WriteBatch, DB};
+    use rust_verkle::utils::{assert_value, str_to_cstr};
+    use rust_verkle::*;
+    use std::path::Path;
+    use tempfile::Builder;
+
+    const FAMILIES: [&str; 3] = ["leaves", "stems", "branches"];
+    const FORMAT_VERSION_KEY: &[u8] = b"\xffformat_version";
+    // Every node written by `write_trie`, one `<family> <key> <value>` line in hex per node
+    // Set `VERKLE_BLESS` to record it again, which is only right along with a schema bump
+    const GOLDEN_LAYOUT: &str = "tests/golden/rocksdb_layout.txt";
+
+    fn leaf(i: u8) -> ([u8; 32], [u8; 32]) {
+        let mut key = [i; 32];
+        key[31] = i % 5;
+        (key, [i.wrapping_mul(3); 32])
+    }
+
+    // Root of a trie of 60 leaves flushed into a new RocksDB database at `path`
+    pub fn write_trie(path: &str) -> [u8; 32] {
+        let db = create_verkle_db(DatabaseScheme::RocksDb, str_to_cstr(path));
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        for i in 0..60u8 {
+            let (key, value) = leaf(i);
+            verkle_trie_insert(trie, key.as_ptr(), value.as_ptr());
+        }
+        let root = unsafe { *(get_root_hash(trie) as *const [u8; 32]) };
+        assert_eq!(verkle_trie_flush_block(trie, 5), 1);
+        assert_eq!(verkle_trie_free(trie), 1);
+        assert_eq!(verkle_db_free(db), 1);
+        root
+    }
+
+    fn open_raw(path: &str) -> DB {
+        DB::open_cf(&Options::default(), path, FAMILIES).unwrap()
+    }
+
+    // Rewrite the database as it was stored before column families: every key in the default
+    // column family and no format marker
+    pub fn to_single_keyspace(path: &str) {
+        let raw = open_raw(path);
+        let mut batch = WriteBatch::default();
+        for name in FAMILIES.iter() {
+            let family = raw.cf_handle(name).unwrap();
+            for (key, value) in raw.iterator_cf(family, IteratorMode::Start) {
+                batch.put(&*key, &*value);
+                batch.delete_cf(family, &*key);
+            }
+        }
+        batch.delete(FORMAT_VERSION_KEY);
+        raw.write(batch).unwrap();
+    }
+
+    pub fn assert_trie(path: &str, root: [u8; 32]) {
+        let db = create_verkle_db(DatabaseScheme::RocksDb, str_to_cstr(path));
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        assert_value(get_root_hash(trie), root);
+        for i in 0..60u8 {
+            let (key, value) = leaf(i);
+            assert_value(verkle_trie_get(trie, key.as_ptr()), value);
+        }
+        let mut block = 0u64;
+        let mut recorded_root = [0u8; 32];
+        assert_eq!(
+            get_last_flushed_block(db, &mut block, recorded_root.as_mut_ptr()),
+            1
+        );
+        assert_eq!(block, 5);
+
+        let report = unsafe { &*check_db_integrity(CommitScheme::TestCommitment, db) };
+        assert_eq!(report.leaves, 60);
+        assert_eq!(
+            (report.mismatched, report.dangling, report.orphans),
+            (0, 0, 0)
+        );
+        assert_eq!(verkle_trie_free(trie), 1);
+        assert_eq!(verkle_db_free(db), 1);
+    }
+
+    // Every node is in the column family of its kind, none is left in the default one
+    pub fn assert_column_families(path: &str) {
+        let raw = open_raw(path);
+        for (marker, name) in FAMILIES.iter().enumerate() {
+            let family = raw.cf_handle(name).unwrap();
+            let mut keys = raw.iterator_cf(family, IteratorMode::Start).peekable();
+            assert!(keys.peek().is_some(), "{} is empty", name);
+            assert!(keys.all(|(key, _)| key[0] == marker as u8));
+        }
+        assert!(raw
+            .iterator(IteratorMode::Start)
+            .all(|(key, _)| key[0] == 0xff));
+        assert_eq!(
+            raw.get(FORMAT_VERSION_KEY).unwrap(),
+            Some(2u32.to_le_bytes().to_vec())
+        );
+    }
+
+    // Hex dump of the nodes in every column family, in key order
+    fn dump_nodes(path: &str) -> String {
+        let raw = open_raw(path);
+        let mut dump = String::new();
+        for name in FAMILIES.iter() {
+            let family = raw.cf_handle(name).unwrap();
+            for (key, value) in raw.iterator_cf(family, IteratorMode::Start) {
+                dump.push_str(&format!(
+                    "{} {} {}\n",
+                    name,
+                    hex::encode(&key),
+                    hex::encode(&value)
+                ));
+            }
+        }
+        dump
+    }
+
+    pub fn set_format_version(path: &str, version: u32) {
+        let raw = open_raw(path);
+        raw.put(FORMAT_VERSION_KEY, version.to_le_bytes()).unwrap();
+    }
+
+    pub fn nodes_in_column_families() {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let root = write_trie(path);
+        assert_column_families(path);
+        assert_trie(path, root);
+    }
+
+    pub fn migrate_single_keyspace() {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let root = write_trie(path);
+        to_single_keyspace(path);
+
+        assert_trie(path, root);
+        assert_column_families(path);
+        // Opening the migrated database again leaves it as it is
+        assert_trie(path, root);
+    }
+
+    // A database written with a newer schema, or one there is no migration from, is not
+    // opened and left as it is
+    pub fn unknown_format_version() {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let root = write_trie(path);
+
+        for version in [3u32, 0] {
+            set_format_version(path, version);
+            let options = RocksDbOptions::default();
+            assert!(create_verkle_db_with_options(str_to_cstr(path), &options).is_null());
+            assert_eq!(verkle_last_error(), VerkleError::UnsupportedSchema);
+            assert!(create_verkle_db(DatabaseScheme::RocksDb, str_to_cstr(path)).is_null());
+            assert_eq!(verkle_last_error(), VerkleError::UnsupportedSchema);
+        }
+
+        set_format_version(path, 2);
+        assert_trie(path, root);
+        assert_eq!(verkle_last_error(), VerkleError::Ok);
+    }
+
+    // A leaf is stored under its table marker followed by the key, its value is stored as is.
+    // Stems and branches are stored under their marker followed by the stem or the path
+    pub fn golden_keys() {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        write_trie(path);
+        let raw = open_raw(path);
+
+        // leaf(7)
+        let mut leaf_key = vec![0x00];
+        leaf_key.extend_from_slice(&[0x07; 31]);
+        leaf_key.push(0x02);
+        let leaves = raw.cf_handle("leaves").unwrap();
+        assert_eq!(raw.get_cf(leaves, &leaf_key).unwrap(), Some(vec![0x15; 32]));
+
+        let mut stem_key = vec![0x01];
+        stem_key.extend_from_slice(&[0x07; 31]);
+        let stems = raw.cf_handle("stems").unwrap();
+        assert!(raw.get_cf(stems, &stem_key).unwrap().is_some());
+
+        // The root, then the slot of the stem of leaf(7) in it
+        let branches = raw.cf_handle("branches").unwrap();
+        assert!(raw.get_cf(branches, [0x02]).unwrap().is_some());
+        assert!(raw.get_cf(branches, [0x02, 0x07]).unwrap().is_some());
+    }
+
+    // The encoding of leaf keys, `StemMeta` and `BranchMeta` matches the recorded copy byte for
+    // byte. If it changes, so does the schema, see `SCHEMA_VERSION`
+    pub fn golden_layout() {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        write_trie(path);
+        let dump = dump_nodes(path);
+
+        let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join(GOLDEN_LAYOUT);
+        if std::env::var_os("VERKLE_BLESS").is_some() || !golden.exists() {
+            std::fs::create_dir_all(golden.parent().unwrap()).unwrap();
+            std::fs::write(&golden, &dump).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&golden).unwrap();
+        for (line, (found, expected)) in dump.lines().zip(expected.lines()).enumerate() {
+            assert_eq!(
+                found, expected,
+                "node {} differs from {}",
+                line, GOLDEN_LAYOUT
+            );
+        }
+        assert_eq!(dump.lines().count(), expected.lines().count());
+    }
+
+    // The version is recorded in a new database before anything is written to it
+    pub fn version_recorded_on_create() {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = create_verkle_db(DatabaseScheme::RocksDb, str_to_cstr(path));
+        assert_eq!(verkle_last_error(), VerkleError::Ok);
+        assert_eq!(verkle_db_free(db), 1);
+
+        let raw = open_raw(path);
+        assert_eq!(
+            raw.get(FORMAT_VERSION_KEY).unwrap(),
+            Some(2u32.to_le_bytes().to_vec())
+        );
+    }
+}
+
+#[test]
+fn nodes_in_column_families() {
+    rocksdb_layout_test_helper::nodes_in_column_families();
+}
+
+#[test]
+fn migrate_single_keyspace() {
+    rocksdb_layout_test_helper::migrate_single_keyspace();
+}
+
+#[test]
+fn unknown_format_version() {
+    rocksdb_layout_test_helper::unknown_format_version();
+}
+
+#[test]
+fn version_recorded_on_create() {
+    rocksdb_layout_test_helper::version_recorded_on_create();
+}
+
+#[test]
+fn golden_keys() {
+    rocksdb_layout_test_helper::golden_keys();
+}
+
+#[test]
+fn golden_layout() {
+    rocksdb_layout_test_helper::golden_layout();
+}
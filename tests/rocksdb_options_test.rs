@@ -0,0 +1,89 @@
+use rust_verkle::*;
+
+#[cfg(test)]
+mod rocksdb_options_test_helper {
+    use rust_verkle::utils::{assert_value, str_to_cstr};
+    use rust_verkle::*;
+    use tempfile::Builder;
+
+    fn leaf(i: u8) -> ([u8; 32], [u8; 32]) {
+        let mut key = [i; 32];
+        key[31] = i % 4;
+        (key, [i.wrapping_add(1); 32])
+    }
+
+    pub fn tuned_options(compression: RocksDbCompression) -> RocksDbOptions {
+        RocksDbOptions {
+            block_cache_size: 8 << 20,
+            bloom_filter_bits: 10,
+            compression,
+            write_buffer_size: 4 << 20,
+            max_open_files: -1,
+        }
+    }
+
+    pub fn zeroed_options() -> RocksDbOptions {
+        RocksDbOptions {
+            block_cache_size: 0,
+            bloom_filter_bits: 0,
+            compression: RocksDbCompression::Snappy,
+            write_buffer_size: 0,
+            max_open_files: 0,
+        }
+    }
+
+    // A database created with options holds the same trie as one created without them, and
+    // can be reopened without them
+    pub fn flush_and_reopen(options: RocksDbOptions) {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = create_verkle_db_with_options(str_to_cstr(path), &options);
+        assert!(!db.is_null());
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        for i in 0..40u8 {
+            let (key, value) = leaf(i);
+            verkle_trie_insert(trie, key.as_ptr(), value.as_ptr());
+        }
+        let root = unsafe { *(get_root_hash(trie) as *const [u8; 32]) };
+        verkle_trie_flush(trie);
+        assert_eq!(verkle_trie_free(trie), 1);
+        assert_eq!(verkle_db_free(db), 1);
+
+        let db = create_verkle_db(DatabaseScheme::RocksDb, str_to_cstr(path));
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        assert_value(get_root_hash(trie), root);
+        for i in 0..40u8 {
+            let (key, value) = leaf(i);
+            assert_value(verkle_trie_get(trie, key.as_ptr()), value);
+        }
+        assert_eq!(verkle_trie_free(trie), 1);
+        assert_eq!(verkle_db_free(db), 1);
+    }
+}
+
+#[test]
+fn null_options() {
+    let dir = tempfile::Builder::new().tempdir().unwrap();
+    let path = utils::str_to_cstr(dir.path().to_str().unwrap());
+    assert!(create_verkle_db_with_options(path, std::ptr::null()).is_null());
+}
+
+#[test]
+fn zeroed_options() {
+    rocksdb_options_test_helper::flush_and_reopen(rocksdb_options_test_helper::zeroed_options());
+}
+
+macro_rules! rocksdb_options_test {
+    ($name:ident, $compression:expr) => {
+        #[test]
+        fn $name() {
+            let options = rocksdb_options_test_helper::tuned_options($compression);
+            rocksdb_options_test_helper::flush_and_reopen(options);
+        }
+    };
+}
+
+rocksdb_options_test!(snappy, RocksDbCompression::Snappy);
+rocksdb_options_test!(no_compression, RocksDbCompression::None);
+rocksdb_options_test!(lz4, RocksDbCompression::Lz4);
+rocksdb_options_test!(zstd, RocksDbCompression::Zstd);
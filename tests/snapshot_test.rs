@@ -0,0 +1,183 @@
+use rust_verkle::*;
+
+#[cfg(test)]
+mod snapshot_test_helper {
+    use rust_verkle::utils::{assert_value, get_boxed_value, str_to_cstr};
+    use rust_verkle::*;
+    use std::ffi::CString;
+    use std::slice;
+    use tempfile::Builder;
+
+    fn leaves() -> Vec<([u8; 32], [u8; 32])> {
+        let mut leaves = Vec::new();
+        for i in 0..50u8 {
+            for suffix in [0u8, 1, 128, 255] {
+                let mut key = [i.wrapping_mul(37); 32];
+                key[1] = i;
+                key[31] = suffix;
+                leaves.push((key, [i ^ suffix; 32]));
+            }
+        }
+        leaves
+    }
+
+    fn root_commitment(trie: *mut VerkleTrie) -> Vec<u8> {
+        let commitment = unsafe { &*get_root_commitment(trie) };
+        unsafe { slice::from_raw_parts(commitment.ptr, commitment.len) }.to_vec()
+    }
+
+    // Build a trie with `leaves` in `db_scheme` and export it into every file of `exports`,
+    // together with the flag saying if commitments are included
+    fn export_trie(db_scheme: DatabaseScheme, exports: &[(&CString, u8)]) -> Vec<u8> {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = create_verkle_db(db_scheme, str_to_cstr(path));
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+
+        let all = leaves();
+        let keys: Vec<_> = all.iter().map(|(key, _)| *key).collect();
+        let vals: Vec<_> = all.iter().map(|(_, val)| *val).collect();
+        verkle_trie_insert_multiple(trie, keys.as_ptr(), vals.as_ptr(), keys.len());
+        verkle_trie_flush(trie);
+
+        for (file, include_commitments) in exports {
+            assert_eq!(export_state(trie, file.as_ptr(), *include_commitments), 1);
+        }
+        root_commitment(trie)
+    }
+
+    fn import_trie(db_scheme: DatabaseScheme, file: &CString) -> Option<*mut VerkleTrie> {
+        let dir = Builder::new().tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = create_verkle_db(db_scheme, str_to_cstr(path));
+        if import_state(CommitScheme::TestCommitment, db, file.as_ptr()) == 0 {
+            return None;
+        }
+        Some(create_trie_from_db(CommitScheme::TestCommitment, db))
+    }
+
+    pub fn export_import_round_trip(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let plain = CString::new(dir.path().join("plain.snap").to_str().unwrap()).unwrap();
+        let full = CString::new(dir.path().join("full.snap").to_str().unwrap()).unwrap();
+        let root = export_trie(db_scheme, &[(&plain, 0), (&full, 1)]);
+
+        // Both snapshots can be imported into either backend
+        for file in [&plain, &full] {
+            for target in [DatabaseScheme::MemoryDb, DatabaseScheme::RocksDb] {
+                let trie = import_trie(target, file).expect("import failed");
+                assert_eq!(root_commitment(trie), root);
+                for (key, value) in leaves().iter().step_by(7) {
+                    let val = verkle_trie_get(trie, get_boxed_value(*key));
+                    assert_value(val, *value);
+                }
+            }
+        }
+    }
+
+    pub fn import_rejects_damaged_file(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let file_path = dir.path().join("state.snap");
+        let file = CString::new(file_path.to_str().unwrap()).unwrap();
+        export_trie(db_scheme, &[(&file, 1)]);
+        let original = std::fs::read(&file_path).unwrap();
+
+        // A flipped bit in the first leaf value is caught by the checksum
+        let mut corrupted = original.clone();
+        corrupted[8 + 2 + 1 + 32 + 1 + 31 + 2 + 1] ^= 1;
+        std::fs::write(&file_path, &corrupted).unwrap();
+        assert!(import_trie(DatabaseScheme::MemoryDb, &file).is_none());
+
+        let truncated = &original[..original.len() - 10];
+        std::fs::write(&file_path, truncated).unwrap();
+        assert!(import_trie(DatabaseScheme::MemoryDb, &file).is_none());
+
+        let mut wrong_magic = original.clone();
+        wrong_magic[0] = b'X';
+        std::fs::write(&file_path, &wrong_magic).unwrap();
+        assert!(import_trie(DatabaseScheme::MemoryDb, &file).is_none());
+
+        let missing = CString::new(dir.path().join("missing.snap").to_str().unwrap()).unwrap();
+        assert!(import_trie(DatabaseScheme::MemoryDb, &missing).is_none());
+
+        std::fs::write(&file_path, &original).unwrap();
+        assert!(import_trie(DatabaseScheme::MemoryDb, &file).is_some());
+    }
+
+    // A failed import writes no root, so the database can be imported into again, but a
+    // database that holds a trie is never imported into
+    pub fn import_needs_empty_target(db_scheme: DatabaseScheme) {
+        let dir = Builder::new().tempdir().unwrap();
+        let file_path = dir.path().join("state.snap");
+        let file = CString::new(file_path.to_str().unwrap()).unwrap();
+        let root = export_trie(db_scheme, &[(&file, 0)]);
+        let original = std::fs::read(&file_path).unwrap();
+
+        let db_dir = Builder::new().tempdir().unwrap();
+        let db = create_verkle_db(
+            DatabaseScheme::RocksDb,
+            str_to_cstr(db_dir.path().to_str().unwrap()),
+        );
+        let mut corrupted = original.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        std::fs::write(&file_path, &corrupted).unwrap();
+        assert_eq!(
+            import_state(CommitScheme::TestCommitment, db, file.as_ptr()),
+            0
+        );
+
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        let (key, _) = leaves()[0];
+        assert!(verkle_trie_get(trie, get_boxed_value(key)).is_null());
+        assert_eq!(verkle_trie_free(trie), 1);
+
+        std::fs::write(&file_path, &original).unwrap();
+        assert_eq!(
+            import_state(CommitScheme::TestCommitment, db, file.as_ptr()),
+            1
+        );
+        assert_eq!(
+            import_state(CommitScheme::TestCommitment, db, file.as_ptr()),
+            0
+        );
+        let trie = create_trie_from_db(CommitScheme::TestCommitment, db);
+        assert_eq!(root_commitment(trie), root);
+    }
+}
+
+macro_rules! snapshot_test {
+    (
+        $module_name: ident;   // Module Name
+        $database_enum: ident;  // Database enum
+        $($function_name: ident),*  // list of functions to implement
+    ) => {
+        #[cfg(test)]
+        #[allow(non_snake_case)]
+        mod $module_name {
+            use super::*;
+
+            $(
+                #[test]
+                fn $function_name() {
+                    snapshot_test_helper::$function_name(DatabaseScheme::$database_enum);
+                }
+            )*
+        }
+    };
+}
+
+snapshot_test![
+    MemoryDBSnapshot;
+    MemoryDb;
+    export_import_round_trip,
+    import_rejects_damaged_file,
+    import_needs_empty_target
+];
+
+snapshot_test![
+    RocksDBSnapshot;
+    RocksDb;
+    export_import_round_trip,
+    import_rejects_damaged_file,
+    import_needs_empty_target
+];
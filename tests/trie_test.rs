@@ -60,6 +60,62 @@ mod trie_test_helper {
         assert_eq!(hash, [0u8; 32]);
     }
 
+    pub fn root_commitment(trie: *mut VerkleTrie) {
+        let one: *const u8 = get_boxed_value(_ONE);
+        let one32: *const u8 = get_boxed_value(_ONE32);
+        verkle_trie_insert(trie, one, one32);
+        verkle_trie_insert(trie, one32, one);
+
+        let _commitment = get_root_commitment(trie);
+        let commitment = unsafe { &mut *_commitment };
+        assert_eq!(commitment.len, 32);
+
+        let mut hash = [0u8; 32];
+        assert_eq!(
+            commitment_to_hash(commitment.ptr, commitment.len, hash.as_mut_ptr()),
+            1
+        );
+        let root_hash = get_array_from_slice_argument(get_root_hash(trie));
+        assert_eq!(hash, root_hash);
+
+        // A truncated commitment is not a point
+        assert_eq!(commitment_to_hash(commitment.ptr, 31, hash.as_mut_ptr()), 0);
+        verkle_proof_free(_commitment);
+    }
+
+    pub fn node_meta(trie: *mut VerkleTrie) {
+        let one: *const u8 = get_boxed_value(_ONE);
+        let one32: *const u8 = get_boxed_value(_ONE32);
+        verkle_trie_insert(trie, one, one32);
+        verkle_trie_insert(trie, one32, one);
+
+        let _root_meta = get_branch_meta(trie, std::ptr::null(), 0);
+        assert!(!_root_meta.is_null());
+        let root_meta = unsafe { &*_root_meta };
+        assert_eq!(root_meta.len, 64);
+        let root_meta = unsafe { std::slice::from_raw_parts(root_meta.ptr, root_meta.len) };
+
+        let _commitment = get_root_commitment(trie);
+        let commitment = unsafe { &*_commitment };
+        let commitment = unsafe { std::slice::from_raw_parts(commitment.ptr, commitment.len) };
+        assert_eq!(&root_meta[..32], commitment);
+        assert_eq!(
+            get_array_from_slice_argument(root_meta[32..].as_ptr()),
+            get_array_from_slice_argument(get_root_hash(trie))
+        );
+
+        let stem = [1u8; 31];
+        let _stem_meta = get_stem_meta(trie, stem.as_ptr());
+        assert!(!_stem_meta.is_null());
+        let stem_meta = unsafe { &*_stem_meta };
+        assert_eq!(stem_meta.len, 192);
+
+        let missing_stem = [2u8; 31];
+        assert!(get_stem_meta(trie, missing_stem.as_ptr()).is_null());
+        let missing_path = [2u8, 3u8];
+        assert!(get_branch_meta(trie, missing_path.as_ptr(), missing_path.len()).is_null());
+    }
+
     pub fn insert_fetch(trie: *mut VerkleTrie) {
         let one: *const u8 = get_boxed_value(_ONE);
         let one32: *const u8 = get_boxed_value(_ONE32);
@@ -191,6 +247,59 @@ mod trie_test_helper {
         );
         assert_eq!(verification, 1);
     }
+
+    // Proofs come from peers, anything that does not parse is rejected instead of panicking
+    pub fn reject_malformed_proof(trie: *mut VerkleTrie) {
+        let one: *const u8 = get_boxed_value(_ONE);
+        let one32: *const u8 = get_boxed_value(_ONE32);
+
+        verkle_trie_insert(trie, one, one);
+        verkle_trie_insert(trie, one32, one);
+        let _proof = get_verkle_proof(trie, one);
+        let proof = unsafe { &mut *_proof };
+
+        let verif = verify_verkle_proof(trie, std::ptr::null(), 0, one, one);
+        assert_eq!(verif, 0);
+        let verif = verify_verkle_proof(trie, proof.ptr, proof.len / 2, one, one);
+        assert_eq!(verif, 0);
+        let garbage = [0xffu8; 200];
+        let verif = verify_verkle_proof(trie, garbage.as_ptr(), garbage.len(), one, one);
+        assert_eq!(verif, 0);
+
+        let bytes = unsafe { std::slice::from_raw_parts(proof.ptr, proof.len) }.to_vec();
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        let verif = verify_verkle_proof(trie, trailing.as_ptr(), trailing.len(), one, one);
+        assert_eq!(verif, 0);
+        // No stems without proof, so the hint byte of the only stem follows the two counts
+        let mut bad_status = bytes.clone();
+        bad_status[8] |= 0b111;
+        let verif = verify_verkle_proof(trie, bad_status.as_ptr(), bad_status.len(), one, one);
+        assert_eq!(verif, 0);
+        // A point that is not on the curve in place of the last commitment
+        let mut bad_point = bytes;
+        let last_point = bad_point.len() - 32 * 19;
+        bad_point[last_point..last_point + 32].copy_from_slice(&[0xff; 32]);
+        let verif = verify_verkle_proof(trie, bad_point.as_ptr(), bad_point.len(), one, one);
+        assert_eq!(verif, 0);
+
+        let no_keys: Vec<[u8; 32]> = Vec::new();
+        let verif = verify_verkle_proof_multiple(
+            trie,
+            proof.ptr,
+            proof.len,
+            no_keys.as_ptr(),
+            no_keys.as_ptr(),
+            0,
+        );
+        assert_eq!(verif, 0);
+
+        // An empty batch does not change the trie
+        let root = unsafe { *(get_root_hash(trie) as *const [u8; 32]) };
+        verkle_trie_insert_multiple(trie, std::ptr::null(), std::ptr::null(), 0);
+        let root_after = unsafe { *(get_root_hash(trie) as *const [u8; 32]) };
+        assert_eq!(root, root_after);
+    }
 }
 
 macro_rules! trie_test {
@@ -228,21 +337,28 @@ trie_test![
     MemoryDb;
     TestCommitment;
     root_hash,
+    root_commitment,
+    node_meta,
     insert_fetch,
     insert_account_fetch,
     gen_verify_proof,
-    generate_proof_test
+    generate_proof_test,
+    reject_malformed_proof
 ];
 
+#[cfg(feature = "rocks_db")]
 trie_test![
     RocksdbTest;
     RocksDb;
     TestCommitment;
     root_hash,
+    root_commitment,
+    node_meta,
     insert_fetch,
     insert_account_fetch,
     gen_verify_proof,
     generate_proof_test,
+    reject_malformed_proof,
     insert_fetch_flush_clear
 ];
 
@@ -251,21 +367,28 @@ trie_test![
     MemoryDb;
     PrecomputeLagrange;
     root_hash,
+    root_commitment,
+    node_meta,
     insert_fetch,
     insert_account_fetch,
     gen_verify_proof,
-    generate_proof_test
+    generate_proof_test,
+    reject_malformed_proof
 ];
 
+#[cfg(feature = "rocks_db")]
 trie_test![
     RocksdbPrelagrange;
     RocksDb;
     PrecomputeLagrange;
     root_hash,
+    root_commitment,
+    node_meta,
     insert_fetch,
     insert_account_fetch,
     gen_verify_proof,
     generate_proof_test,
+    reject_malformed_proof,
     insert_fetch_flush_clear
 ];
 
@@ -303,17 +426,22 @@ trie_from_db_test![
     MemoryDb;
     TestCommitment;
     root_hash,
+    root_commitment,
+    node_meta,
     insert_fetch,
     insert_account_fetch,
     gen_verify_proof,
     generate_proof_test
 ];
 
+#[cfg(feature = "rocks_db")]
 trie_from_db_test![
     RocksdbTestDB;
     RocksDb;
     TestCommitment;
     root_hash,
+    root_commitment,
+    node_meta,
     insert_fetch,
     insert_account_fetch,
     gen_verify_proof,
@@ -326,17 +454,22 @@ trie_from_db_test![
     MemoryDb;
     PrecomputeLagrange;
     root_hash,
+    root_commitment,
+    node_meta,
     insert_fetch,
     insert_account_fetch,
     gen_verify_proof,
     generate_proof_test
 ];
 
+#[cfg(feature = "rocks_db")]
 trie_from_db_test![
     RocksdbPrelagrangeDB;
     RocksDb;
     PrecomputeLagrange;
     root_hash,
+    root_commitment,
+    node_meta,
     insert_fetch,
     insert_account_fetch,
     gen_verify_proof,
@@ -379,17 +512,22 @@ trie_from_read_only_db_test![
     MemoryDb;
     TestCommitment;
     root_hash,
+    root_commitment,
+    node_meta,
     insert_fetch,
     insert_account_fetch,
     gen_verify_proof,
     generate_proof_test
 ];
 
+#[cfg(feature = "rocks_db")]
 trie_from_read_only_db_test![
     RocksdbTestReadOnlyDB;
     RocksDb;
     TestCommitment;
     root_hash,
+    root_commitment,
+    node_meta,
     insert_fetch,
     insert_account_fetch,
     gen_verify_proof,
@@ -402,17 +540,22 @@ trie_from_read_only_db_test![
     MemoryDb;
     PrecomputeLagrange;
     root_hash,
+    root_commitment,
+    node_meta,
     insert_fetch,
     insert_account_fetch,
     gen_verify_proof,
     generate_proof_test
 ];
 
+#[cfg(feature = "rocks_db")]
 trie_from_read_only_db_test![
     RocksdbPrelagrangeReadOnlyDB;
     RocksDb;
     PrecomputeLagrange;
     root_hash,
+    root_commitment,
+    node_meta,
     insert_fetch,
     insert_account_fetch,
     gen_verify_proof,